@@ -0,0 +1,54 @@
+use crate::state::read_state;
+use candid::Principal;
+
+/// Ensure the caller is a controller of this canister.
+pub fn ensure_controller() -> Result<Principal, String> {
+    let caller = caller();
+    if is_controller(&caller) {
+        Ok(caller)
+    } else {
+        Err(format!("Caller {} is not a controller", caller))
+    }
+}
+
+/// Ensure the caller is a controller or one of the explicitly authorized principals.
+pub fn ensure_authorized() -> Result<Principal, String> {
+    let caller = caller();
+    if is_controller(&caller) || read_state(|s| s.authorized_principals.contains(&caller)) {
+        Ok(caller)
+    } else {
+        Err(format!("Caller {} is not authorized", caller))
+    }
+}
+
+#[cfg(not(test))]
+fn caller() -> Principal {
+    ic_cdk::caller()
+}
+
+#[cfg(not(test))]
+fn is_controller(principal: &Principal) -> bool {
+    ic_cdk::api::is_controller(principal)
+}
+
+// Unit tests run outside a canister, where the caller and controller system calls
+// are unavailable; tests pick the caller, and no principal is a controller
+#[cfg(test)]
+thread_local! {
+    static CALLER: std::cell::Cell<Principal> = const { std::cell::Cell::new(Principal::anonymous()) };
+}
+
+#[cfg(test)]
+fn caller() -> Principal {
+    CALLER.with(std::cell::Cell::get)
+}
+
+#[cfg(test)]
+fn is_controller(_principal: &Principal) -> bool {
+    false
+}
+
+#[cfg(test)]
+pub fn set_caller(principal: Principal) {
+    CALLER.with(|caller| caller.set(principal));
+}
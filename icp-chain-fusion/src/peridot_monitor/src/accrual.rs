@@ -0,0 +1,101 @@
+use crate::contracts::IPToken;
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::state::{market_key, read_state, State, UserPosition};
+use alloy::primitives::Address;
+use alloy::providers::ProviderBuilder;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Stored vs. interest-accrued borrow balance for one account in one market.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct BorrowBalanceComparison {
+    pub user: String,
+    pub market: String,
+    pub chain_id: u64,
+    pub stored: String,
+    pub current: String,
+    pub accrued: String,
+    pub stale_since_blocks: Option<u64>,
+}
+
+/// Blocks between the market's last `AccrueInterest` and the chain's sync cursor.
+/// `None` until both are known.
+pub fn market_stale_since_blocks(state: &State, chain_id: u64, market: &str) -> Option<u64> {
+    let head = state.head_blocks.get(&chain_id)?;
+    let last_accrual = state.market_states.get(&market_key(chain_id, market))?.last_accrual_block;
+    (last_accrual > 0).then(|| head.saturating_sub(last_accrual))
+}
+
+/// Staleness of the least recently accrued market the position owes debt in, or
+/// `None` when it has no debt or no borrowed market's accrual is known.
+pub fn position_stale_since_blocks(state: &State, position: &UserPosition) -> Option<u64> {
+    position.borrow_balances.iter()
+        .filter(|(_, amount)| *amount > 0)
+        .filter_map(|(market, _)| market_stale_since_blocks(state, position.chain_id, market))
+        .max()
+}
+
+/// Read both the stored borrow balance and `borrowBalanceCurrent`, which accrues
+/// interest first. The latter is a state-changing function, so it runs as an
+/// `eth_call` simulation and nothing is written on-chain.
+pub async fn current_borrow_balance(user: &str, market: &str, chain_id: u64) -> Result<BorrowBalanceComparison, String> {
+    let account = Address::from_str(user).map_err(|e| format!("Invalid user address: {}", e))?;
+    let market_address = Address::from_str(market).map_err(|e| format!("Invalid market address: {}", e))?;
+
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let p_token = IPToken::new(market_address, provider);
+
+    let stored = p_token.borrowBalanceStored(account).call().await
+        .map_err(|e| format!("borrowBalanceStored failed: {}", e))?._0;
+    let current = p_token.borrowBalanceCurrent(account).call().await
+        .map_err(|e| format!("borrowBalanceCurrent failed: {}", e))?._0;
+
+    Ok(BorrowBalanceComparison {
+        user: user.to_string(),
+        market: market.to_string(),
+        chain_id,
+        stored: stored.to_string(),
+        current: current.to_string(),
+        accrued: current.saturating_sub(stored).to_string(),
+        stale_since_blocks: read_state(|s| market_stale_since_blocks(s, chain_id, market)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::new_market_state;
+    use crate::state::{init_test_state, mutate_state};
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    #[test]
+    fn staleness_counts_blocks_since_the_borrowed_markets_accrued() {
+        init_test_state();
+        let fresh = Address::repeat_byte(0x01);
+        let stale = Address::repeat_byte(0x02);
+        mutate_state(|s| {
+            for (market, accrued) in [(fresh, 995), (stale, 900)] {
+                let mut state = new_market_state(10143, market);
+                state.last_accrual_block = accrued;
+                s.market_states.insert(market_key(10143, &state.market_address), state);
+            }
+        });
+        let mut position = user_position(10143);
+        position.borrow_balances = vec![(fresh.to_string(), 10), (stale.to_string(), 0)];
+
+        // No cursor yet
+        assert_eq!(read_state(|s| position_stale_since_blocks(s, &position)), None);
+
+        mutate_state(|s| s.record_synced_block(10143, 1_000));
+        assert_eq!(read_state(|s| position_stale_since_blocks(s, &position)), Some(5));
+        position.borrow_balances[1].1 = 10;
+        assert_eq!(read_state(|s| position_stale_since_blocks(s, &position)), Some(100));
+        assert_eq!(read_state(|s| market_stale_since_blocks(s, 10143, &stale.to_string())), Some(100));
+    }
+}
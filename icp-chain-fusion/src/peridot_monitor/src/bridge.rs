@@ -0,0 +1,205 @@
+use crate::logger;
+use crate::source_proof::SourceProof;
+use alloy::primitives::{Address, B256, U256};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Monad testnet USDC the mock bridge pretends every inbound transfer arrives as.
+const MOCK_MONAD_USDC: &str = "0x28fE679719e740D15FC60325416bB43eAc50cD15";
+const MOCK_BRIDGE_TX_HASH: &str = "0x1234567890abcdef1234567890abcdef12345678";
+/// Deposit the mock bridge reports for inbound transfers, paid into a placeholder escrow.
+const MOCK_DEPOSIT_TX_HASH: B256 = B256::repeat_byte(0xde);
+const MOCK_BRIDGE_ESCROW: Address = Address::repeat_byte(0xe5);
+/// End-to-end time assumed for a route with no `RouteTiming`.
+pub const DEFAULT_ROUTE_TIME_SECS: u64 = 300;
+
+/// How long each leg of a (source, target) bridge route takes, in seconds.
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct RouteTiming {
+    pub source_finality_secs: u64,
+    pub bridge_latency_secs: u64,
+    pub target_finality_secs: u64,
+}
+
+impl RouteTiming {
+    pub fn total_secs(&self) -> u64 {
+        self.source_finality_secs
+            .saturating_add(self.bridge_latency_secs)
+            .saturating_add(self.target_finality_secs)
+    }
+}
+
+/// Built-in timings for the supported testnet routes into Monad, keyed by
+/// (source chain, target chain).
+pub fn default_route_timings() -> BTreeMap<(u64, u64), RouteTiming> {
+    let into_monad = |source_finality_secs| RouteTiming {
+        source_finality_secs,
+        bridge_latency_secs: 120,
+        target_finality_secs: 2,
+    };
+    BTreeMap::from([
+        ((97, 10143), into_monad(45)),          // BNB testnet: 15 blocks of 3s
+        ((11155111, 10143), into_monad(780)),   // Sepolia: two epochs to finality
+    ])
+}
+
+/// Routes as (source, target, timing) entries, for JSON and stable storage.
+pub fn route_timing_list(routes: &BTreeMap<(u64, u64), RouteTiming>) -> Vec<(u64, u64, RouteTiming)> {
+    routes.iter().map(|((source, target), timing)| (*source, *target, *timing)).collect()
+}
+
+/// Expected seconds for a request to cross from `source_chain_id` to `target_chain_id`.
+pub fn route_time_secs(routes: &BTreeMap<(u64, u64), RouteTiming>, source_chain_id: u64, target_chain_id: u64) -> u64 {
+    routes.get(&(source_chain_id, target_chain_id))
+        .map(RouteTiming::total_secs)
+        .unwrap_or(DEFAULT_ROUTE_TIME_SECS)
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct BridgeReceipt {
+    pub tx_hash: Option<String>, // bridge transfer on the sending chain, if one was made
+    pub asset_address: String,   // asset as it arrives on the destination chain
+    pub amount: String,          // amount delivered, after any bridge fees
+    #[serde(default)]
+    pub source_proof: Option<SourceProof>, // deposit evidence, for bridges that provide it
+}
+
+/// Moves assets between chains for the cross-chain handler. Implement this to plug
+/// in a real bridge (LayerZero, Wormhole, ...) without touching the handler.
+#[allow(async_fn_in_trait)]
+pub trait BridgeAdapter {
+    async fn bridge(
+        &self,
+        from_chain: u64,
+        to_chain: u64,
+        asset: &str,
+        amount: &str,
+    ) -> Result<BridgeReceipt, String>;
+
+    /// Bridge and deliver to `recipient` rather than the requesting user, e.g. a
+    /// borrow paid out to a separate address. Adapters that cannot address a
+    /// recipient keep this default and reject the transfer.
+    async fn bridge_to(
+        &self,
+        _from_chain: u64,
+        _to_chain: u64,
+        _asset: &str,
+        _amount: &str,
+        recipient: &str,
+    ) -> Result<BridgeReceipt, String> {
+        Err(format!("This bridge cannot deliver to a separate recipient ({})", recipient))
+    }
+}
+
+/// MVP behaviour: assets are assumed to already be available on Monad, and
+/// transfers back to the source chain return a placeholder hash. Inbound
+/// transfers carry a placeholder deposit proof in the real proof format, which
+/// fails verification since no such deposit exists.
+pub struct MockBridgeAdapter {
+    pub monad_chain_id: u64,
+}
+
+impl BridgeAdapter for MockBridgeAdapter {
+    async fn bridge(
+        &self,
+        from_chain: u64,
+        to_chain: u64,
+        asset: &str,
+        amount: &str,
+    ) -> Result<BridgeReceipt, String> {
+        logger::log(format!("🌉 Bridging {} of {} from chain {} to chain {}", amount, asset, from_chain, to_chain));
+
+        if to_chain == self.monad_chain_id {
+            let source_proof = match (Address::from_str(asset), U256::from_str(amount)) {
+                (Ok(asset), Ok(amount)) => {
+                    Some(SourceProof::deposit(from_chain, MOCK_DEPOSIT_TX_HASH, MOCK_BRIDGE_ESCROW, asset, amount))
+                }
+                _ => None,
+            };
+            Ok(BridgeReceipt {
+                tx_hash: None,
+                asset_address: MOCK_MONAD_USDC.to_string(),
+                amount: amount.to_string(),
+                source_proof,
+            })
+        } else {
+            Ok(BridgeReceipt {
+                tx_hash: Some(MOCK_BRIDGE_TX_HASH.to_string()),
+                asset_address: asset.to_string(),
+                amount: amount.to_string(),
+                source_proof: None,
+            })
+        }
+    }
+
+    async fn bridge_to(
+        &self,
+        from_chain: u64,
+        to_chain: u64,
+        asset: &str,
+        amount: &str,
+        recipient: &str,
+    ) -> Result<BridgeReceipt, String> {
+        logger::log(format!("🌉 Delivering to {}", recipient));
+        self.bridge(from_chain, to_chain, asset, amount).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::block_on;
+
+    async fn bridge_through<B: BridgeAdapter>(bridge: &B, from_chain: u64, to_chain: u64) -> BridgeReceipt {
+        bridge.bridge(from_chain, to_chain, "0x00000000000000000000000000000000000000cc", "100").await.unwrap()
+    }
+
+    #[test]
+    fn mock_adapter_delivers_into_the_configured_monad_chain() {
+        let bridge = MockBridgeAdapter { monad_chain_id: 10143 };
+
+        let inbound = block_on(bridge_through(&bridge, 97, 10143));
+        assert_eq!(inbound.tx_hash, None);
+        assert_eq!(inbound.asset_address, MOCK_MONAD_USDC);
+        assert_eq!(inbound.amount, "100");
+        let proof = inbound.source_proof.expect("inbound transfers carry a deposit proof");
+        assert_eq!((proof.chain_id, proof.tx_hash), (97, MOCK_DEPOSIT_TX_HASH.to_string()));
+
+        let outbound = block_on(bridge_through(&bridge, 10143, 97));
+        assert_eq!(outbound.tx_hash.as_deref(), Some(MOCK_BRIDGE_TX_HASH));
+        assert_eq!(outbound.asset_address, "0x00000000000000000000000000000000000000cc");
+
+        // Another Peridot chain is honoured instead of a hardcoded Monad id
+        let elsewhere = MockBridgeAdapter { monad_chain_id: 143 };
+        assert!(block_on(bridge_through(&elsewhere, 97, 10143)).tx_hash.is_some());
+    }
+
+    #[test]
+    fn separate_recipients_need_adapter_support() {
+        struct NoRecipients;
+        impl BridgeAdapter for NoRecipients {
+            async fn bridge(&self, _: u64, _: u64, asset: &str, amount: &str) -> Result<BridgeReceipt, String> {
+                Ok(BridgeReceipt {
+                    tx_hash: None,
+                    asset_address: asset.to_string(),
+                    amount: amount.to_string(),
+                    source_proof: None,
+                })
+            }
+        }
+
+        let payout = "0x00000000000000000000000000000000000000dd";
+        assert!(block_on(NoRecipients.bridge_to(10143, 97, "0xcc", "1", payout)).is_err());
+        let mock = MockBridgeAdapter { monad_chain_id: 10143 };
+        assert!(block_on(mock.bridge_to(10143, 97, "0xcc", "1", payout)).is_ok());
+    }
+
+    #[test]
+    fn unknown_routes_use_the_default_time() {
+        let routes = default_route_timings();
+        assert_eq!(route_time_secs(&routes, 97, 10143), 167);
+        assert_eq!(route_time_secs(&routes, 1, 10143), DEFAULT_ROUTE_TIME_SECS);
+    }
+}
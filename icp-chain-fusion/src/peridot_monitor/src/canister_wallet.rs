@@ -0,0 +1,70 @@
+use crate::rpc_manager::call_with_fallback;
+use crate::state::{mutate_state, read_state};
+use alloy::primitives::U256;
+use alloy::network::TxSigner;
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::signers::icp::IcpSigner;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// Maximum number of derivation path components accepted by threshold ECDSA.
+pub const MAX_DERIVATION_PATH_LEN: usize = 255;
+
+/// Native balance (in wei) below which `get_health` warns that gas may run out: 0.05 tokens.
+pub const LOW_BALANCE_THRESHOLD_WEI: u128 = 50_000_000_000_000_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct NativeBalance {
+    pub chain_id: u64,
+    pub address: String,
+    pub balance_wei: String,
+    pub symbol: String,
+    pub updated_at: u64,
+}
+
+impl NativeBalance {
+    pub fn is_low(&self) -> bool {
+        self.balance_wei.parse::<U256>()
+            .map(|balance| balance < U256::from(LOW_BALANCE_THRESHOLD_WEI))
+            .unwrap_or(true)
+    }
+}
+
+/// EVM address for the canister's key under `derivation_path`. Nothing is cached;
+/// the empty path yields the canister's own address.
+pub async fn derived_address(derivation_path: Vec<Vec<u8>>) -> Result<String, String> {
+    if derivation_path.len() > MAX_DERIVATION_PATH_LEN {
+        return Err(format!(
+            "Derivation path has {} components, maximum is {}",
+            derivation_path.len(), MAX_DERIVATION_PATH_LEN
+        ));
+    }
+    let key_name = read_state(|s| s.ecdsa_key_id.name.clone());
+    let signer = IcpSigner::new(derivation_path, &key_name, None).await
+        .map_err(|e| format!("Failed to derive ECDSA key: {}", e))?;
+    Ok(signer.address().to_string())
+}
+
+/// Fetch the canister's native gas-token balance on a chain and cache it for `get_health`.
+pub async fn fetch_native_balance(chain_id: u64) -> Result<NativeBalance, String> {
+    let address = read_state(|s| s.canister_evm_address)
+        .ok_or_else(|| "Signer address not initialized yet".to_string())?;
+    let symbol = read_state(|s| s.chain_configs.get(&chain_id).map(|c| c.native_symbol().to_string()))
+        .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+
+    let balance = call_with_fallback(chain_id, |service| async move {
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+        provider.get_balance(address).await.map_err(|e| e.to_string())
+    }).await?;
+
+    let native_balance = NativeBalance {
+        chain_id,
+        address: address.to_string(),
+        balance_wei: balance.to_string(),
+        symbol,
+        updated_at: ic_cdk::api::time() / 1_000_000_000,
+    };
+    mutate_state(|s| s.native_balances.insert(chain_id, native_balance.clone()));
+    Ok(native_balance)
+}
@@ -1,62 +1,375 @@
-use crate::rpc_manager::RpcManager;
-use alloy::primitives::Address;
-use alloy::rpc::types::Log;
+use crate::cycles;
+use crate::guard::TimerGuard;
+use crate::job;
+use crate::logger;
+use crate::rpc_manager::{batch_call, call_with_fallback, chain_topic_limit, split_topics, topic_limit, RpcManager};
+use crate::state::{mutate_state, read_state, LogSource, State, TaskType};
+use crate::token_metadata;
+use crate::unrecognized_events;
+use crate::watchdog;
+use crate::PeridotEvents;
+use alloy::primitives::{address, Address, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol_types::SolEvent;
+use alloy::transports::icp::IcpConfig;
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
+use std::time::Duration;
+
+/// How often every configured chain is synced by `sync_scheduled`.
+pub const CHAIN_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A contract address parsed once where it enters the canister. Crosses the
+/// candid and JSON boundaries as text, so a malformed address is rejected when
+/// the config is decoded rather than on every use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractAddress(pub Address);
+
+impl std::ops::Deref for ContractAddress {
+    type Target = Address;
+
+    fn deref(&self) -> &Address {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ContractAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for ContractAddress {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        Address::from_str(value)
+            .map(ContractAddress)
+            .map_err(|e| format!("invalid contract address {}: {}", value, e))
+    }
+}
+
+impl CandidType for ContractAddress {
+    fn _ty() -> candid::types::Type {
+        <String as CandidType>::_ty()
+    }
+
+    fn idl_serialize<S: candid::types::Serializer>(&self, serializer: S) -> Result<(), S::Error> {
+        serializer.serialize_text(&self.0.to_string())
+    }
+}
+
+impl Serialize for ContractAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ContractAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
 
 #[derive(Debug, Clone, CandidType, Deserialize, Serialize)]
 pub struct ChainConfig {
     pub chain_id: u64,
     pub name: String,
-    pub peridot_contract: String,
+    pub peridot_contract: ContractAddress,
+    pub controller: String,
+    pub price_oracle: String,
+    pub lens: Option<String>,
+    /// Gas token symbol; optional so configs persisted before it existed still decode.
+    pub native_symbol: Option<String>,
     pub block_time_ms: u64,
     pub confirmation_blocks: u64,
+    /// topic0 hashes to monitor on this chain; `None` means the core Peridot events.
+    pub event_topics: Option<Vec<String>>,
+    /// First block scanned when the chain has no sync progress, normally the
+    /// Peridot deployment block, so pre-deployment history is skipped.
+    pub genesis_sync_block: Option<u64>,
+}
+
+/// Sort logs into chain order, `(block_number, log_index)`, so events apply as
+/// they did on-chain. Providers don't all return logs in that order, and logs
+/// merged from several topic-split filters never are.
+pub fn sort_logs(logs: &mut [Log]) {
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+}
+
+/// Whether a log was already applied, or is queued for the canister's own scraper.
+/// Replays check this instead of `processed_logs`, which compaction trims.
+fn already_seen(state: &State, chain_id: u64, log: &Log) -> bool {
+    let queued = chain_id == state.chain_id && log.transaction_hash.is_some_and(|transaction_hash| {
+        state.logs_to_process.contains_key(&LogSource {
+            transaction_hash,
+            log_index: log.log_index.unwrap_or_default(),
+        })
+    });
+    queued || state.has_applied_log(chain_id, log)
+}
+
+/// Replays must start inside the chain's replay window: below it, applied log keys
+/// have been pruned and already-applied logs could not be told apart from missed ones.
+fn ensure_replayable(state: &State, chain_id: u64, block: u64) -> Result<(), String> {
+    let horizon = state.replay_horizon(chain_id);
+    if block < horizon {
+        return Err(format!(
+            "Block {} of chain {} is below the replay window; the oldest replayable block is {}",
+            block, chain_id, horizon
+        ));
+    }
+    Ok(())
+}
+
+/// Address held in an indexed event topic: the low 20 bytes of the 32-byte word.
+pub fn topic_address(topic: B256) -> Address {
+    Address::from_word(topic)
+}
+
+/// topic0 of the core Peridot market and controller events.
+pub fn core_event_topics() -> Vec<B256> {
+    vec![
+        PeridotEvents::Mint::SIGNATURE_HASH,
+        PeridotEvents::Redeem::SIGNATURE_HASH,
+        PeridotEvents::Borrow::SIGNATURE_HASH,
+        PeridotEvents::RepayBorrow::SIGNATURE_HASH,
+        PeridotEvents::LiquidateBorrow::SIGNATURE_HASH,
+        PeridotEvents::AccrueInterest::SIGNATURE_HASH,
+        PeridotEvents::NewCollateralFactor::SIGNATURE_HASH,
+        PeridotEvents::MarketEntered::SIGNATURE_HASH,
+        PeridotEvents::MarketExited::SIGNATURE_HASH,
+    ]
+}
+
+impl ChainConfig {
+    /// Check that every configured contract address parses. `peridot_contract`
+    /// is already parsed on decode.
+    pub fn validate(&self) -> Result<(), String> {
+        let lens = self.lens.iter().map(|lens| ("lens", lens.as_str()));
+        for (field, value) in [
+            ("controller", self.controller.as_str()),
+            ("price_oracle", self.price_oracle.as_str()),
+        ].into_iter().chain(lens) {
+            Address::from_str(value)
+                .map_err(|e| format!("Chain {}: invalid {} address {}: {}", self.chain_id, field, value, e))?;
+        }
+        self.monitored_topics()?;
+        Ok(())
+    }
+
+    /// Event signatures (topic0) monitored on this chain.
+    pub fn monitored_topics(&self) -> Result<Vec<B256>, String> {
+        match &self.event_topics {
+            None => Ok(core_event_topics()),
+            Some(topics) => topics.iter()
+                .map(|topic| B256::from_str(topic)
+                    .map_err(|e| format!("Chain {}: invalid event topic {}: {}", self.chain_id, topic, e)))
+                .collect(),
+        }
+    }
+
+    /// The gas token symbol, or `UNKNOWN` when none is configured.
+    pub fn native_symbol(&self) -> &str {
+        self.native_symbol.as_deref().unwrap_or(token_metadata::UNKNOWN_SYMBOL)
+    }
+
+    pub fn controller_address(&self) -> Result<Address, String> {
+        Address::from_str(&self.controller).map_err(|e| format!("Invalid controller address: {}", e))
+    }
+
+    /// The price oracle address, or an error when no oracle is deployed yet.
+    pub fn price_oracle_address(&self) -> Result<Address, String> {
+        let oracle = Address::from_str(&self.price_oracle)
+            .map_err(|e| format!("Invalid price oracle address: {}", e))?;
+        if oracle == Address::ZERO {
+            return Err(format!("No price oracle configured for chain {}", self.chain_id));
+        }
+        Ok(oracle)
+    }
+}
+
+/// Add a chain, rejecting ids that are already configured and Peridot contracts
+/// already registered under another id, instead of silently overwriting.
+pub fn insert_chain_config(configs: &mut BTreeMap<u64, ChainConfig>, config: ChainConfig) -> Result<(), String> {
+    config.validate()?;
+    if configs.contains_key(&config.chain_id) {
+        return Err(format!("Chain {} is already configured", config.chain_id));
+    }
+    let contract = config.peridot_contract;
+    if let Some(existing) = configs.values().find(|c| c.peridot_contract == contract) {
+        return Err(format!(
+            "Peridot contract {} is already configured for chain {} ({})",
+            contract, existing.chain_id, existing.name
+        ));
+    }
+    configs.insert(config.chain_id, config);
+    Ok(())
+}
+
+/// Check a full set of chain configs: each entry is valid, keyed by its own
+/// chain id, and no Peridot contract appears under two ids.
+pub fn validate_chain_configs(configs: &BTreeMap<u64, ChainConfig>) -> Result<(), String> {
+    let mut checked = BTreeMap::new();
+    for (chain_id, config) in configs {
+        if *chain_id != config.chain_id {
+            return Err(format!("Chain config keyed {} declares chain id {}", chain_id, config.chain_id));
+        }
+        insert_chain_config(&mut checked, config.clone())?;
+    }
+    Ok(())
+}
+
+/// Split persisted chain configs into the entries that pass `validate_chain_configs`
+/// checks, in chain id order, and one error per rejected entry.
+pub fn partition_chain_configs(configs: BTreeMap<u64, ChainConfig>) -> (BTreeMap<u64, ChainConfig>, Vec<String>) {
+    let mut valid = BTreeMap::new();
+    let mut rejected = Vec::new();
+    for (chain_id, config) in configs {
+        let result = if chain_id != config.chain_id {
+            Err(format!("Chain config keyed {} declares chain id {}", chain_id, config.chain_id))
+        } else {
+            insert_chain_config(&mut valid, config)
+        };
+        if let Err(e) = result {
+            rejected.push(format!("chain {}: {}", chain_id, e));
+        }
+    }
+    (valid, rejected)
+}
+
+/// Default cap on the number of blocks covered by one `eth_getLogs` request.
+pub const DEFAULT_MAX_BLOCK_SPAN: u64 = 10_000;
+
+/// Last block of the next sync window: the confirmed head, capped at
+/// `max_span` blocks past `from_block`.
+pub fn sync_window_end(from_block: u64, confirmed_head: u64, max_span: u64) -> u64 {
+    confirmed_head.min(from_block.saturating_add(max_span))
+}
+
+/// Reject inverted, zero-width, and oversized ranges before they reach a log filter.
+pub fn validate_block_range(from_block: u64, to_block: u64) -> Result<(), String> {
+    if to_block <= from_block {
+        return Err(format!("Invalid block range {}..{}: end must be after start", from_block, to_block));
+    }
+    let max_span = read_state(|s| s.max_block_span);
+    if to_block - from_block > max_span {
+        return Err(format!(
+            "Block range {}..{} spans {} blocks, maximum is {}",
+            from_block, to_block, to_block - from_block, max_span
+        ));
+    }
+    Ok(())
+}
+
+/// Built-in Peridot deployments, used to seed state at init.
+pub fn default_chain_configs() -> BTreeMap<u64, ChainConfig> {
+    let mut chain_configs = BTreeMap::new();
+
+    // Monad testnet configuration
+    insert_chain_config(&mut chain_configs, ChainConfig {
+        chain_id: 10143,
+        name: "Monad Testnet".to_string(),
+        peridot_contract: ContractAddress(address!("a41D586530BC7BC872095950aE03a780d5114445")),
+        controller: "0xa41D586530BC7BC872095950aE03a780d5114445".to_string(),
+        price_oracle: Address::ZERO.to_string(), // Not deployed yet - set via set_chain_contracts
+        lens: None,
+        native_symbol: Some("MON".to_string()),
+        block_time_ms: 1000, // 1 second
+        confirmation_blocks: 12,
+        event_topics: None,
+        genesis_sync_block: None, // deployment block not recorded; set with set_genesis_sync_block
+    }).expect("BUG: invalid built-in Monad config");
+
+    // BNB testnet configuration
+    insert_chain_config(&mut chain_configs, ChainConfig {
+        chain_id: 97,
+        name: "BNB Testnet".to_string(),
+        peridot_contract: ContractAddress(address!("e797A0001A3bC1B2760a24c3D7FDD172906bCCd6")),
+        controller: "0xe797A0001A3bC1B2760a24c3D7FDD172906bCCd6".to_string(),
+        price_oracle: "0x82BF1C5516F6A91d4bF1E0aB62aF373dB049Df91".to_string(),
+        lens: None,
+        native_symbol: Some("BNB".to_string()),
+        block_time_ms: 3000, // 3 seconds
+        confirmation_blocks: 6,
+        event_topics: None,
+        genesis_sync_block: None, // deployment block not recorded; set with set_genesis_sync_block
+    }).expect("BUG: invalid built-in BNB config");
+
+    chain_configs
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize, Serialize)]
+pub struct RangeSyncReport {
+    pub chain_id: u64,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub fetched_logs: u64,
+    pub applied_events: u64,
+    pub skipped_duplicates: u64,
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize, Serialize)]
+pub struct IngestReport {
+    pub chain_id: u64,
+    pub tx_hash: String,
+    pub total_logs: u64,
+    pub matched_logs: u64,
+    pub applied_events: u64,
+    pub skipped_duplicates: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct ChainFusionManager {
     pub _rpc_manager: RpcManager,
     pub chain_configs: HashMap<u64, ChainConfig>,
-    pub last_synced_blocks: HashMap<u64, u64>,
 }
 
 impl ChainFusionManager {
     pub fn new() -> Self {
-        let mut chain_configs = HashMap::new();
-        
-        // Monad testnet configuration
-        chain_configs.insert(10143, ChainConfig {
-            chain_id: 10143,
-            name: "Monad Testnet".to_string(),
-            peridot_contract: "0xa41D586530BC7BC872095950aE03a780d5114445".to_string(),
-            block_time_ms: 1000, // 1 second
-            confirmation_blocks: 12,
-        });
-        
-        // BNB testnet configuration  
-        chain_configs.insert(97, ChainConfig {
-            chain_id: 97,
-            name: "BNB Testnet".to_string(),
-            peridot_contract: "0xe797A0001A3bC1B2760a24c3D7FDD172906bCCd6".to_string(),
-            block_time_ms: 3000, // 3 seconds
-            confirmation_blocks: 6,
-        });
+        let chain_configs = read_state(|s| s.chain_configs.clone())
+            .into_iter()
+            .collect();
         
         Self {
             _rpc_manager: RpcManager::new(),
             chain_configs,
-            last_synced_blocks: HashMap::new(),
         }
     }
     
+    /// Block to resume scanning from; see `State::sync_cursor`.
+    pub fn next_sync_block(&self, chain_id: u64) -> Option<u64> {
+        read_state(|s| s.sync_cursor(chain_id))
+    }
+
+    /// Timer entry point for `sync_all_chains`. A tick is skipped while the
+    /// previous sync is still running.
+    pub async fn sync_scheduled() {
+        let Ok(_guard) = TimerGuard::new(TaskType::SyncChains) else {
+            return;
+        };
+        if let Err(e) = Self::new().sync_all_chains().await {
+            logger::log(format!("Scheduled chain sync failed: {}", e));
+        }
+    }
+
+    /// Sync every configured chain except those paused with `set_chain_sync_enabled`.
     pub async fn sync_all_chains(&mut self) -> Result<(), String> {
-        let chain_ids: Vec<u64> = self.chain_configs.keys().cloned().collect();
+        cycles::ensure_cycles("full sync")?;
+        let paused = read_state(|s| s.sync_paused_chains.clone());
+        let chain_ids: Vec<u64> = self.chain_configs.keys()
+            .filter(|chain_id| !paused.contains(chain_id))
+            .cloned()
+            .collect();
         
         for chain_id in chain_ids {
             if let Err(e) = self.sync_chain_events(chain_id).await {
-                ic_cdk::println!("Failed to sync chain {}: {}", chain_id, e);
+                logger::log(format!("Failed to sync chain {}: {}", chain_id, e));
                 // Continue with other chains even if one fails
             }
         }
@@ -65,29 +378,46 @@ impl ChainFusionManager {
     }
     
     pub async fn sync_chain_events(&mut self, chain_id: u64) -> Result<(), String> {
+        cycles::ensure_cycles(&format!("sync of chain {}", chain_id))?;
         let _config = self.chain_configs.get(&chain_id)
             .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
         
-        // Fix borrowing issue by cloning the value
-        let from_block = *self.last_synced_blocks.get(&chain_id).unwrap_or(&0);
-        let to_block = self.get_safe_to_block(chain_id).await?;
-        
+        let safe_head = self.get_safe_to_block(chain_id).await?;
+        // Without a cursor or deployment block there is no known start; begin at the
+        // confirmed head instead of scanning from block 0
+        let Some(from_block) = self.next_sync_block(chain_id) else {
+            logger::log(format!("Chain {} has no sync cursor or genesis block; starting at block {}", chain_id, safe_head));
+            mutate_state(|s| s.record_synced_block(chain_id, safe_head));
+            watchdog::record_sync_success(chain_id);
+            return Ok(());
+        };
+        let max_span = read_state(|s| s.max_block_span);
+        let to_block = sync_window_end(from_block, safe_head, max_span);
         if from_block >= to_block {
+            watchdog::record_sync_success(chain_id);
             return Ok(()); // No new blocks to process
         }
         
-        let logs = self.fetch_peridot_events(chain_id, from_block, to_block).await?;
+        // Every topic group in one outcall where the provider batches, one call each otherwise
+        let logs = match self.fetch_events_batched(chain_id, from_block, to_block).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                logger::log(format!("Batched sync unavailable for chain {}, using sequential calls: {}", chain_id, e));
+                self.fetch_peridot_events(chain_id, from_block, to_block).await?
+            }
+        };
         
-        ic_cdk::println!(
+        logger::log(format!(
             "Processing {} events for chain {} (blocks {} to {})", 
             logs.len(), 
             chain_id, 
             from_block, 
             to_block
-        );
+        ));
         
         self.process_events(chain_id, logs).await?;
-        self.last_synced_blocks.insert(chain_id, to_block);
+        mutate_state(|s| s.record_synced_block(chain_id, to_block));
+        watchdog::record_sync_success(chain_id);
         
         Ok(())
     }
@@ -95,97 +425,208 @@ impl ChainFusionManager {
     async fn get_safe_to_block(&mut self, chain_id: u64) -> Result<u64, String> {
         let config = self.chain_configs.get(&chain_id).unwrap();
         
-        let latest_block: u64 = 0; // Simplified for now - will implement proper RPC calls later
+        let latest_block = call_with_fallback(chain_id, |service| async move {
+            let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+            provider.get_block_number().await.map_err(|e| e.to_string())
+        }).await?;
         
         // Use confirmed blocks only  
         Ok(latest_block.saturating_sub(config.confirmation_blocks))
     }
     
-    async fn fetch_peridot_events(&mut self, chain_id: u64, _from_block: u64, _to_block: u64) -> Result<Vec<Log>, String> {
+    /// Fetch the logs in `[from_block, to_block]` with every topic group in one
+    /// batched outcall. `to_block` is the confirmed head, so the filter never
+    /// reaches blocks that could still be reorganised.
+    async fn fetch_events_batched(&self, chain_id: u64, from_block: u64, to_block: u64) -> Result<Vec<Log>, String> {
+        let config = self.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+        let contracts = self.monitored_contracts(chain_id)?;
+        validate_block_range(from_block, to_block)?;
+
+        // Any provider may answer the batch, so respect the tightest topic limit
+        let mut calls = Vec::new();
+        for topics in split_topics(&config.monitored_topics()?, chain_topic_limit(chain_id)) {
+            let filter = Filter::new()
+                .address(contracts.clone())
+                .event_signature(topics)
+                .from_block(from_block)
+                .to_block(to_block);
+            let filter = serde_json::to_value(&filter).map_err(|e| format!("Failed to encode filter: {}", e))?;
+            calls.push(("eth_getLogs", json!([filter])));
+        }
+
+        let results = batch_call(chain_id, &calls).await?;
+
+        let mut logs: Vec<Log> = Vec::new();
+        for result in &results {
+            let group: Vec<Log> = serde_json::from_value(result.clone())
+                .map_err(|e| format!("Invalid eth_getLogs result: {}", e))?;
+            logs.extend(group);
+        }
+        sort_logs(&mut logs);
+        Ok(logs)
+    }
+
+    async fn fetch_peridot_events(&mut self, chain_id: u64, from_block: u64, to_block: u64) -> Result<Vec<Log>, String> {
         let config = self.chain_configs.get(&chain_id).unwrap();
-        let _contract_address = Address::from_str(&config.peridot_contract)
-            .map_err(|e| format!("Invalid contract address: {}", e))?;
+        let contracts = self.monitored_contracts(chain_id)?;
+        validate_block_range(from_block, to_block)?;
+
+        let topics = config.monitored_topics()?;
         
-        // Simplified for now - return empty logs
-        Ok(Vec::new())
+        call_with_fallback(chain_id, |service| {
+            let groups = split_topics(&topics, topic_limit(&service));
+            let contracts = contracts.clone();
+            async move {
+                let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+                let mut logs = Vec::new();
+                for topics in groups {
+                    let filter = Filter::new()
+                        .address(contracts.clone())
+                        .event_signature(topics)
+                        .from_block(from_block)
+                        .to_block(to_block);
+                    logs.extend(provider.get_logs(&filter).await.map_err(|e| e.to_string())?);
+                }
+                sort_logs(&mut logs);
+                Ok(logs)
+            }
+        }).await
     }
     
-    async fn process_events(&self, chain_id: u64, logs: Vec<Log>) -> Result<(), String> {
+    async fn process_events(&self, chain_id: u64, mut logs: Vec<Log>) -> Result<(), String> {
+        sort_logs(&mut logs);
         for log in logs {
+            if read_state(|s| s.has_applied_log(chain_id, &log)) {
+                continue;
+            }
             if let Err(e) = self.process_single_event(chain_id, &log).await {
-                ic_cdk::println!("Failed to process event: {}", e);
+                logger::log(format!("Failed to process event: {}", e));
                 // Continue processing other events
             }
         }
         Ok(())
     }
     
-    async fn process_single_event(&self, chain_id: u64, log: &Log) -> Result<(), String> {
-        if log.topics().is_empty() {
-            return Ok(());
+    /// Contracts whose logs may change state on a chain; see `State::monitored_contracts`.
+    fn monitored_contracts(&self, chain_id: u64) -> Result<Vec<Address>, String> {
+        read_state(|s| s.monitored_contracts(chain_id))
+    }
+
+    /// Run a log through the same event pipeline as scraped logs. Returns whether
+    /// the log was a recognised Peridot event; applied logs are recorded so that
+    /// replays skip them.
+    async fn process_single_event(&self, chain_id: u64, log: &Log) -> Result<bool, String> {
+        if !job::from_monitored_contract(chain_id, log) {
+            return Ok(false);
         }
         
-        let event_signature = log.topics()[0].to_string();
-        match event_signature.as_str() {
-            "0x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f" => {
-                self.process_mint_event(chain_id, log).await
-            },
-            "0xe5b754fb1abb7f01b499791d0b820ae3b6af3424ac1c59768edb53c4ec31a929" => {
-                self.process_redeem_event(chain_id, log).await
-            },
-            "0x13ed6866d4e1ee6da46f845c46d7e6b8c23c8e7b8a2adb2e2e6e4c8f6d7c2e9f" => {
-                self.process_borrow_event(chain_id, log).await
-            },
-            "0xa615e577de3f5b5e7b2b4b7f8c5a3b2a1e9f8c7e6d5b4a3c2d1f0e9d8c7b6a5" => {
-                self.process_repay_event(chain_id, log).await
-            },
-            "0xb3e2ad3f0d0a8b4c5e6d7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8" => {
-                self.process_liquidation_event(chain_id, log).await
-            },
-            _ => Ok(()),
+        let config = self.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+        let monitored = match log.topics().first() {
+            Some(topic0) => config.monitored_topics()?.contains(topic0),
+            None => false,
+        };
+        if !monitored {
+            unrecognized_events::handle(chain_id, log);
+            return Ok(false);
         }
-    }
-    
-    async fn process_mint_event(&self, chain_id: u64, log: &Log) -> Result<(), String> {
-        if log.topics().len() < 2 {
-            return Ok(());
+
+        let applied = job::apply_event(chain_id, log).await;
+        if applied {
+            mutate_state(|s| s.record_applied_log(chain_id, log));
+        } else {
+            unrecognized_events::handle(chain_id, log);
         }
+        Ok(applied)
+    }
+
+    /// Fetch a transaction receipt and apply its Peridot logs, skipping logs that
+    /// were already processed. Used to replay events the scraper missed.
+    pub async fn ingest_transaction(&self, chain_id: u64, tx_hash: B256) -> Result<IngestReport, String> {
+        let contracts = self.monitored_contracts(chain_id)?;
         
-        let user_address = format!("0x{}", hex::encode(&log.topics()[1][12..]));
+        let receipt = call_with_fallback(chain_id, |service| async move {
+            let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+            provider.get_transaction_receipt(tx_hash).await.map_err(|e| e.to_string())
+        }).await?
+            .ok_or_else(|| format!("No receipt found for transaction {} on chain {}", tx_hash, chain_id))?;
+        if let Some(block) = receipt.block_number {
+            read_state(|s| ensure_replayable(s, chain_id, block))?;
+        }
         
-        ic_cdk::println!("Processing Mint event for user {} on chain {}", user_address, chain_id);
+        let logs = receipt.inner.logs();
+        let mut report = IngestReport {
+            chain_id,
+            tx_hash: tx_hash.to_string(),
+            total_logs: logs.len() as u64,
+            matched_logs: 0,
+            applied_events: 0,
+            skipped_duplicates: 0,
+        };
         
-        // In a real implementation, we would update the user's position here
-        // For now, just log the event
+        for log in logs.iter().filter(|log| contracts.contains(&log.address())) {
+            report.matched_logs += 1;
+
+            if read_state(|s| already_seen(s, chain_id, log)) {
+                report.skipped_duplicates += 1;
+                continue;
+            }
+
+            if self.process_single_event(chain_id, log).await? {
+                report.applied_events += 1;
+            }
+        }
         
-        Ok(())
+        Ok(report)
     }
     
-    async fn process_redeem_event(&self, _chain_id: u64, _log: &Log) -> Result<(), String> {
-        // Similar implementation for redeem events
-        Ok(())
-    }
-    
-    async fn process_borrow_event(&self, _chain_id: u64, _log: &Log) -> Result<(), String> {
-        // Similar implementation for borrow events
-        Ok(())
-    }
-    
-    async fn process_repay_event(&self, _chain_id: u64, _log: &Log) -> Result<(), String> {
-        // Similar implementation for repay events
-        Ok(())
-    }
-    
-    async fn process_liquidation_event(&self, _chain_id: u64, _log: &Log) -> Result<(), String> {
-        // Process liquidation events and update positions
-        Ok(())
+    /// Re-scan an exact block range through the event pipeline, skipping logs that
+    /// were already processed. The sync cursor and head are left untouched, and
+    /// ranges starting below the replay window are rejected.
+    pub async fn sync_block_range(&mut self, chain_id: u64, from_block: u64, to_block: u64) -> Result<RangeSyncReport, String> {
+        cycles::ensure_cycles(&format!("range sync of chain {}", chain_id))?;
+        if !self.chain_configs.contains_key(&chain_id) {
+            return Err(format!("Chain {} not configured", chain_id));
+        }
+        read_state(|s| ensure_replayable(s, chain_id, from_block))?;
+
+        let logs = self.fetch_peridot_events(chain_id, from_block, to_block).await?;
+        let mut report = RangeSyncReport {
+            chain_id,
+            from_block,
+            to_block,
+            fetched_logs: logs.len() as u64,
+            applied_events: 0,
+            skipped_duplicates: 0,
+        };
+
+        for log in &logs {
+            if read_state(|s| already_seen(s, chain_id, log)) {
+                report.skipped_duplicates += 1;
+                continue;
+            }
+
+            match self.process_single_event(chain_id, log).await {
+                Ok(true) => report.applied_events += 1,
+                Ok(false) => {}
+                Err(e) => logger::log(format!("Failed to process event: {}", e)),
+            }
+        }
+
+        logger::log(format!(
+            "Range sync of chain {} (blocks {} to {}): {} applied, {} duplicates",
+            chain_id, from_block, to_block, report.applied_events, report.skipped_duplicates
+        ));
+        Ok(report)
     }
     
     pub fn get_chain_summary(&self) -> HashMap<u64, String> {
         let mut summary = HashMap::new();
         
         for (chain_id, config) in &self.chain_configs {
-            let last_block = self.last_synced_blocks.get(chain_id).unwrap_or(&0);
+            let last_block = self.next_sync_block(*chain_id)
+                .map_or_else(|| "not synced".to_string(), |block| block.to_string());
             summary.insert(*chain_id, format!(
                 "{}: {} (last block: {})", 
                 config.name, 
@@ -196,4 +637,246 @@ impl ChainFusionManager {
         
         summary
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    fn monad_config() -> ChainConfig {
+        default_chain_configs().remove(&10143).expect("Monad is a built-in chain")
+    }
+
+    #[test]
+    fn contract_addresses_are_validated_per_chain() {
+        assert!(validate_chain_configs(&default_chain_configs()).is_ok());
+
+        let mut config = monad_config();
+        config.controller = "not-an-address".to_string();
+        assert!(config.validate().unwrap_err().contains("invalid controller address"));
+
+        let mut config = monad_config();
+        config.lens = Some("0x123".to_string());
+        assert!(config.validate().unwrap_err().contains("invalid lens address"));
+    }
+
+    #[test]
+    fn malformed_contract_addresses_are_rejected_when_decoded() {
+        let bytes = candid::encode_one("0x123").unwrap();
+        assert!(candid::decode_one::<ContractAddress>(&bytes).is_err());
+        assert!("0x123".parse::<ContractAddress>().unwrap_err().starts_with("invalid contract address 0x123"));
+        assert!(serde_json::from_str::<ContractAddress>("\"not-an-address\"").is_err());
+
+        // Parsed once, so casing no longer affects comparisons
+        let lower: ContractAddress = "0xa41d586530bc7bc872095950ae03a780d5114445".parse().unwrap();
+        assert_eq!(lower, monad_config().peridot_contract);
+        let bytes = candid::encode_one(&lower).unwrap();
+        assert_eq!(candid::decode_one::<ContractAddress>(&bytes).unwrap(), lower);
+    }
+
+    #[test]
+    fn undeployed_price_oracle_is_reported() {
+        let mut config = monad_config();
+        assert!(config.price_oracle_address().unwrap_err().contains("No price oracle"));
+
+        config.price_oracle = "0x82BF1C5516F6A91d4bF1E0aB62aF373dB049Df91".to_string();
+        assert_eq!(
+            config.price_oracle_address().unwrap(),
+            address!("82BF1C5516F6A91d4bF1E0aB62aF373dB049Df91")
+        );
+        assert_eq!(config.controller_address().unwrap(), address!("a41D586530BC7BC872095950aE03a780d5114445"));
+    }
+
+    #[test]
+    fn configs_persisted_without_native_symbol_still_decode() {
+        #[derive(CandidType)]
+        struct ChainConfigWithoutSymbol {
+            chain_id: u64,
+            name: String,
+            peridot_contract: String,
+            controller: String,
+            price_oracle: String,
+            lens: Option<String>,
+            block_time_ms: u64,
+            confirmation_blocks: u64,
+            event_topics: Option<Vec<String>>,
+            genesis_sync_block: Option<u64>,
+        }
+
+        let config = monad_config();
+        let old = ChainConfigWithoutSymbol {
+            chain_id: config.chain_id,
+            name: config.name.clone(),
+            peridot_contract: config.peridot_contract.to_string(),
+            controller: config.controller.clone(),
+            price_oracle: config.price_oracle.clone(),
+            lens: None,
+            block_time_ms: config.block_time_ms,
+            confirmation_blocks: config.confirmation_blocks,
+            event_topics: None,
+            genesis_sync_block: config.genesis_sync_block,
+        };
+        let bytes = candid::encode_one(&old).unwrap();
+        let decoded: ChainConfig = candid::decode_one(&bytes).unwrap();
+        assert_eq!(decoded.native_symbol, None);
+        assert_eq!(decoded.native_symbol(), token_metadata::UNKNOWN_SYMBOL);
+        assert_eq!(decoded.peridot_contract, config.peridot_contract);
+    }
+
+    #[test]
+    fn monitored_topics_default_to_core_events_unless_configured() {
+        let mut config = monad_config();
+        config.event_topics = None;
+        assert_eq!(config.monitored_topics().unwrap(), core_event_topics());
+
+        let accrue = PeridotEvents::AccrueInterest::SIGNATURE_HASH;
+        config.event_topics = Some(vec![accrue.to_string()]);
+        assert_eq!(config.monitored_topics().unwrap(), vec![accrue]);
+
+        config.event_topics = Some(vec!["0x1234".to_string()]);
+        assert!(config.validate().unwrap_err().contains("invalid event topic"));
+    }
+
+    #[test]
+    fn sync_windows_stop_at_the_confirmed_head() {
+        assert_eq!(sync_window_end(100, 150, 10_000), 150);
+        assert_eq!(sync_window_end(100, 50_000, 10_000), 10_100);
+        // Nothing new is confirmed yet
+        assert!(sync_window_end(100, 90, 10_000) <= 100);
+    }
+
+    #[test]
+    fn fresh_chains_sync_from_the_configured_genesis_block() {
+        init_test_state();
+        let manager = ChainFusionManager::new();
+        assert_eq!(manager.next_sync_block(10143), None);
+
+        mutate_state(|s| s.chain_configs.get_mut(&10143).unwrap().genesis_sync_block = Some(4_200_000));
+        assert_eq!(manager.next_sync_block(10143), Some(4_200_000));
+
+        mutate_state(|s| {
+            s.record_synced_block(10143, 4_200_500);
+            s.record_synced_block(10143, 4_200_100);
+        });
+        assert_eq!(manager.next_sync_block(10143), Some(4_200_500));
+    }
+
+    #[test]
+    fn each_invalid_persisted_config_is_reported() {
+        let mut configs = default_chain_configs();
+        let mut broken = monad_config();
+        broken.controller = "not-an-address".to_string();
+        configs.insert(10143, broken);
+        let mut mislabelled = monad_config();
+        mislabelled.chain_id = 41454;
+        configs.insert(1, mislabelled);
+
+        let (valid, rejected) = partition_chain_configs(configs);
+        assert_eq!(valid.keys().copied().collect::<Vec<_>>(), vec![97]);
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected.iter().any(|e| e.starts_with("chain 1:") && e.contains("declares chain id 41454")));
+        assert!(rejected.iter().any(|e| e.starts_with("chain 10143:") && e.contains("controller")));
+    }
+
+    #[test]
+    fn padded_topics_decode_to_checksummed_addresses() {
+        let topic = B256::from_str("0x000000000000000000000000a41d586530bc7bc872095950ae03a780d5114445").unwrap();
+        let address = topic_address(topic);
+        assert_eq!(address, address!("a41D586530BC7BC872095950aE03a780d5114445"));
+        assert_eq!(address.to_string(), "0xa41D586530BC7BC872095950aE03a780d5114445");
+        assert_eq!(address.into_word(), topic);
+    }
+
+    #[test]
+    fn replays_skip_applied_and_queued_logs_after_compaction() {
+        init_test_state();
+        let log_at = |block: u64| Log {
+            block_number: Some(block),
+            transaction_hash: Some(B256::with_last_byte(block as u8)),
+            log_index: Some(0),
+            ..Default::default()
+        };
+        mutate_state(|s| {
+            s.processed_logs_retention = 1;
+            for block in 1..=3 {
+                s.record_log_to_process(&log_at(block));
+                s.record_processed_log(LogSource { transaction_hash: B256::with_last_byte(block as u8), log_index: 0 });
+                s.record_applied_log(10143, &log_at(block));
+            }
+            s.compact_processed_logs();
+            s.record_log_to_process(&log_at(4));
+        });
+
+        read_state(|s| {
+            assert!(!s.processed_logs.contains_key(&LogSource { transaction_hash: B256::with_last_byte(1), log_index: 0 }));
+            assert!(already_seen(s, 10143, &log_at(1)));
+            assert!(already_seen(s, 10143, &log_at(4)));
+            assert!(!already_seen(s, 10143, &log_at(5)));
+            assert!(!already_seen(s, 97, &log_at(1)));
+        });
+    }
+
+    #[test]
+    fn shuffled_logs_sort_into_chain_order() {
+        let chain_order: Vec<(u64, u64)> = [(7, 0), (7, 1), (7, 12), (8, 0), (8, 3), (10, 2), (11, 0), (11, 1)].to_vec();
+        let log_at = |&(block_number, log_index): &(u64, u64)| Log {
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            ..Default::default()
+        };
+
+        // Every rotation of a fixed shuffle, so each log starts out in every position
+        let shuffle = [5, 2, 7, 0, 3, 6, 1, 4];
+        for rotation in 0..shuffle.len() {
+            let mut logs: Vec<Log> = (0..shuffle.len())
+                .map(|i| log_at(&chain_order[shuffle[(i + rotation) % shuffle.len()]]))
+                .collect();
+            sort_logs(&mut logs);
+            let sorted: Vec<(u64, u64)> = logs.iter()
+                .map(|log| (log.block_number.unwrap(), log.log_index.unwrap()))
+                .collect();
+            assert_eq!(sorted, chain_order, "rotation {}", rotation);
+        }
+    }
+
+    #[test]
+    fn positions_come_back_with_their_applied_logs_after_an_upgrade() {
+        use crate::lifecycle::PersistedState;
+        use crate::test_support::block_on;
+
+        init_test_state();
+        let controller = read_state(|s| s.chain_configs[&10143].controller_address().unwrap());
+        let user = Address::repeat_byte(0xaa);
+        let market = Address::repeat_byte(0x0f);
+        let log_at = |block: u64, data| Log {
+            inner: alloy::primitives::Log { address: controller, data },
+            block_number: Some(block),
+            transaction_hash: Some(B256::with_last_byte(block as u8)),
+            log_index: Some(0),
+            ..Default::default()
+        };
+        let entered = |block| log_at(block, PeridotEvents::MarketEntered { pToken: market, account: user }.encode_log_data());
+        let exited = log_at(101, PeridotEvents::MarketExited { pToken: market, account: user }.encode_log_data());
+        let collateral = || read_state(|s| {
+            s.user_positions.get(&(job::position_key(user), 10143)).map(|p| p.collateral_enabled.clone())
+        });
+
+        let manager = ChainFusionManager::new();
+        block_on(manager.process_events(10143, vec![entered(100), exited.clone(), entered(102)])).unwrap();
+        mutate_state(|s| s.record_synced_block(10143, 102));
+        assert_eq!(collateral(), Some(vec![market.to_string()]));
+
+        let snapshot = read_state(PersistedState::capture);
+        init_test_state();
+        assert_eq!(collateral(), None);
+        mutate_state(|s| snapshot.restore(s));
+        assert_eq!(collateral(), Some(vec![market.to_string()]));
+
+        // Replaying part of the history is deduped instead of undoing the later entry
+        let manager = ChainFusionManager::new();
+        block_on(manager.process_events(10143, vec![exited])).unwrap();
+        assert_eq!(collateral(), Some(vec![market.to_string()]));
+        assert!(read_state(|s| ensure_replayable(s, 10143, 100)).is_ok());
+    }
+}
@@ -0,0 +1,61 @@
+use alloy::sol;
+
+// Peridot contract ABIs, declared once and shared by every call site.
+sol! {
+    #[sol(rpc)]
+    interface IPeridotController {
+        function getAccountLiquidity(address account) external view returns (uint256, uint256, uint256);
+        function getAllMarkets() external view returns (address[] memory);
+        function liquidateCalculateSeizeTokens(address pTokenBorrowed, address pTokenCollateral, uint256 actualRepayAmount) external view returns (uint256, uint256);
+        function closeFactorMantissa() external view returns (uint256);
+        function liquidationIncentiveMantissa() external view returns (uint256);
+        function enterMarkets(address[] calldata pTokens) external returns (uint256[] memory);
+        function exitMarket(address pTokenAddress) external returns (uint256);
+    }
+
+    #[sol(rpc)]
+    interface IPToken {
+        function mint(uint256 mintAmount) external returns (uint256);
+        function redeem(uint256 redeemTokens) external returns (uint256);
+        function redeemUnderlying(uint256 redeemAmount) external returns (uint256);
+        function borrow(uint256 borrowAmount) external returns (uint256);
+        function repayBorrow(uint256 repayAmount) external returns (uint256);
+        function liquidateBorrow(address borrower, uint256 repayAmount, address pTokenCollateral) external returns (uint256);
+        function balanceOf(address owner) external view returns (uint256);
+        function borrowBalanceStored(address account) external view returns (uint256);
+        function borrowBalanceCurrent(address account) external returns (uint256);
+        function supplyRatePerBlock() external view returns (uint256);
+        function borrowRatePerBlock() external view returns (uint256);
+        function totalSupply() external view returns (uint256);
+        function totalBorrows() external view returns (uint256);
+        function totalReserves() external view returns (uint256);
+        function getCash() external view returns (uint256);
+        function exchangeRateStored() external view returns (uint256);
+        function accrualBlockNumber() external view returns (uint256);
+        function borrowIndex() external view returns (uint256);
+        function underlying() external view returns (address);
+    }
+
+    /// pToken markets for the chain's native gas token take `msg.value` instead of an amount.
+    #[sol(rpc)]
+    interface IPNativeToken {
+        function mint() external payable;
+    }
+
+    #[sol(rpc)]
+    interface IERC20 {
+        function balanceOf(address account) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function approve(address spender, uint256 amount) external returns (bool);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function symbol() external view returns (string memory);
+        function decimals() external view returns (uint8);
+
+        event Transfer(address indexed from, address indexed to, uint256 value);
+    }
+
+    #[sol(rpc)]
+    interface IPriceOracle {
+        function getUnderlyingPrice(address pToken) external view returns (uint256);
+    }
+}
@@ -1,13 +1,81 @@
-use alloy::primitives::Address;
+use crate::bridge::{self, BridgeAdapter, BridgeReceipt, MockBridgeAdapter};
+use crate::contracts::{IERC20, IPNativeToken, IPToken};
+use crate::cycles;
+use crate::enhanced_api::canonical_address;
+use crate::gas_price;
+use crate::guard::ExecutionGuard;
+use crate::liquidation_cooldown;
+use crate::logger;
+use crate::math;
+use crate::metrics;
+use crate::position_limits;
+use crate::price_oracle;
+use crate::request_store;
+use crate::retry_queue;
+use crate::risk_parameters;
+use crate::source_proof::SourceProof;
+use crate::rpc_manager;
+use crate::token_metadata;
+use crate::state::{mutate_state, read_state};
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{address, hex, keccak256, Address, Bytes, B256, U256};
 use alloy::providers::{Provider, ProviderBuilder};
-use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::{Log, TransactionRequest};
 use alloy::transports::icp::{IcpConfig, RpcService, RpcApi};
 use alloy::network::{TxSigner, TransactionBuilder};
 use alloy::signers::icp::IcpSigner;
 use alloy::network::EthereumWallet;
+use alloy::sol_types::{SolCall, SolEvent, SolValue};
 use candid::{CandidType, Deserialize};
 use serde::{Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Head-block polls (one outcall each) spent waiting for `target_confirmations`.
+const MAX_CONFIRMATION_POLLS: u32 = 30;
+/// Block time assumed for a chain without a config when spacing confirmation polls.
+const DEFAULT_BLOCK_TIME_MS: u64 = 1_000;
+/// Longest wait between two confirmation polls.
+const MAX_CONFIRMATION_POLL_DELAY: Duration = Duration::from_secs(30);
+
+/// Wait before the next head poll: roughly the time for the missing blocks to be
+/// produced, at least one second and at most `MAX_CONFIRMATION_POLL_DELAY`.
+fn confirmation_poll_delay(missing_blocks: u64, block_time_ms: u64) -> Duration {
+    Duration::from_millis(missing_blocks.saturating_mul(block_time_ms))
+        .clamp(Duration::from_secs(1), MAX_CONFIRMATION_POLL_DELAY)
+}
+
+/// A transaction the node accepted, checkpointed before its receipt is awaited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SentTransaction {
+    tx_hash: String,
+    sent_at: u64, // nanoseconds
+    /// Nonce and legacy gas price the transaction was signed with, so it can be
+    /// replaced at a higher fee.
+    #[serde(default)]
+    nonce: Option<u64>,
+    #[serde(default)]
+    gas_price: Option<u128>,
+}
+
+/// Checkpoint holding the gas price shared by an approve and the mint it enables.
+const LINKED_GAS_PRICE_STEP: &str = "linked_gas_price";
+
+/// A replacement must outbid the stuck transaction by at least 10% to be
+/// accepted by the mempool; bump by 1/8 (12.5%).
+const FEE_BUMP_DIVISOR: u128 = 8;
+
+/// Gas price for replacing a transaction priced at `previous`: at least a
+/// mempool-acceptable bump, and no lower than the chain's current price.
+fn bumped_gas_price(previous: u128, current: u128) -> u128 {
+    previous.saturating_add(previous.div_ceil(FEE_BUMP_DIVISOR)).max(current)
+}
+
+/// Checkpoint name for the broadcast of a request's `action` transaction.
+fn broadcast_step(action: &str) -> String {
+    format!("{}_broadcast", action)
+}
 
 // ===== REAL CROSS-CHAIN CONFIGURATION =====
 
@@ -34,7 +102,7 @@ impl Default for CrossChainConfig {
     fn default() -> Self {
         let mut supported_chains = HashMap::new();
         
-        // BNB Testnet (only source chain for initial testing)
+        // BNB Testnet
         supported_chains.insert(97, ChainInfo {
             name: "BNB Testnet".to_string(),
             _rpc_url: "https://data-seed-prebsc-1-s1.binance.org:8545".to_string(),
@@ -50,10 +118,51 @@ impl Default for CrossChainConfig {
             _gas_token_symbol: "BNB".to_string(),
         });
 
+        // Ethereum Sepolia
+        supported_chains.insert(11155111, ChainInfo {
+            name: "Ethereum Sepolia".to_string(),
+            _rpc_url: "https://ethereum-sepolia-rpc.publicnode.com".to_string(),
+            _supported_assets: {
+                let mut assets = HashMap::new();
+                assets.insert("USDC".to_string(), address!("1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"));
+                assets.insert("WETH".to_string(), address!("fFf9976782d46CC05630D1f6eBAb18b2324d6B14"));
+                assets.insert("ETH".to_string(), Address::ZERO);
+                assets
+            },
+            _gas_token_symbol: "ETH".to_string(),
+        });
+
+        // Ethereum mainnet moves real funds, so it is only accepted once an operator opts in
+        if read_state(|s| s.mainnet_sources_enabled) {
+            supported_chains.insert(1, ChainInfo {
+                name: "Ethereum Mainnet".to_string(),
+                _rpc_url: "https://ethereum-rpc.publicnode.com".to_string(),
+                _supported_assets: {
+                    let mut assets = HashMap::new();
+                    assets.insert("USDC".to_string(), address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"));
+                    assets.insert("WETH".to_string(), address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+                    assets.insert("ETH".to_string(), Address::ZERO);
+                    assets
+                },
+                _gas_token_symbol: "ETH".to_string(),
+            });
+        }
+
+        let monad_chain_id = 10143;  // Monad Testnet (target) - CORRECTED
+
+        // Prefer the controller configured in state over the built-in deployment
+        let monad_peridot_controller = read_state(|s| {
+            s.chain_configs.get(&monad_chain_id).and_then(|c| c.controller_address().ok())
+        }).unwrap_or_else(|| Address::parse_checksummed("0xa41D586530BC7BC872095950aE03a780d5114445", None).unwrap());
+
+        // Prefer the first configured RPC URL so runtime overrides take effect
+        let monad_rpc_url = read_state(|s| s.rpc_urls.get(&monad_chain_id).and_then(|urls| urls.first().cloned()))
+            .unwrap_or_else(|| "https://testnet-rpc.monad.xyz".to_string());
+
         Self {
-            monad_chain_id: 10143,  // Monad Testnet (target) - CORRECTED
-            monad_rpc_url: "https://testnet-rpc.monad.xyz".to_string(),
-            monad_peridot_controller: Address::parse_checksummed("0xa41D586530BC7BC872095950aE03a780d5114445", None).unwrap(),
+            monad_chain_id,
+            monad_rpc_url,
+            monad_peridot_controller,
             supported_source_chains: supported_chains,
         }
     }
@@ -65,7 +174,7 @@ impl Default for CrossChainConfig {
 pub struct CrossChainRequest {
     pub user_address: String,            // User's address on source chain
     pub source_chain_id: u64,            // Chain where user initiates (ETH, Polygon, etc.)
-    pub target_chain_id: u64,            // Always Monad (10143) for Peridot
+    pub target_chain_id: u64,            // Chain with a Peridot deployment (Monad 10143)
     pub action: PeridotAction,            // What to do on Monad
     pub amount: String,                   // Amount in wei/smallest unit
     pub asset_address: String,           // Asset contract on source chain
@@ -73,21 +182,120 @@ pub struct CrossChainRequest {
     pub deadline: u64,                   // Transaction deadline
 }
 
+impl CrossChainRequest {
+    /// The single canonical encoding of a request. Every feature that hashes or
+    /// signs requests (idempotency keys, dedupe, EIP-712) must use these bytes,
+    /// never the JSON or candid form.
+    ///
+    /// Layout is `abi.encode(user, sourceChainId, targetChainId, actionKind,
+    /// actionFields, amount, asset, maxGasPrice, deadline)` with types
+    /// `(address, uint64, uint64, string, bytes, uint256, address, uint64, uint64)`,
+    /// where `actionFields` is the ABI encoding of the action's own fields. Fails
+    /// if an address or amount does not parse.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, String> {
+        let user = parse_address("user", &self.user_address)?;
+        Ok((
+            user,
+            self.source_chain_id,
+            self.target_chain_id,
+            self.action.kind().to_string(),
+            self.action.canonical_fields(user)?,
+            CrossChainTransactionHandler::parse_amount(&self.amount)?,
+            parse_address("asset", &self.asset_address)?,
+            self.max_gas_price,
+            self.deadline,
+        )
+            .abi_encode_params())
+    }
+}
+
+fn parse_address(field: &str, value: &str) -> Result<Address, String> {
+    Address::from_str(value).map_err(|e| format!("Invalid {} address '{}': {}", field, value, e))
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub enum PeridotAction {
     Supply { underlying_asset: String },
     Redeem { p_token_amount: String },
-    Borrow { underlying_asset: String },
+    RedeemUnderlying { underlying_asset: String, underlying_amount: String },
+    Borrow {
+        underlying_asset: String,
+        recipient_address: Option<String>, // payout address on the source chain; defaults to the user
+    },
     RepayBorrow { underlying_asset: String },
     LiquidateBorrow {
         borrower: String,
         underlying_asset: String,
         collateral_asset: String,
+        auto_claim: Option<bool>, // redeem seized collateral and bridge it to the liquidator
     },
     EnableCollateral { p_token: String },
     DisableCollateral { p_token: String },
 }
 
+impl PeridotAction {
+    /// Variant names, as accepted by `set_action_enabled`.
+    pub const KINDS: [&'static str; 8] = [
+        "Supply",
+        "Redeem",
+        "RedeemUnderlying",
+        "Borrow",
+        "RepayBorrow",
+        "LiquidateBorrow",
+        "EnableCollateral",
+        "DisableCollateral",
+    ];
+
+    /// ABI encoding of the variant's fields in declaration order, as used by
+    /// `CrossChainRequest::canonical_bytes`. Optional fields encode their
+    /// effective value: a borrow without a recipient pays `user`, and
+    /// `auto_claim` defaults to false.
+    pub fn canonical_fields(&self, user: Address) -> Result<Bytes, String> {
+        let amount = CrossChainTransactionHandler::parse_amount;
+        let encoded = match self {
+            PeridotAction::Supply { underlying_asset } => {
+                (parse_address("underlying", underlying_asset)?,).abi_encode_params()
+            }
+            PeridotAction::Redeem { p_token_amount } => (amount(p_token_amount)?,).abi_encode_params(),
+            PeridotAction::RedeemUnderlying { underlying_asset, underlying_amount } => {
+                (parse_address("underlying", underlying_asset)?, amount(underlying_amount)?).abi_encode_params()
+            }
+            PeridotAction::Borrow { underlying_asset, recipient_address } => (
+                parse_address("underlying", underlying_asset)?,
+                recipient_address.as_deref().map(|r| parse_address("recipient", r)).transpose()?.unwrap_or(user),
+            )
+                .abi_encode_params(),
+            PeridotAction::RepayBorrow { underlying_asset } => {
+                (parse_address("underlying", underlying_asset)?,).abi_encode_params()
+            }
+            PeridotAction::LiquidateBorrow { borrower, underlying_asset, collateral_asset, auto_claim } => (
+                parse_address("borrower", borrower)?,
+                parse_address("underlying", underlying_asset)?,
+                parse_address("collateral", collateral_asset)?,
+                auto_claim.unwrap_or(false),
+            )
+                .abi_encode_params(),
+            PeridotAction::EnableCollateral { p_token } | PeridotAction::DisableCollateral { p_token } => {
+                (parse_address("pToken", p_token)?,).abi_encode_params()
+            }
+        };
+        Ok(encoded.into())
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PeridotAction::Supply { .. } => "Supply",
+            PeridotAction::Redeem { .. } => "Redeem",
+            PeridotAction::RedeemUnderlying { .. } => "RedeemUnderlying",
+            PeridotAction::Borrow { .. } => "Borrow",
+            PeridotAction::RepayBorrow { .. } => "RepayBorrow",
+            PeridotAction::LiquidateBorrow { .. } => "LiquidateBorrow",
+            PeridotAction::EnableCollateral { .. } => "EnableCollateral",
+            PeridotAction::DisableCollateral { .. } => "DisableCollateral",
+        }
+    }
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct CrossChainResponse {
     pub request_id: String,
@@ -98,9 +306,80 @@ pub struct CrossChainResponse {
     pub actual_amount: Option<String>,
     pub error_message: Option<String>,
     pub estimated_completion_time: Option<u64>,
+    pub claim: Option<CollateralClaim>,    // Seized collateral redeemed and bridged after a liquidation
+    #[serde(default)]
+    pub source_proof: Option<SourceProof>, // Bridge evidence of the source-chain deposit
+    /// Every status the request passed through with its timestamp (seconds), oldest
+    /// first; filled from the request timeline when it finishes.
+    #[serde(default)]
+    pub status_history: Option<Vec<(TransactionStatus, u64)>>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct CalldataArg {
+    pub name: String,
+    pub value: String,
+}
+
+/// Encoded call for an action, as returned by `get_action_calldata`.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ActionCalldata {
+    pub to: String, // pToken market the call is sent to
+    pub function: String, // Solidity signature, e.g. "mint(uint256)"
+    pub selector: String,
+    pub calldata: String, // 0x-prefixed hex
+    pub args: Vec<CalldataArg>,
+}
+
+/// Error returned by the typed execute endpoints. Handler errors are plain
+/// strings; the ones with a known prefix map to their own variant.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum PeridotError {
+    ActionDisabled(String),
+    PriceStale(String),
+    ApprovalRequired(String),
+    InsufficientCycles(String),
+    Busy(String),
+    Failed(String),
+}
+
+impl From<String> for PeridotError {
+    fn from(message: String) -> Self {
+        if let Some(rest) = message.strip_prefix("ActionDisabled: ") {
+            PeridotError::ActionDisabled(rest.to_string())
+        } else if let Some(rest) = message.strip_prefix("PriceStale: ") {
+            PeridotError::PriceStale(rest.to_string())
+        } else if let Some(rest) = message.strip_prefix("InsufficientCycles: ") {
+            PeridotError::InsufficientCycles(rest.to_string())
+        } else if let Some(rest) = message.strip_prefix("Busy: ") {
+            PeridotError::Busy(rest.to_string())
+        } else {
+            PeridotError::Failed(message)
+        }
+    }
+}
+
+impl std::fmt::Display for PeridotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeridotError::ActionDisabled(message) => write!(f, "ActionDisabled: {}", message),
+            PeridotError::PriceStale(message) => write!(f, "PriceStale: {}", message),
+            PeridotError::InsufficientCycles(message) => write!(f, "InsufficientCycles: {}", message),
+            PeridotError::Busy(message) => write!(f, "Busy: {}", message),
+            PeridotError::ApprovalRequired(message) | PeridotError::Failed(message) => f.write_str(message),
+        }
+    }
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct CollateralClaim {
+    pub redeem_tx_hash: String,            // pToken.redeem on Monad
+    pub bridge_tx_hash: Option<String>,    // Transfer back to the liquidator's source chain
+    pub underlying_asset: String,
+    pub amount: String,                    // Underlying received from the redeem
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq, Eq)]
 pub enum TransactionStatus {
     Pending,
     SourceChainProcessing,    // Processing on user's chain
@@ -110,6 +389,14 @@ pub enum TransactionStatus {
     Failed,
 }
 
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct BatchResponse {
+    pub total: u64,
+    pub completed: u64,
+    pub responses: Vec<CrossChainResponse>,
+    pub error_message: Option<String>,   // First hard failure, if execution stopped early
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct GasEstimate {
     pub total_gas_cost_usd: f64,
@@ -119,6 +406,16 @@ pub struct GasEstimate {
     pub estimated_time_seconds: u64,
 }
 
+/// Total amount of `token` sent to `recipient` by the ERC20 `Transfer` events in
+/// a receipt's `logs`.
+pub(crate) fn transferred_to(logs: &[Log], token: Address, recipient: Address) -> U256 {
+    logs.iter()
+        .filter(|log| log.address() == token)
+        .filter_map(|log| IERC20::Transfer::decode_log(&log.inner, true).ok())
+        .filter(|transfer| transfer.to == recipient)
+        .fold(U256::ZERO, |total, transfer| total.saturating_add(transfer.value))
+}
+
 // ===== REAL CROSS-CHAIN TRANSACTION HANDLER =====
 
 pub struct CrossChainTransactionHandler;
@@ -126,291 +423,871 @@ pub struct CrossChainTransactionHandler;
 impl CrossChainTransactionHandler {
     /// Execute a real cross-chain transaction to Monad Peridot contracts
     pub async fn execute_cross_chain_action(request: CrossChainRequest) -> Result<CrossChainResponse, String> {
+        Self::execute_cross_chain_action_with(request, &Self::default_bridge()).await
+    }
+
+    /// Execute a cross-chain transaction, moving assets through the given bridge
+    pub async fn execute_cross_chain_action_with<B: BridgeAdapter>(
+        request: CrossChainRequest,
+        bridge: &B,
+    ) -> Result<CrossChainResponse, String> {
+        cycles::ensure_cycles("cross-chain execution")?;
         // Validate request
         Self::validate_request(&request)?;
+        if read_state(|s| s.chain_time_deadlines) {
+            Self::check_deadline_on_chain(&request).await?;
+        }
+        let request_id = Self::generate_request_id(&request)?;
         
-        let config = CrossChainConfig::default();
-        let request_id = Self::generate_request_id(&request);
+        let _slot = Self::admit(&request_id, &request)?;
         
-        ic_cdk::print(&format!("🔄 Starting cross-chain transaction: {} -> Monad", 
+        if let PeridotAction::LiquidateBorrow { borrower, .. } = &request.action {
+            liquidation_cooldown::ensure_ready(borrower, Self::current_timestamp())?;
+        }
+
+        let config = CrossChainConfig::default();
+        logger::log(format!("🔄 Starting cross-chain transaction: {} -> Monad",
             config.supported_source_chains.get(&request.source_chain_id)
                 .map(|c| c.name.as_str()).unwrap_or("Unknown")));
         
-        match &request.action {
-            PeridotAction::Supply { underlying_asset: _ } => {
-                Self::execute_cross_chain_supply(request, config, request_id).await
-            },
-            PeridotAction::Borrow { underlying_asset: _ } => {
-                Self::execute_cross_chain_borrow(request, config, request_id).await
-            },
-            PeridotAction::LiquidateBorrow { borrower: _, underlying_asset: _, collateral_asset: _ } => {
-                Self::execute_cross_chain_liquidation(request, config, request_id).await
-            },
-            _ => Err("Action not yet implemented for cross-chain".to_string()),
+        request_store::create(&request_id, &request)?;
+        let result = Self::run_request(request.clone(), request_id.clone(), bridge).await;
+        if let Err(e) = &result {
+            retry_queue::record_failure(&request_id, &request, e, 1);
+        }
+        result
+    }
+
+    /// Take an execution slot for a new request. When every slot is taken the
+    /// request is rejected, or, with `queue_busy_executions`, stored as `Pending`
+    /// and parked in the retry queue.
+    fn admit(request_id: &str, request: &CrossChainRequest) -> Result<ExecutionGuard, String> {
+        ExecutionGuard::new().or_else(|busy| {
+            if !read_state(|s| s.queue_busy_executions) {
+                return Err(busy);
+            }
+            request_store::create(request_id, request)?;
+            request_store::transition(request_id, TransactionStatus::Pending, Some(format!("queued: {}", busy)));
+            retry_queue::record_failure(request_id, request, &busy, 1);
+            Err(format!("{}; queued as {}", busy, request_id))
+        })
+    }
+
+    /// Re-run a previously failed request under its original id, skipping any
+    /// checkpointed steps.
+    pub async fn retry(request: CrossChainRequest, request_id: String) -> Result<CrossChainResponse, String> {
+        cycles::ensure_cycles("cross-chain retry")?;
+        let _slot = ExecutionGuard::new()?;
+        if let PeridotAction::LiquidateBorrow { borrower, .. } = &request.action {
+            liquidation_cooldown::ensure_ready(borrower, Self::current_timestamp())?;
+        }
+        Self::run_request(request, request_id, &Self::default_bridge()).await
+    }
+
+    /// Resume requests left in flight by an upgrade through the default bridge.
+    pub async fn resume_in_flight() {
+        Self::resume_in_flight_with(&Self::default_bridge()).await
+    }
+
+    /// Resume requests left in flight by an upgrade, moving assets through the
+    /// given bridge. Steps with a checkpoint are skipped, so transactions that
+    /// already went out are not sent again; safety checks in front of a step that
+    /// has not run yet are evaluated again. Each resumed request takes an execution
+    /// slot; requests that find none, and requests already queued for retry, are
+    /// left to the retry queue.
+    pub async fn resume_in_flight_with<B: BridgeAdapter>(bridge: &B) {
+        for record in request_store::in_flight() {
+            if read_state(|s| s.retry_queue.contains_key(&record.request_id)) {
+                continue;
+            }
+            let _slot = match ExecutionGuard::new() {
+                Ok(slot) => slot,
+                Err(busy) => {
+                    retry_queue::record_failure(&record.request_id, &record.request, &busy, 1);
+                    continue;
+                }
+            };
+            logger::log(format!("Resuming cross-chain request {} from {} completed step(s)",
+                record.request_id, record.checkpoints.len()));
+            let _ = Self::run_request(record.request, record.request_id, bridge).await;
+        }
+    }
+
+    /// The bridge used when the caller does not supply one.
+    fn default_bridge() -> MockBridgeAdapter {
+        MockBridgeAdapter { monad_chain_id: CrossChainConfig::default().monad_chain_id }
+    }
+
+    async fn run_request<B: BridgeAdapter>(
+        request: CrossChainRequest,
+        request_id: String,
+        bridge: &B,
+    ) -> Result<CrossChainResponse, String> {
+        let config = CrossChainConfig::default();
+        let kind = request.action.kind();
+        let borrower = match &request.action {
+            PeridotAction::LiquidateBorrow { borrower, .. } => Some(borrower.clone()),
+            _ => None,
+        };
+        let mut result = if read_state(|s| s.paused) {
+            Err("ActionDisabled: the canister is paused".to_string())
+        } else if read_state(|s| s.disabled_actions.contains(kind)) {
+            Err(format!("ActionDisabled: {} actions are disabled", kind))
+        } else {
+            match &request.action {
+                PeridotAction::Supply { underlying_asset: _ } => {
+                    Self::execute_cross_chain_supply(request, config, request_id.clone(), bridge).await
+                },
+                PeridotAction::Borrow { .. } => {
+                    Self::execute_cross_chain_borrow(request, config, request_id.clone(), bridge).await
+                },
+                PeridotAction::LiquidateBorrow { .. } => {
+                    Self::execute_cross_chain_liquidation(request, config, request_id.clone(), bridge).await
+                },
+                PeridotAction::Redeem { .. } | PeridotAction::RedeemUnderlying { .. } => {
+                    Self::execute_cross_chain_redeem(request, config, request_id.clone(), bridge).await
+                },
+                _ => Err("Action not yet implemented for cross-chain".to_string()),
+            }
+        };
+
+        if let (Ok(_), Some(borrower)) = (&result, &borrower) {
+            liquidation_cooldown::record_liquidation(borrower, Self::current_timestamp());
+        }
+        request_store::finish(&request_id, &mut result);
+        result
+    }
+
+    /// Execute several cross-chain actions in order (e.g. supply collateral, then borrow).
+    ///
+    /// Every request is validated before anything is sent. Execution is sequential and
+    /// stops at the first failure, returning the responses of the actions that already
+    /// completed. This is NOT atomic on-chain: completed actions are not rolled back.
+    pub async fn execute_batch(requests: Vec<CrossChainRequest>) -> Result<BatchResponse, String> {
+        if requests.is_empty() {
+            return Err("Batch contains no actions".to_string());
+        }
+        for (index, request) in requests.iter().enumerate() {
+            Self::validate_request(request).map_err(|e| format!("Action {} invalid: {}", index, e))?;
+        }
+
+        let total = requests.len() as u64;
+        let mut responses = Vec::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            match Self::execute_cross_chain_action(request).await {
+                Ok(response) => responses.push(response),
+                Err(e) => {
+                    return Ok(BatchResponse {
+                        total,
+                        completed: responses.len() as u64,
+                        responses,
+                        error_message: Some(format!("Action {} failed: {}", index, e)),
+                    });
+                }
+            }
         }
+
+        Ok(BatchResponse {
+            total,
+            completed: total,
+            responses,
+            error_message: None,
+        })
     }
     
     /// Execute cross-chain supply: User on Source Chain -> Supply to Monad Peridot
-    async fn execute_cross_chain_supply(
+    async fn execute_cross_chain_supply<B: BridgeAdapter>(
         request: CrossChainRequest, 
         config: CrossChainConfig, 
-        request_id: String
+        request_id: String,
+        bridge: &B,
     ) -> Result<CrossChainResponse, String> {
-        ic_cdk::print("💰 Executing cross-chain supply to Monad Peridot");
+        logger::log("💰 Executing cross-chain supply to Monad Peridot");
         
         // Step 1: Get or create user's representation on Monad
-        let monad_user_address = Self::get_or_create_monad_address(&request.user_address).await?;
+        let monad_user_address: String = request_store::run_step(&request_id, "monad_user_address", async {
+            request_store::transition(&request_id, TransactionStatus::SourceChainProcessing, None);
+            Self::get_or_create_monad_address(&request.user_address).await
+        }).await?;
         
         // Step 2: Handle asset bridging/conversion if needed
-        let monad_asset_amount = Self::bridge_asset_to_monad(
-            &request.asset_address,
-            &request.amount,
-            request.source_chain_id,
-            &config
-        ).await?;
-        
-        // Step 3: Execute supply transaction on Monad using threshold ECDSA
-        let monad_tx_hash = Self::execute_monad_supply(
-            &monad_user_address,
-            &monad_asset_amount.asset_address,
-            &monad_asset_amount.amount,
-            &config
-        ).await?;
+        let monad_asset_amount: BridgeReceipt = request_store::run_step(&request_id, "bridge", async {
+            request_store::transition(&request_id, TransactionStatus::CrossChainBridging, None);
+            bridge.bridge(
+                request.source_chain_id,
+                config.monad_chain_id,
+                &request.asset_address,
+                &request.amount,
+            ).await
+        }).await?;
+        if let Some(proof) = &monad_asset_amount.source_proof {
+            request_store::record_source_proof(&request_id, proof.clone());
+        }
+
+        // Step 3: ERC20 markets pull the asset, so approve first. The approve and the
+        // mint are a linked pair: they share one gas price, an approve that is not
+        // mined is replaced at a bumped price that the mint then also pays, and the
+        // mint is cancelled if the approve fails rather than being sent without an
+        // allowance.
+        let linked_gas_price = if Self::is_native_asset(&monad_asset_amount.asset_address) {
+            None
+        } else {
+            let gas_price: u128 = request_store::run_step(&request_id, LINKED_GAS_PRICE_STEP, async {
+                gas_price::gas_price_wei(config.monad_chain_id).await
+            }).await?;
+            let approved: Result<Option<String>, String> = request_store::run_step(&request_id, "approve_tx", async {
+                request_store::transition(&request_id, TransactionStatus::TargetChainProcessing, None);
+                Self::ensure_allowance(&request_id, &monad_asset_amount.asset_address, &monad_asset_amount.amount, gas_price, &config).await
+            }).await;
+            let gas_price = match approved {
+                Ok(_approve_tx_hash) => gas_price,
+                Err(e) if e.contains("was not mined") => {
+                    Self::bump_linked_approve(&request_id, &monad_asset_amount.asset_address, &monad_asset_amount.amount, gas_price, &config).await
+                }
+                Err(e) => Err(e),
+            }.map_err(|e| format!("LinkedFailure: supply cancelled because its approve failed: {}", e))?;
+            Some(gas_price)
+        };
+
+        // Step 4: Execute supply transaction on Monad using threshold ECDSA
+        let monad_tx_hash: String = request_store::run_step(&request_id, "supply_tx", async {
+            request_store::transition(&request_id, TransactionStatus::TargetChainProcessing, None);
+            Self::execute_monad_supply(
+                &request_id,
+                &monad_user_address,
+                &monad_asset_amount.asset_address,
+                &monad_asset_amount.amount,
+                linked_gas_price,
+                &config
+            ).await
+        }).await?;
         
         Ok(CrossChainResponse {
             request_id,
             status: TransactionStatus::Completed,
-            source_tx_hash: None, // Could add source chain transaction if doing actual bridging
+            source_tx_hash: monad_asset_amount.tx_hash,
             target_tx_hash: Some(monad_tx_hash),
             gas_used: Some(150000), // Estimated
             actual_amount: Some(monad_asset_amount.amount),
             error_message: None,
-            estimated_completion_time: Some(Self::current_timestamp() + 300),
+            estimated_completion_time: Some(Self::estimated_completion_time(&request)),
+            claim: None,
+            source_proof: monad_asset_amount.source_proof,
+            status_history: None,
         })
     }
     
     /// Execute cross-chain borrow: User requests from Source Chain -> Borrow on Monad -> Send back
-    async fn execute_cross_chain_borrow(
+    async fn execute_cross_chain_borrow<B: BridgeAdapter>(
         request: CrossChainRequest,
         config: CrossChainConfig,
-        request_id: String
+        request_id: String,
+        bridge: &B,
     ) -> Result<CrossChainResponse, String> {
-        ic_cdk::print("🏦 Executing cross-chain borrow from Monad Peridot");
+        logger::log("🏦 Executing cross-chain borrow from Monad Peridot");
         
         // Step 1: Verify user has sufficient collateral on Monad
-        let monad_user_address = Self::get_or_create_monad_address(&request.user_address).await?;
-        Self::verify_collateral_on_monad(&monad_user_address, &request.amount).await?;
+        let monad_user_address: String = request_store::run_step(&request_id, "monad_user_address", async {
+            request_store::transition(&request_id, TransactionStatus::SourceChainProcessing, None);
+            Self::get_or_create_monad_address(&request.user_address).await
+        }).await?;
+        request_store::run_check(&request_id, "borrow_tx",
+            Self::verify_collateral_on_monad(&monad_user_address, &request.amount)).await?;
         
         // Step 2: Execute borrow on Monad
-        let borrow_tx_hash = Self::execute_monad_borrow(
-            &monad_user_address,
-            &request.asset_address,
-            &request.amount,
-            &config
-        ).await?;
-        
-        // Step 3: Bridge borrowed assets back to user's source chain
-        let source_tx_hash = Self::bridge_assets_to_source_chain(
-            &request.user_address,
-            &request.asset_address,
-            &request.amount,
-            request.source_chain_id,
-            &config
-        ).await?;
+        let underlying_asset = match &request.action {
+            PeridotAction::Borrow { underlying_asset, .. } => underlying_asset,
+            _ => &request.asset_address,
+        };
+        let borrow_tx_hash: String = request_store::run_step(&request_id, "borrow_tx", async {
+            request_store::transition(&request_id, TransactionStatus::TargetChainProcessing, None);
+            Self::execute_monad_borrow(
+                &request_id,
+                &monad_user_address,
+                underlying_asset,
+                &request.amount,
+                &config
+            ).await
+        }).await?;
+
+        // Step 3: Bridge borrowed assets back to the recipient on the source chain
+        let recipient = Self::borrow_recipient(&request);
+        let receipt: BridgeReceipt = request_store::run_step(&request_id, "bridge", async {
+            request_store::transition(&request_id, TransactionStatus::CrossChainBridging, Some(borrow_tx_hash.clone()));
+            if recipient.eq_ignore_ascii_case(&request.user_address) {
+                bridge.bridge(
+                    config.monad_chain_id,
+                    request.source_chain_id,
+                    &request.asset_address,
+                    &request.amount,
+                ).await
+            } else {
+                bridge.bridge_to(
+                    config.monad_chain_id,
+                    request.source_chain_id,
+                    &request.asset_address,
+                    &request.amount,
+                    recipient,
+                ).await
+            }
+        }).await?;
         
         Ok(CrossChainResponse {
             request_id,
             status: TransactionStatus::Completed,
-            source_tx_hash: Some(source_tx_hash),
+            source_tx_hash: receipt.tx_hash,
             target_tx_hash: Some(borrow_tx_hash),
             gas_used: Some(200000),
             actual_amount: Some(request.amount),
             error_message: None,
-            estimated_completion_time: Some(Self::current_timestamp() + 400),
+            estimated_completion_time: Some(Self::estimated_completion_time(&request)),
+            claim: None,
+            source_proof: None,
+            status_history: None,
         })
     }
     
-    /// Execute cross-chain liquidation
-    async fn execute_cross_chain_liquidation(
+    /// Execute cross-chain redeem: Redeem on Monad Peridot -> Send underlying back
+    async fn execute_cross_chain_redeem<B: BridgeAdapter>(
         request: CrossChainRequest,
         config: CrossChainConfig,
-        request_id: String
+        request_id: String,
+        bridge: &B,
     ) -> Result<CrossChainResponse, String> {
-        ic_cdk::print("⚡ Executing cross-chain liquidation on Monad Peridot");
+        logger::log("💸 Executing cross-chain redeem from Monad Peridot");
         
-        if let PeridotAction::LiquidateBorrow { borrower, underlying_asset, collateral_asset } = &request.action {
-            // Execute liquidation directly on Monad
-            let liquidation_tx_hash = Self::execute_monad_liquidation(
-                &request.user_address,  // liquidator
-                borrower,
-                underlying_asset,
-                collateral_asset,
+        // Step 1: Redeem on Monad, either a pToken quantity or an exact underlying amount
+        let redeem_tx_hash: String = request_store::run_step(&request_id, "redeem_tx", async {
+            request_store::transition(&request_id, TransactionStatus::TargetChainProcessing, None);
+            let underlying_asset = match &request.action {
+                PeridotAction::RedeemUnderlying { underlying_asset, .. } => underlying_asset,
+                _ => &request.asset_address,
+            };
+            let market = Self::monad_market(underlying_asset, &config).await?;
+            let redeem_call_data = Self::encode_peridot_redeem_call(&request.action)?;
+            Self::send_monad_transaction_to(&request_id, market, U256::ZERO, "redeem", redeem_call_data, 180000, &config).await
+        }).await?;
+
+        // Step 2: Bridge redeemed underlying back to user's source chain
+        let receipt: BridgeReceipt = request_store::run_step(&request_id, "bridge", async {
+            request_store::transition(&request_id, TransactionStatus::CrossChainBridging, Some(redeem_tx_hash.clone()));
+            bridge.bridge(
+                config.monad_chain_id,
+                request.source_chain_id,
+                &request.asset_address,
                 &request.amount,
-                &config
-            ).await?;
+            ).await
+        }).await?;
+
+        Ok(CrossChainResponse {
+            request_id,
+            status: TransactionStatus::Completed,
+            source_tx_hash: receipt.tx_hash,
+            target_tx_hash: Some(redeem_tx_hash),
+            gas_used: Some(180000),
+            actual_amount: Some(request.amount),
+            error_message: None,
+            estimated_completion_time: Some(Self::estimated_completion_time(&request)),
+            claim: None,
+            source_proof: None,
+            status_history: None,
+        })
+    }
+
+    /// Execute cross-chain liquidation, optionally claiming the seized collateral
+    async fn execute_cross_chain_liquidation<B: BridgeAdapter>(
+        request: CrossChainRequest,
+        config: CrossChainConfig,
+        request_id: String,
+        bridge: &B,
+    ) -> Result<CrossChainResponse, String> {
+        logger::log("⚡ Executing cross-chain liquidation on Monad Peridot");
+
+        if let PeridotAction::LiquidateBorrow { borrower, underlying_asset, collateral_asset, auto_claim } = &request.action {
+            let auto_claim = auto_claim.unwrap_or(false);
+            let collateral = Address::from_str(collateral_asset)
+                .map_err(|e| format!("Invalid collateral pToken address '{}': {}", collateral_asset, e))?;
+            let borrowed = Self::monad_market(underlying_asset, &config).await?;
+
+            // Never liquidate on prices older than the configured staleness limit
+            request_store::run_check(&request_id, "liquidation_tx",
+                price_oracle::ensure_fresh_prices(config.monad_chain_id, &[borrowed, collateral])).await?;
+
+            // Reject repays the comptroller would revert for exceeding the close factor
+            request_store::run_check(&request_id, "liquidation_tx", async {
+                request_store::transition(&request_id, TransactionStatus::SourceChainProcessing, None);
+                Self::check_close_factor(borrower, borrowed, &request.amount, &config).await
+            }).await?;
+
+            // Execute liquidation directly on Monad
+            let liquidation_tx_hash: String = request_store::run_step(&request_id, "liquidation_tx", async {
+                request_store::transition(&request_id, TransactionStatus::TargetChainProcessing, None);
+                Self::execute_monad_liquidation(
+                    &request_id,
+                    &request.user_address,  // liquidator
+                    borrower,
+                    borrowed,
+                    collateral_asset,
+                    &request.amount,
+                    &config
+                ).await
+            }).await?;
+
+            // The liquidation itself is final; a failed claim is reported, not raised
+            let (claim, error_message) = if auto_claim {
+                match Self::claim_seized_collateral(&request, &request_id, collateral, &liquidation_tx_hash, &config, bridge).await {
+                    Ok(claim) => (Some(claim), None),
+                    Err(e) => {
+                        logger::log(format!("Collateral claim for {} failed: {}", request_id, e));
+                        (None, Some(format!("Liquidation succeeded but collateral claim failed: {}", e)))
+                    }
+                }
+            } else {
+                (None, None)
+            };
             
             Ok(CrossChainResponse {
                 request_id,
                 status: TransactionStatus::Completed,
-                source_tx_hash: None,
+                source_tx_hash: claim.as_ref().and_then(|c| c.bridge_tx_hash.clone()),
                 target_tx_hash: Some(liquidation_tx_hash),
                 gas_used: Some(180000),
                 actual_amount: Some(request.amount.clone()),
-                error_message: None,
-                estimated_completion_time: Some(Self::current_timestamp() + 350),
+                error_message,
+                estimated_completion_time: Some(Self::estimated_completion_time(&request)),
+                claim,
+                source_proof: None,
+                status_history: None,
             })
         } else {
             Err("Invalid liquidation action".to_string())
         }
     }
     
+    /// Redeem the pTokens seized by a liquidation and bridge the underlying to the
+    /// liquidator on their source chain. Amounts come from the `Transfer` events in
+    /// each transaction's receipt, not from diffing the canister's balances, which
+    /// other requests running concurrently also move.
+    async fn claim_seized_collateral<B: BridgeAdapter>(
+        request: &CrossChainRequest,
+        request_id: &str,
+        collateral: Address,
+        liquidation_tx_hash: &str,
+        config: &CrossChainConfig,
+        bridge: &B,
+    ) -> Result<CollateralClaim, String> {
+        let mut claim: CollateralClaim = request_store::run_step(request_id, "claim_redeem", async {
+            let canister = Self::canister_evm_address()?;
+            let liquidation_logs = Self::monad_receipt_logs(liquidation_tx_hash, config).await?;
+            let seized = transferred_to(&liquidation_logs, collateral, canister);
+            if seized.is_zero() {
+                return Err("No collateral was seized".to_string());
+            }
+
+            let underlying = Self::monad_underlying(collateral, config).await?;
+            let redeem_call_data = IPToken::redeemCall { redeemTokens: seized }.abi_encode();
+            let redeem_tx_hash = Self::send_monad_transaction_to(request_id, collateral, U256::ZERO, "claim_redeem", redeem_call_data, 180000, config).await?;
+            let redeem_logs = Self::monad_receipt_logs(&redeem_tx_hash, config).await?;
+            let received = transferred_to(&redeem_logs, underlying, canister);
+
+            Ok(CollateralClaim {
+                redeem_tx_hash,
+                bridge_tx_hash: None,
+                underlying_asset: underlying.to_string(),
+                amount: received.to_string(),
+            })
+        }).await?;
+
+        let receipt: BridgeReceipt = request_store::run_step(request_id, "claim_bridge", async {
+            request_store::transition(request_id, TransactionStatus::CrossChainBridging, Some(claim.redeem_tx_hash.clone()));
+            bridge.bridge(
+                config.monad_chain_id,
+                request.source_chain_id,
+                &claim.underlying_asset,
+                &claim.amount,
+            ).await
+        }).await?;
+
+        claim.bridge_tx_hash = receipt.tx_hash;
+        Ok(claim)
+    }
+
+    fn canister_evm_address() -> Result<Address, String> {
+        read_state(|s| s.canister_evm_address)
+            .ok_or_else(|| "Canister EVM address not initialized".to_string())
+    }
+
+    /// Logs of a mined Monad transaction, from its receipt.
+    async fn monad_receipt_logs(tx_hash: &str, config: &CrossChainConfig) -> Result<Vec<Log>, String> {
+        let hash = B256::from_str(tx_hash)
+            .map_err(|e| format!("Invalid transaction hash '{}': {}", tx_hash, e))?;
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(Self::monad_rpc_service(config)));
+        let receipt = provider.get_transaction_receipt(hash).await
+            .map_err(|e| format!("Failed to fetch receipt for {}: {}", tx_hash, e))?
+            .ok_or_else(|| format!("No receipt for {}", tx_hash))?;
+        Ok(receipt.inner.logs().to_vec())
+    }
+
+    /// Underlying token of a Monad pToken market.
+    async fn monad_underlying(p_token: Address, config: &CrossChainConfig) -> Result<Address, String> {
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(Self::monad_rpc_service(config)));
+        IPToken::new(p_token, provider).underlying().call().await
+            .map(|result| result._0)
+            .map_err(|e| format!("underlying() of {} failed: {}", p_token, e))
+    }
+
+    /// The Monad pToken market of an underlying token.
+    async fn monad_market(underlying_asset: &str, config: &CrossChainConfig) -> Result<Address, String> {
+        let underlying = Address::from_str(underlying_asset)
+            .map_err(|e| format!("Invalid underlying asset address '{}': {}", underlying_asset, e))?;
+        token_metadata::market_for_underlying(config.monad_chain_id, underlying).await
+    }
+
+    fn monad_rpc_service(config: &CrossChainConfig) -> RpcService {
+        RpcService::Custom(RpcApi {
+            url: config.monad_rpc_url.clone(),
+            headers: None,
+        })
+    }
+
+    /// Ensure `repay_amount` does not exceed `closeFactor × borrowBalance` for the
+    /// borrower in the borrowed pToken `market`.
+    async fn check_close_factor(
+        borrower: &str,
+        market: Address,
+        repay_amount: &str,
+        config: &CrossChainConfig
+    ) -> Result<(), String> {
+        let borrower = Address::from_str(borrower)
+            .map_err(|e| format!("Invalid borrower address: {}", e))?;
+        let repay_amount = Self::parse_amount(repay_amount)?;
+
+        let params = risk_parameters::get_or_fetch(config.monad_chain_id).await?;
+        let borrow_balance = risk_parameters::read_borrow_balance(config.monad_chain_id, market, borrower).await?;
+        params.check_repay(repay_amount, borrow_balance)
+    }
+
     // ===== MONAD BLOCKCHAIN INTERACTION FUNCTIONS =====
     
     /// Execute supply transaction on Monad Peridot using threshold ECDSA
     async fn execute_monad_supply(
+        request_id: &str,
         _user_address: &str,
         asset_address: &str,
         amount: &str,
+        gas_price: Option<u128>,
         config: &CrossChainConfig
     ) -> Result<String, String> {
-        ic_cdk::print(&format!("🔗 Executing supply on Monad: {} amount {}", asset_address, amount));
+        logger::log(format!("🔗 Executing supply on Monad: {} amount {}", asset_address, amount));
         
-        // Get ICP canister's ECDSA address for Monad
-        let signer = Self::get_threshold_ecdsa_signer().await?;
-        let _canister_address = signer.address();
-        
-        // Create RPC provider for Monad
-        let rpc_service = RpcService::Custom(RpcApi {
-            url: config.monad_rpc_url.clone(),
-            headers: None,
-        });
-        let icp_config = IcpConfig::new(rpc_service);
-        let provider = ProviderBuilder::new()
-            .with_gas_estimation()
-            .wallet(EthereumWallet::new(signer))
-            .on_icp(icp_config);
+        // Native-token markets take the amount as msg.value on a payable mint()
+        if Self::is_native_asset(asset_address) {
+            let value = Self::parse_amount(amount)?;
+            let market = token_metadata::market_for_underlying(config.monad_chain_id, Address::ZERO).await?;
+            return Self::send_monad_transaction_to(request_id, market, value, "supply", Self::encode_native_supply_call(), 150000, config).await;
+        }
         
-        // Create Peridot supply transaction
-        // This would call the pToken.mint(amount) function on Monad
+        // ERC20 markets: pToken.mint(amount) on the asset's market
+        let market = Self::monad_market(asset_address, config).await?;
         let supply_call_data = Self::encode_peridot_supply_call(asset_address, amount)?;
         
-        let mut tx_request = TransactionRequest::default()
-            .to(config.monad_peridot_controller)
-            .input(supply_call_data.into())
-            .gas_limit(150000);
-        
-        tx_request.set_chain_id(config.monad_chain_id);
+        Self::send_monad_transaction_priced(request_id, market, U256::ZERO, "supply", supply_call_data, 150000, gas_price, config).await
+    }
+
+    /// Approve the pToken market the supply mints into for `amount` of
+    /// `asset_address`, unless the canister's allowance already covers it. Returns
+    /// the approve transaction hash, or `None` when no approve was needed.
+    async fn ensure_allowance(
+        request_id: &str,
+        asset_address: &str,
+        amount: &str,
+        gas_price: u128,
+        config: &CrossChainConfig
+    ) -> Result<Option<String>, String> {
+        let (token, approve) = Self::approve_call(asset_address, amount, config).await?;
+        let owner = Self::get_threshold_ecdsa_signer()?.address();
         
-        // Send transaction to Monad
-        match provider.send_transaction(tx_request).await {
-            Ok(pending_tx) => {
-                let tx_hash = format!("{:?}", pending_tx.tx_hash());
-                ic_cdk::print(&format!("✅ Monad supply transaction sent: {}", tx_hash));
-                Ok(tx_hash)
-            },
-            Err(e) => {
-                let error_msg = format!("Failed to send Monad transaction: {}", e);
-                ic_cdk::print(&error_msg);
-                Err(error_msg)
-            }
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(Self::monad_rpc_service(config)));
+        let allowance = IERC20::new(token, provider).allowance(owner, approve.spender).call().await
+            .map_err(|e| format!("Failed to read allowance of {} for {}: {}", token, approve.spender, e))?._0;
+        if allowance >= approve.amount {
+            return Ok(None);
         }
+
+        Self::send_monad_transaction_priced(request_id, token, U256::ZERO, "approve", approve.abi_encode(), 60000, Some(gas_price), config)
+            .await
+            .map(Some)
+    }
+
+    /// `approve(market, amount)` on the token `asset_address`, where `market` is
+    /// the pToken market for that underlying asset.
+    async fn approve_call(asset_address: &str, amount: &str, config: &CrossChainConfig) -> Result<(Address, IERC20::approveCall), String> {
+        let token = Address::from_str(asset_address)
+            .map_err(|e| format!("Invalid asset address {}: {}", asset_address, e))?;
+        let amount = Self::parse_amount(amount)?;
+        let spender = Self::monad_market(asset_address, config).await?;
+        Ok((token, IERC20::approveCall { spender, amount }))
+    }
+
+    /// Replace an approve that was not mined with one at the same nonce and a
+    /// bumped gas price. The bumped price becomes the pair's linked price before
+    /// the replacement is sent, so the mint (now or on a retry) pays it too.
+    async fn bump_linked_approve(
+        request_id: &str,
+        asset_address: &str,
+        amount: &str,
+        gas_price: u128,
+        config: &CrossChainConfig
+    ) -> Result<u128, String> {
+        let stuck: SentTransaction = request_store::checkpoint(request_id, &broadcast_step("approve"))
+            .ok_or_else(|| format!("No approve broadcast recorded for {} to replace", request_id))?;
+        let nonce = stuck.nonce
+            .ok_or_else(|| format!("Approve {} has no recorded nonce to replace", stuck.tx_hash))?;
+        let current = gas_price::gas_price_wei(config.monad_chain_id).await?;
+        let bumped = bumped_gas_price(stuck.gas_price.unwrap_or(gas_price), current);
+        logger::log(format!("⛽ Replacing approve {} at {} wei (was {})", stuck.tx_hash, bumped, gas_price));
+        request_store::replace_checkpoint(request_id, LINKED_GAS_PRICE_STEP, &bumped)?;
+
+        let (token, approve) = Self::approve_call(asset_address, amount, config).await?;
+        let replacement = Self::broadcast_monad_transaction(token, U256::ZERO, "approve", approve.abi_encode(), 60000, Some(bumped), Some(nonce), config).await?;
+        request_store::replace_checkpoint(request_id, &broadcast_step("approve"), &replacement)?;
+        let approve_tx_hash = Self::confirm_monad_transaction(replacement, "approve", config).await?;
+        request_store::replace_checkpoint(request_id, "approve_tx", &Some(approve_tx_hash))?;
+        Ok(bumped)
     }
     
     /// Execute borrow transaction on Monad Peridot
     async fn execute_monad_borrow(
+        request_id: &str,
         _user_address: &str,
         asset_address: &str,
         amount: &str,
         config: &CrossChainConfig
     ) -> Result<String, String> {
-        ic_cdk::print(&format!("🏦 Executing borrow on Monad: {} amount {}", asset_address, amount));
-        
-        // Similar to supply but calls pToken.borrow(amount)
-        let signer = Self::get_threshold_ecdsa_signer().await?;
-        let rpc_service = RpcService::Custom(RpcApi {
-            url: config.monad_rpc_url.clone(),
-            headers: None,
-        });
-        let icp_config = IcpConfig::new(rpc_service);
-        let provider = ProviderBuilder::new()
-            .with_gas_estimation()
-            .wallet(EthereumWallet::new(signer))
-            .on_icp(icp_config);
+        logger::log(format!("🏦 Executing borrow on Monad: {} amount {}", asset_address, amount));
         
+        // Similar to supply but calls pToken.borrow(amount) on the asset's market
+        let market = Self::monad_market(asset_address, config).await?;
         let borrow_call_data = Self::encode_peridot_borrow_call(asset_address, amount)?;
         
-        let mut tx_request = TransactionRequest::default()
-            .to(config.monad_peridot_controller)
-            .input(borrow_call_data.into())
-            .gas_limit(200000);
-        
-        tx_request.set_chain_id(config.monad_chain_id);
-        
-        match provider.send_transaction(tx_request).await {
-            Ok(pending_tx) => {
-                let tx_hash = format!("{:?}", pending_tx.tx_hash());
-                ic_cdk::print(&format!("✅ Monad borrow transaction sent: {}", tx_hash));
-                Ok(tx_hash)
-            },
-            Err(e) => Err(format!("Failed to send Monad borrow transaction: {}", e))
-        }
+        Self::send_monad_transaction_to(request_id, market, U256::ZERO, "borrow", borrow_call_data, 200000, config).await
     }
     
-    /// Execute liquidation transaction on Monad Peridot
+    /// Execute liquidation transaction on Monad Peridot: `liquidateBorrow` is
+    /// called on the borrowed pToken `market`, whose debt is repaid.
     async fn execute_monad_liquidation(
+        request_id: &str,
         _liquidator_address: &str,
         borrower_address: &str,
-        underlying_asset: &str,
+        market: Address,
         collateral_asset: &str,
         amount: &str,
         config: &CrossChainConfig
     ) -> Result<String, String> {
-        ic_cdk::print(&format!("⚡ Executing liquidation on Monad: borrower {} amount {}", borrower_address, amount));
+        logger::log(format!("⚡ Executing liquidation on Monad: borrower {} amount {}", borrower_address, amount));
         
-        let signer = Self::get_threshold_ecdsa_signer().await?;
-        let rpc_service = RpcService::Custom(RpcApi {
-            url: config.monad_rpc_url.clone(),
-            headers: None,
-        });
-        let icp_config = IcpConfig::new(rpc_service);
+        let liquidation_call_data = Self::encode_peridot_liquidation_call(
+            borrower_address, &market.to_string(), collateral_asset, amount
+        )?;
+
+        Self::send_monad_transaction_to(request_id, market, U256::ZERO, "liquidation", liquidation_call_data, 180000, config).await
+    }
+
+    /// Sign and send a transaction to `to` on Monad for `request_id`, attaching
+    /// `value` wei of the native token, wait for its receipt, and record
+    /// submitted/confirmed/failed metrics for the action.
+    async fn send_monad_transaction_to(
+        request_id: &str,
+        to: Address,
+        value: U256,
+        action: &str,
+        call_data: Vec<u8>,
+        gas_limit: u64,
+        config: &CrossChainConfig
+    ) -> Result<String, String> {
+        Self::send_monad_transaction_priced(request_id, to, value, action, call_data, gas_limit, None, config).await
+    }
+
+    /// `send_monad_transaction_to` with an explicit legacy gas price instead of the
+    /// estimated fees, for transactions that must be priced together.
+    ///
+    /// The hash is checkpointed as soon as the node accepts the transaction, so a
+    /// retry after a receipt or confirmation failure polls that hash again instead
+    /// of broadcasting the action a second time.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_monad_transaction_priced(
+        request_id: &str,
+        to: Address,
+        value: U256,
+        action: &str,
+        call_data: Vec<u8>,
+        gas_limit: u64,
+        gas_price: Option<u128>,
+        config: &CrossChainConfig
+    ) -> Result<String, String> {
+        let sent: SentTransaction = request_store::run_step(request_id, &broadcast_step(action), async {
+            Self::broadcast_monad_transaction(to, value, action, call_data, gas_limit, gas_price, None, config).await
+        }).await?;
+        Self::confirm_monad_transaction(sent, action, config).await
+    }
+
+    /// Wait for `sent` to be mined, and buried to the configured confirmation
+    /// depth, returning its hash.
+    async fn confirm_monad_transaction(sent: SentTransaction, action: &str, config: &CrossChainConfig) -> Result<String, String> {
+        let chain_id = config.monad_chain_id;
+        let tx_hash = sent.tx_hash;
+
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(Self::monad_rpc_service(config)));
+        let hash = B256::from_str(&tx_hash)
+            .map_err(|e| format!("Invalid transaction hash '{}': {}", tx_hash, e))?;
+        let block_time_ms = read_state(|s| s.chain_configs.get(&chain_id).map(|c| c.block_time_ms))
+            .unwrap_or(DEFAULT_BLOCK_TIME_MS);
+
+        let mut receipt = None;
+        for _ in 0..MAX_CONFIRMATION_POLLS {
+            match provider.get_transaction_receipt(hash).await {
+                Ok(Some(found)) => {
+                    receipt = Some(found);
+                    break;
+                }
+                Ok(None) => rpc_manager::sleep(confirmation_poll_delay(1, block_time_ms)).await,
+                Err(e) => {
+                    metrics::record_failed(chain_id, action);
+                    return Err(format!("Failed to fetch receipt for Monad {} transaction {}: {}", action, tx_hash, e));
+                }
+            }
+        }
+        let receipt = match receipt {
+            Some(receipt) if receipt.status() => receipt,
+            Some(_) => {
+                metrics::record_failed(chain_id, action);
+                return Err(format!("Monad {} transaction {} reverted", action, tx_hash));
+            }
+            None => {
+                metrics::record_failed(chain_id, action);
+                return Err(format!("Monad {} transaction {} was not mined after {} polls", action, tx_hash, MAX_CONFIRMATION_POLLS));
+            }
+        };
+
+        // Optionally wait until the block is buried deep enough to survive a reorg
+        let target_confirmations = read_state(|s| s.target_confirmations);
+        if target_confirmations > 0 {
+            let mined_block = receipt.block_number
+                .ok_or_else(|| format!("Receipt for {} has no block number", tx_hash))?;
+            let mut confirmed = false;
+            for _ in 0..MAX_CONFIRMATION_POLLS {
+                let head = provider.get_block_number().await
+                    .map_err(|e| format!("Failed to fetch Monad head block: {}", e))?;
+                let confirmations = head.saturating_sub(mined_block) + 1;
+                if confirmations >= target_confirmations {
+                    confirmed = true;
+                    break;
+                }
+                rpc_manager::sleep(confirmation_poll_delay(target_confirmations - confirmations, block_time_ms)).await;
+            }
+            if !confirmed {
+                metrics::record_failed(chain_id, action);
+                return Err(format!("Monad {} transaction {} did not reach {} confirmations", action, tx_hash, target_confirmations));
+            }
+
+            // The transaction must still be in the same block after the wait
+            let still_mined = provider.get_transaction_receipt(receipt.transaction_hash).await
+                .map_err(|e| format!("Failed to re-check receipt for {}: {}", tx_hash, e))?
+                .and_then(|r| r.block_number) == Some(mined_block);
+            if !still_mined {
+                metrics::record_failed(chain_id, action);
+                return Err(format!("Monad {} transaction {} was reorged out of block {}", action, tx_hash, mined_block));
+            }
+        }
+
+        let elapsed_ms = ic_cdk::api::time().saturating_sub(sent.sent_at) / 1_000_000;
+        metrics::record_confirmed(chain_id, action, elapsed_ms);
+        Ok(tx_hash)
+    }
+
+    /// Sign and broadcast a Monad transaction, returning its hash once the node
+    /// has accepted it. `replacing` signs with that nonce, replacing the pending
+    /// transaction that holds it, instead of the next one.
+    #[allow(clippy::too_many_arguments)]
+    async fn broadcast_monad_transaction(
+        to: Address,
+        value: U256,
+        action: &str,
+        call_data: Vec<u8>,
+        gas_limit: u64,
+        gas_price: Option<u128>,
+        replacing: Option<u64>,
+        config: &CrossChainConfig
+    ) -> Result<SentTransaction, String> {
+        // Get ICP canister's ECDSA address for Monad
+        let signer = Self::get_threshold_ecdsa_signer()?;
+        let canister_address = signer.address();
+
+        // Create RPC provider for Monad
+        let icp_config = IcpConfig::new(Self::monad_rpc_service(config));
         let provider = ProviderBuilder::new()
             .with_gas_estimation()
             .wallet(EthereumWallet::new(signer))
             .on_icp(icp_config);
         
-        let liquidation_call_data = Self::encode_peridot_liquidation_call(
-            borrower_address, underlying_asset, collateral_asset, amount
-        )?;
-        
         let mut tx_request = TransactionRequest::default()
-            .to(config.monad_peridot_controller)
-            .input(liquidation_call_data.into())
-            .gas_limit(180000);
+            .to(to)
+            .value(value)
+            .input(call_data.into())
+            .gas_limit(gas_limit);
         
-        tx_request.set_chain_id(config.monad_chain_id);
+        if let Some(gas_price) = gas_price {
+            tx_request.set_gas_price(gas_price);
+        }
+
+        let chain_id = config.monad_chain_id;
+        tx_request.set_chain_id(chain_id);
+
+        // Track the nonce locally so sequential sends (e.g. batches) don't reuse one
+        // before the RPC node has seen the previous transaction
+        let nonce = match replacing.or_else(|| read_state(|s| s.nonces.get(&chain_id).copied())) {
+            Some(nonce) => nonce,
+            None => provider.get_transaction_count(canister_address).await
+                .map_err(|e| format!("Failed to fetch nonce for {}: {}", canister_address, e))?,
+        };
+        tx_request.set_nonce(nonce);
         
-        match provider.send_transaction(tx_request).await {
+        metrics::record_submitted(chain_id, action);
+        let sent_at = ic_cdk::api::time();
+
+        // Send transaction to Monad
+        let pending_tx = match provider.send_transaction(tx_request).await {
             Ok(pending_tx) => {
-                let tx_hash = format!("{:?}", pending_tx.tx_hash());
-                ic_cdk::print(&format!("✅ Monad liquidation transaction sent: {}", tx_hash));
-                Ok(tx_hash)
+                // A replacement reuses an earlier nonce and must not rewind the counter
+                mutate_state(|s| {
+                    let next = s.nonces.get(&chain_id).map_or(nonce + 1, |&next| next.max(nonce + 1));
+                    s.nonces.insert(chain_id, next)
+                });
+                pending_tx
             },
-            Err(e) => Err(format!("Failed to send Monad liquidation transaction: {}", e))
-        }
+            Err(e) => {
+                // Resync from the chain on the next send
+                mutate_state(|s| s.nonces.remove(&chain_id));
+                metrics::record_failed(chain_id, action);
+                let error_msg = format!("Failed to send Monad {} transaction: {}", action, e);
+                logger::log(&error_msg);
+                return Err(error_msg);
+            }
+        };
+
+        let tx_hash = format!("{:?}", pending_tx.tx_hash());
+        logger::log(format!("✅ Monad {} transaction sent: {}", action, tx_hash));
+
+        Ok(SentTransaction { tx_hash, sent_at, nonce: Some(nonce), gas_price })
     }
     
     // ===== UTILITY FUNCTIONS =====
     
-    /// Get threshold ECDSA signer for cross-chain transactions
-    async fn get_threshold_ecdsa_signer() -> Result<IcpSigner, String> {
-        let key_name = "dfx_test_key"; // Use "key_1" for mainnet
-        match IcpSigner::new(vec![], key_name, None).await {
-            Ok(signer) => {
-                ic_cdk::print(&format!("🔑 Threshold ECDSA signer initialized: {:?}", signer.address()));
-                Ok(signer)
-            },
-            Err(e) => Err(format!("Failed to initialize threshold ECDSA signer: {}", e))
-        }
+    /// Get the threshold ECDSA signer initialized at canister start-up.
+    /// Fails with a "signer not ready" error until initialization has completed.
+    fn get_threshold_ecdsa_signer() -> Result<IcpSigner, String> {
+        read_state(|s| s.signer.clone())
+            .ok_or_else(|| "Signer not ready: threshold ECDSA key initialization is still in progress, retry shortly".to_string())
     }
     
     /// Get or create user's address representation on Monad
@@ -420,107 +1297,245 @@ impl CrossChainTransactionHandler {
         Ok(source_address.to_string())
     }
     
-    /// Bridge assets from source chain to Monad (simplified for MVP)
-    async fn bridge_asset_to_monad(
-        _source_asset: &str,
-        amount: &str,
-        source_chain_id: u64,
-        _config: &CrossChainConfig
-    ) -> Result<MonadAsset, String> {
-        ic_cdk::print(&format!("🌉 Bridging asset from chain {} to Monad", source_chain_id));
-        
-        // For MVP: Assume assets are available on Monad
-        // In production: Implement actual cross-chain bridging
-        Ok(MonadAsset {
-            asset_address: "0x28fE679719e740D15FC60325416bB43eAc50cD15".to_string(), // Mock Monad USDC
-            amount: amount.to_string(),
-        })
-    }
-    
     /// Verify user has sufficient collateral on Monad for borrowing
     async fn verify_collateral_on_monad(user_address: &str, _borrow_amount: &str) -> Result<(), String> {
-        ic_cdk::print(&format!("🔍 Verifying collateral for user {} on Monad", user_address));
+        logger::log(format!("🔍 Verifying collateral for user {} on Monad", user_address));
         
         // For MVP: Skip verification
         // In production: Query Monad Peridot contracts for user's collateral
         Ok(())
     }
     
-    /// Bridge borrowed assets back to user's source chain
-    async fn bridge_assets_to_source_chain(
-        user_address: &str,
-        _asset_address: &str,
-        _amount: &str,
-        source_chain_id: u64,
-        _config: &CrossChainConfig
-    ) -> Result<String, String> {
-        ic_cdk::print(&format!("🌉 Bridging assets back to chain {} for user {}", source_chain_id, user_address));
-        
-        // For MVP: Return mock transaction hash
-        // In production: Execute actual cross-chain transfer
-        Ok("0x1234567890abcdef1234567890abcdef12345678".to_string())
+    /// Encode pToken.mint(uint256)
+    fn encode_peridot_supply_call(_asset_address: &str, amount: &str) -> Result<Vec<u8>, String> {
+        Ok(IPToken::mintCall { mintAmount: Self::parse_amount(amount)? }.abi_encode())
     }
     
-    /// Encode Peridot supply function call
-    fn encode_peridot_supply_call(_asset_address: &str, _amount: &str) -> Result<Vec<u8>, String> {
-        // For MVP: Return mock call data
-        // In production: Use proper ABI encoding for pToken.mint(amount)
-        Ok(vec![0x40, 0xc1, 0x0f, 0x19]) // Mock function selector
+    /// Encode the payable pToken.mint() used by native-token markets
+    fn encode_native_supply_call() -> Vec<u8> {
+        IPNativeToken::mintCall {}.abi_encode()
     }
     
-        /// Encode Peridot borrow function call
-    fn encode_peridot_borrow_call(_asset_address: &str, _amount: &str) -> Result<Vec<u8>, String> {
-        // For MVP: Return mock call data
-        // In production: Use proper ABI encoding for pToken.borrow(amount)
-        Ok(vec![0xc5, 0xea, 0xd9, 0xc0]) // Mock function selector
+    /// The zero address stands for the chain's native gas token in asset maps.
+    fn is_native_asset(asset_address: &str) -> bool {
+        Address::from_str(asset_address).map(|a| a == Address::ZERO).unwrap_or(false)
     }
     
-    /// Encode Peridot liquidation function call
+    /// Encode pToken.borrow(uint256)
+    fn encode_peridot_borrow_call(_asset_address: &str, amount: &str) -> Result<Vec<u8>, String> {
+        Ok(IPToken::borrowCall { borrowAmount: Self::parse_amount(amount)? }.abi_encode())
+    }
+
+    /// Encode pToken.redeem(uint256) or pToken.redeemUnderlying(uint256)
+    fn encode_peridot_redeem_call(action: &PeridotAction) -> Result<Vec<u8>, String> {
+        match action {
+            PeridotAction::Redeem { p_token_amount } => Ok(IPToken::redeemCall {
+                redeemTokens: Self::parse_amount(p_token_amount)?,
+            }.abi_encode()),
+            PeridotAction::RedeemUnderlying { underlying_amount, .. } => Ok(IPToken::redeemUnderlyingCall {
+                redeemAmount: Self::parse_amount(underlying_amount)?,
+            }.abi_encode()),
+            _ => Err("Not a redeem action".to_string()),
+        }
+    }
+
+    /// Calldata the canister would send for `action`, and the market it is sent to,
+    /// built with the same encoders as execution. The arguments are decoded back
+    /// from the encoded bytes rather than copied from the input, so they show
+    /// exactly what is sent. The market comes from the metadata cache, since a
+    /// query cannot look it up on chain.
+    pub fn action_calldata(action: &PeridotAction, asset_address: &str, amount: &str) -> Result<ActionCalldata, String> {
+        let decode_error = |e: alloy::sol_types::Error| format!("Calldata does not decode: {}", e);
+        let underlying = match action {
+            PeridotAction::Supply { .. } if Self::is_native_asset(asset_address) => Address::ZERO.to_string(),
+            PeridotAction::Borrow { underlying_asset, .. }
+            | PeridotAction::RedeemUnderlying { underlying_asset, .. }
+            | PeridotAction::LiquidateBorrow { underlying_asset, .. } => underlying_asset.clone(),
+            _ => asset_address.to_string(),
+        };
+        let monad_chain_id = CrossChainConfig::default().monad_chain_id;
+        let to = Address::from_str(&underlying)
+            .map_err(|e| format!("Invalid underlying asset address '{}': {}", underlying, e))
+            .and_then(|underlying| {
+                read_state(|s| token_metadata::cached_market_for(s, monad_chain_id, underlying))
+                    .ok_or_else(|| format!("Market for underlying {} on chain {} not loaded yet", underlying, monad_chain_id))
+            });
+        let (function, data, args) = match action {
+            PeridotAction::Supply { .. } if Self::is_native_asset(asset_address) => {
+                (IPNativeToken::mintCall::SIGNATURE, Self::encode_native_supply_call(), Vec::new())
+            }
+            PeridotAction::Supply { .. } => {
+                let data = Self::encode_peridot_supply_call(asset_address, amount)?;
+                let call = IPToken::mintCall::abi_decode(&data, true).map_err(decode_error)?;
+                (IPToken::mintCall::SIGNATURE, data, vec![("mintAmount", call.mintAmount.to_string())])
+            }
+            PeridotAction::Borrow { .. } => {
+                let data = Self::encode_peridot_borrow_call(asset_address, amount)?;
+                let call = IPToken::borrowCall::abi_decode(&data, true).map_err(decode_error)?;
+                (IPToken::borrowCall::SIGNATURE, data, vec![("borrowAmount", call.borrowAmount.to_string())])
+            }
+            PeridotAction::Redeem { .. } => {
+                let data = Self::encode_peridot_redeem_call(action)?;
+                let call = IPToken::redeemCall::abi_decode(&data, true).map_err(decode_error)?;
+                (IPToken::redeemCall::SIGNATURE, data, vec![("redeemTokens", call.redeemTokens.to_string())])
+            }
+            PeridotAction::RedeemUnderlying { .. } => {
+                let data = Self::encode_peridot_redeem_call(action)?;
+                let call = IPToken::redeemUnderlyingCall::abi_decode(&data, true).map_err(decode_error)?;
+                (IPToken::redeemUnderlyingCall::SIGNATURE, data, vec![("redeemAmount", call.redeemAmount.to_string())])
+            }
+            PeridotAction::LiquidateBorrow { borrower, underlying_asset, collateral_asset, .. } => {
+                let data = Self::encode_peridot_liquidation_call(borrower, underlying_asset, collateral_asset, amount)?;
+                let call = IPToken::liquidateBorrowCall::abi_decode(&data, true).map_err(decode_error)?;
+                (IPToken::liquidateBorrowCall::SIGNATURE, data, vec![
+                    ("borrower", call.borrower.to_string()),
+                    ("repayAmount", call.repayAmount.to_string()),
+                    ("pTokenCollateral", call.pTokenCollateral.to_string()),
+                ])
+            }
+            other => return Err(format!("{} actions are not sent as calldata", other.kind())),
+        };
+
+        Ok(ActionCalldata {
+            to: to?.to_string(),
+            function: function.to_string(),
+            selector: hex::encode_prefixed(&data[..4]),
+            calldata: hex::encode_prefixed(&data),
+            args: args.into_iter().map(|(name, value)| CalldataArg { name: name.to_string(), value }).collect(),
+        })
+    }
+
+    /// Encode pToken.liquidateBorrow(address,uint256,address)
     fn encode_peridot_liquidation_call(
-        _borrower: &str,
+        borrower: &str,
         _underlying_asset: &str, 
-        _collateral_asset: &str,
-        _amount: &str
+        collateral_asset: &str,
+        amount: &str
     ) -> Result<Vec<u8>, String> {
-        // For MVP: Return mock call data
-        // In production: Use proper ABI encoding for liquidateBorrow()
-        Ok(vec![0xf5, 0xe3, 0xc4, 0x62]) // Mock function selector
+        Ok(IPToken::liquidateBorrowCall {
+            borrower: Address::from_str(borrower)
+                .map_err(|e| format!("Invalid borrower address '{}': {}", borrower, e))?,
+            repayAmount: Self::parse_amount(amount)?,
+            pTokenCollateral: Address::from_str(collateral_asset)
+                .map_err(|e| format!("Invalid collateral pToken address '{}': {}", collateral_asset, e))?,
+        }.abi_encode())
+    }
+
+    /// Parse a decimal (or 0x-prefixed hex) token amount
+    pub fn parse_amount(amount: &str) -> Result<U256, String> {
+        amount.parse::<U256>().map_err(|e| format!("Invalid amount '{}': {}", amount, e))
     }
     
-    /// Generate unique request ID
-    fn generate_request_id(request: &CrossChainRequest) -> String {
-        format!("ccreq_{}_{}_{}", request.source_chain_id, request.target_chain_id, Self::current_timestamp())
+    /// `ccreq_{content hash}_{counter}`: the first 8 bytes of the keccak256 of the
+    /// request's canonical encoding, so identical requests share a prefix, and a
+    /// canister-wide counter that keeps every id unique.
+    fn generate_request_id(request: &CrossChainRequest) -> Result<String, String> {
+        let hash = keccak256(request.canonical_bytes()?);
+        let short_hash: String = hash[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        let counter = mutate_state(|s| {
+            s.request_counter += 1;
+            s.request_counter
+        });
+        Ok(format!("ccreq_{}_{}", short_hash, counter))
     }
     
     /// Get current timestamp
+    #[cfg(not(test))]
     fn current_timestamp() -> u64 {
         (ic_cdk::api::time() / 1_000_000_000) as u64
     }
     
+    // Unit tests run outside a canister, where the time system call is unavailable
+    #[cfg(test)]
+    fn current_timestamp() -> u64 {
+        crate::test_support::now()
+    }
+
     /// Validate cross-chain request
     fn validate_request(request: &CrossChainRequest) -> Result<(), String> {
         // Check deadline (temporarily disabled for testing)
         let current_time = Self::current_timestamp();
-        ic_cdk::print(&format!("DEBUG: current_time={}, request.deadline={}", current_time, request.deadline));
         // TODO: Fix timestamp calculation
         // if request.deadline < current_time {
         //     return Err(format!("Transaction deadline has passed. Current: {}, Deadline: {}", current_time, request.deadline));
         // }
         
-        // Validate target chain is Monad
-        if request.target_chain_id != 10143 {
-            return Err("Target chain must be Monad (10143)".to_string());
+        Self::validate_chains(request.source_chain_id, request.target_chain_id)?;
+
+        if let PeridotAction::Borrow { recipient_address: Some(recipient), .. } = &request.action {
+            Address::from_str(recipient)
+                .map_err(|e| format!("Invalid recipient address: {}", e))?;
+        }
+
+        // Peridot reverts self-liquidation; reject it before signing anything
+        if let PeridotAction::LiquidateBorrow { borrower, .. } = &request.action {
+            if canonical_address(borrower) == canonical_address(&request.user_address) {
+                return Err(format!("Liquidator {} cannot liquidate their own position", request.user_address));
+            }
         }
         
+        read_state(|s| position_limits::check_request(s, request, current_time))?;
+
+        Ok(())
+    }
+
+    /// Reject a request whose deadline is already behind the target chain's latest
+    /// block timestamp, which is the clock the execution is judged by on-chain.
+    async fn check_deadline_on_chain(request: &CrossChainRequest) -> Result<(), String> {
+        let block_time = rpc_manager::call_with_fallback(request.target_chain_id, |service| async move {
+            let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+            provider.get_block_by_number(BlockNumberOrTag::Latest, false).await
+                .map_err(|e| e.to_string())?
+                .map(|block| block.header.timestamp)
+                .ok_or_else(|| "latest block not found".to_string())
+        }).await?;
+        Self::check_deadline_at(request, block_time)
+    }
+
+    /// Reject a request whose deadline is before `block_time`, the target chain's
+    /// latest block timestamp.
+    fn check_deadline_at(request: &CrossChainRequest, block_time: u64) -> Result<(), String> {
+        if request.deadline < block_time {
+            return Err(format!(
+                "Transaction deadline has passed on chain {}. Block time: {}, Deadline: {}",
+                request.target_chain_id, block_time, request.deadline
+            ));
+        }
+        Ok(())
+    }
+
+    /// Where borrowed assets are delivered: the explicit recipient, else the borrower.
+    fn borrow_recipient(request: &CrossChainRequest) -> &str {
+        match &request.action {
+            PeridotAction::Borrow { recipient_address: Some(recipient), .. } => recipient,
+            _ => &request.user_address,
+        }
+    }
+
+    /// Check the source/target chain pair before any request is built or sent.
+    pub fn validate_chains(source_chain_id: u64, target_chain_id: u64) -> Result<(), String> {
+        // Validate target chain has a Peridot deployment
+        Self::get_peridot_contract_for_chain(target_chain_id)
+            .map_err(|e| format!("Invalid target chain: {}", e))?;
+
         // Validate source chain is supported
         let config = CrossChainConfig::default();
-        if !config.supported_source_chains.contains_key(&request.source_chain_id) {
-            return Err(format!("Source chain {} not supported", request.source_chain_id));
+        if !config.supported_source_chains.contains_key(&source_chain_id) {
+            let mut supported: Vec<u64> = config.supported_source_chains.keys().copied().collect();
+            supported.sort();
+            return Err(format!("Source chain {} not supported (supported: {:?})", source_chain_id, supported));
         }
         
         Ok(())
     }
 
+    /// The caller's source chain, or the configured `default_source_chain` when omitted.
+    pub fn resolve_source_chain(source_chain_id: Option<u64>) -> Result<u64, String> {
+        source_chain_id
+            .or_else(|| read_state(|s| s.default_source_chain))
+            .ok_or_else(|| "No source chain given and no default source chain configured".to_string())
+    }
+
     /// Enhanced gas estimation for cross-chain operations
     pub async fn estimate_gas_costs(request: &CrossChainRequest) -> Result<GasEstimate, String> {
         Self::validate_request(request)?;
@@ -534,16 +1549,22 @@ impl CrossChainTransactionHandler {
             PeridotAction::Supply { .. } => (100000u64, 150000u64, 1.0),
             PeridotAction::Borrow { .. } => (120000u64, 200000u64, 1.5),
             PeridotAction::LiquidateBorrow { .. } => (80000u64, 180000u64, 1.2),
+            PeridotAction::Redeem { .. } | PeridotAction::RedeemUnderlying { .. } => (100000u64, 180000u64, 1.5),
             _ => (100000u64, 150000u64, 1.0),
         };
         
-        // Estimate USD costs (mock prices for MVP)
-        let eth_price_usd = 3500.0;
-        let gas_price_gwei = 20.0;
-        let gwei_to_eth = 1e-9;
+        // Each leg is priced in its own chain's gas token, at live gas prices when reachable
+        let source_symbol = Self::gas_token_symbol(request.source_chain_id, &config)?;
+        let target_symbol = Self::gas_token_symbol(request.target_chain_id, &config)?;
+        let source_gas_price_gwei = Self::gas_price_gwei(request.source_chain_id).await;
+        let target_gas_price_gwei = Self::gas_price_gwei(request.target_chain_id).await;
         
-        let source_gas_cost_usd = (source_gas as f64) * gas_price_gwei * gwei_to_eth * eth_price_usd;
-        let target_gas_cost_usd = (target_gas as f64) * gas_price_gwei * gwei_to_eth * eth_price_usd;
+        let source_gas_cost_usd = read_state(|s| {
+            gas_price::gas_cost_usd(s, &source_symbol, source_gas, source_gas_price_gwei)
+        })?;
+        let target_gas_cost_usd = read_state(|s| {
+            gas_price::gas_cost_usd(s, &target_symbol, target_gas, target_gas_price_gwei)
+        })?;
         let icp_cycles_cost_usd = 0.045; // Estimated ICP cycles cost
         
         let total_cost = (source_gas_cost_usd + target_gas_cost_usd + icp_cycles_cost_usd) * complexity_multiplier;
@@ -553,11 +1574,44 @@ impl CrossChainTransactionHandler {
             source_chain_gas: source_gas,
             target_chain_gas: target_gas,
             icp_cycles_cost: 10_000_000, // ICP cycles
-            estimated_time_seconds: 300,  // 5 minutes for cross-chain completion
+            estimated_time_seconds: read_state(|s| {
+                bridge::route_time_secs(&s.route_timings, request.source_chain_id, request.target_chain_id)
+            }),
         })
     }
     
-    fn get_rpc_service_for_chain(chain_id: u64) -> Result<RpcService, String> {
+    /// When a request started now should finish, from its route's timing.
+    fn estimated_completion_time(request: &CrossChainRequest) -> u64 {
+        let route_secs = read_state(|s| {
+            bridge::route_time_secs(&s.route_timings, request.source_chain_id, request.target_chain_id)
+        });
+        Self::current_timestamp() + route_secs
+    }
+
+    /// Symbol of a chain's gas token, from its chain config or source-chain entry.
+    fn gas_token_symbol(chain_id: u64, config: &CrossChainConfig) -> Result<String, String> {
+        read_state(|s| s.chain_configs.get(&chain_id).and_then(|c| c.native_symbol.clone()))
+            .or_else(|| config.supported_source_chains.get(&chain_id).map(|c| c._gas_token_symbol.clone()))
+            .ok_or_else(|| format!("Gas token of chain {} not known", chain_id))
+    }
+
+    /// Current gas price on a chain in gwei from its configured source, falling back
+    /// to a fixed estimate when the source can't be reached.
+    async fn gas_price_gwei(chain_id: u64) -> f64 {
+        match gas_price::gas_price_wei(chain_id).await {
+            Ok(wei) => math::wei_to_human(wei, 9),
+            Err(e) => {
+                logger::log(format!("Using default gas price for chain {}: {}", chain_id, e));
+                gas_price::DEFAULT_GAS_PRICE_GWEI
+            }
+        }
+    }
+
+    pub fn get_rpc_service_for_chain(chain_id: u64) -> Result<RpcService, String> {
+        if let Some(service) = rpc_manager::rpc_services_for_chain(chain_id).into_iter().next() {
+            return Ok(service);
+        }
+
         let config = CrossChainConfig::default();
         
         if chain_id == config.monad_chain_id {
@@ -576,6 +1630,8 @@ impl CrossChainTransactionHandler {
         }
     }
     
+    /// Peridot controller on `chain_id`: Monad's built-in deployment, or any chain
+    /// configured with a non-zero controller address.
     fn get_peridot_contract_for_chain(chain_id: u64) -> Result<Address, String> {
         let config = CrossChainConfig::default();
         
@@ -583,13 +1639,440 @@ impl CrossChainTransactionHandler {
             return Ok(config.monad_peridot_controller);
         }
         
-        Err(format!("Peridot contracts not deployed on chain {}", chain_id))
+        let controller = read_state(|s| s.chain_configs.get(&chain_id).map(|c| c.controller_address()))
+            .ok_or_else(|| format!("Peridot contracts not deployed on chain {}", chain_id))??;
+        if controller == Address::ZERO {
+            return Err(format!("No Peridot controller configured for chain {}", chain_id));
+        }
+        Ok(controller)
     }
 }
 
-// ===== HELPER TYPES =====
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::block_on;
+
+    fn supply_request(amount: &str) -> CrossChainRequest {
+        CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    fn completed_response(request_id: &str) -> CrossChainResponse {
+        CrossChainResponse {
+            request_id: request_id.to_string(),
+            status: TransactionStatus::Completed,
+            source_tx_hash: None,
+            target_tx_hash: None,
+            gas_used: None,
+            actual_amount: None,
+            error_message: None,
+            estimated_completion_time: None,
+            claim: None,
+            source_proof: None,
+            status_history: None,
+        }
+    }
+
+    #[test]
+    fn empty_batch_is_rejected_before_execution() {
+        let result = block_on(CrossChainTransactionHandler::execute_batch(Vec::new()));
+        assert_eq!(result.unwrap_err(), "Batch contains no actions");
+    }
+
+    #[test]
+    fn signer_reports_not_ready_until_initialized() {
+        crate::state::init_test_state();
+        let error = CrossChainTransactionHandler::get_threshold_ecdsa_signer().unwrap_err();
+        assert!(error.starts_with("Signer not ready"));
+    }
+
+    #[test]
+    fn mainnet_is_a_source_only_once_enabled() {
+        crate::state::init_test_state();
+        assert!(CrossChainTransactionHandler::validate_chains(11155111, 10143).is_ok());
+        let error = CrossChainTransactionHandler::validate_chains(1, 10143).unwrap_err();
+        assert!(error.contains("Source chain 1 not supported"));
+
+        mutate_state(|s| s.mainnet_sources_enabled = true);
+        assert!(CrossChainTransactionHandler::validate_chains(1, 10143).is_ok());
+    }
+
+    #[test]
+    fn borrows_deliver_to_the_recipient_when_given() {
+        let mut request = supply_request("100");
+        request.action = PeridotAction::Borrow {
+            underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            recipient_address: None,
+        };
+        assert_eq!(CrossChainTransactionHandler::borrow_recipient(&request), request.user_address);
+
+        let payout = "0x00000000000000000000000000000000000000dd".to_string();
+        request.action = PeridotAction::Borrow {
+            underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            recipient_address: Some(payout.clone()),
+        };
+        assert_eq!(CrossChainTransactionHandler::borrow_recipient(&request), payout);
+    }
+
+    #[test]
+    fn confirmation_polls_wait_for_the_missing_blocks() {
+        assert_eq!(confirmation_poll_delay(3, 1_000), Duration::from_secs(3));
+        assert_eq!(confirmation_poll_delay(1, 400), Duration::from_secs(1));
+        assert_eq!(confirmation_poll_delay(100, 3_000), MAX_CONFIRMATION_POLL_DELAY);
+    }
+
+    #[test]
+    fn claimed_amounts_come_from_transfers_to_the_canister() {
+        let canister = Address::repeat_byte(0xca);
+        let collateral = Address::repeat_byte(0x44);
+        let transfer = |token: Address, to: Address, value: u64| Log {
+            inner: alloy::primitives::Log {
+                address: token,
+                data: IERC20::Transfer { from: Address::repeat_byte(0x33), to, value: U256::from(value) }.encode_log_data(),
+            },
+            ..Default::default()
+        };
+        let logs = vec![
+            transfer(collateral, canister, 700),
+            // Protocol share of the seizure and another token's transfer are not ours
+            transfer(collateral, Address::repeat_byte(0x01), 20),
+            transfer(Address::repeat_byte(0x55), canister, 9_000),
+            transfer(collateral, canister, 5),
+        ];
+        assert_eq!(transferred_to(&logs, collateral, canister), U256::from(705));
+        assert_eq!(transferred_to(&[], collateral, canister), U256::ZERO);
+    }
+
+    #[test]
+    fn retries_reuse_the_checkpointed_broadcast() {
+        crate::state::init_test_state();
+        request_store::create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+
+        let first: SentTransaction = block_on(request_store::run_step("ccreq_1", &broadcast_step("supply"), async {
+            Ok(SentTransaction { tx_hash: "0xabc".to_string(), sent_at: 5, nonce: Some(7), gas_price: None })
+        })).unwrap();
+        // A retry after a receipt timeout must not sign and send a second transaction
+        let resumed: SentTransaction = block_on(request_store::run_step("ccreq_1", &broadcast_step("supply"), async {
+            Err::<SentTransaction, _>("broadcast again".to_string())
+        })).unwrap();
+
+        assert_eq!(first.tx_hash, resumed.tx_hash);
+        assert_eq!(resumed.sent_at, 5);
+    }
+
+    #[test]
+    fn logically_equal_requests_share_canonical_bytes() {
+        let user = "0x00000000000000000000000000000000000000Aa";
+        let usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+        let mut request = supply_request("1000");
+        request.user_address = user.to_string();
+        request.action = PeridotAction::Borrow { underlying_asset: usdc.to_string(), recipient_address: None };
+
+        // Address casing, hex amounts and an explicit default recipient change nothing
+        let mut same = request.clone();
+        same.user_address = user.to_lowercase();
+        same.amount = "0x3e8".to_string();
+        same.action = PeridotAction::Borrow {
+            underlying_asset: usdc.to_lowercase(),
+            recipient_address: Some(user.to_string()),
+        };
+        let bytes = request.canonical_bytes().unwrap();
+        assert_eq!(bytes, same.canonical_bytes().unwrap());
+
+        type Layout = (Address, u64, u64, String, Bytes, U256, Address, u64, u64);
+        let decoded = Layout::abi_decode_params(&bytes, true).unwrap();
+        assert_eq!(decoded.0, Address::from_str(user).unwrap());
+        assert_eq!(decoded.5, U256::from(1000));
+
+        let mut other = request.clone();
+        other.amount = "1001".to_string();
+        assert_ne!(bytes, other.canonical_bytes().unwrap());
+        other.user_address = "not an address".to_string();
+        assert!(other.canonical_bytes().is_err());
+    }
+
+    #[test]
+    fn deadlines_are_judged_by_the_target_chain_clock() {
+        let mut request = supply_request("1000");
+        // Canister clock at 1_000 would accept this, but the chain is already at 1_060
+        request.deadline = 1_030;
+        assert!(CrossChainTransactionHandler::check_deadline_at(&request, 1_000).is_ok());
+        let err = CrossChainTransactionHandler::check_deadline_at(&request, 1_060).unwrap_err();
+        assert!(err.starts_with("Transaction deadline has passed on chain 10143"), "{}", err);
+    }
+
+    #[test]
+    fn identical_requests_share_a_prefix_but_not_an_id() {
+        crate::state::init_test_state();
+        let request = supply_request("100");
+        let first = CrossChainTransactionHandler::generate_request_id(&request).unwrap();
+        let second = CrossChainTransactionHandler::generate_request_id(&request.clone()).unwrap();
+        let other = CrossChainTransactionHandler::generate_request_id(&supply_request("200")).unwrap();
+
+        let prefix = |id: &str| id.rsplit_once('_').map(|(prefix, _)| prefix.to_string()).unwrap();
+        assert!(first.starts_with("ccreq_"));
+        assert_eq!(prefix(&first), prefix(&second));
+        assert_ne!(first, second);
+        assert_ne!(prefix(&first), prefix(&other));
+    }
+
+    /// Delivers every transfer unchanged.
+    struct Delivered;
+    impl BridgeAdapter for Delivered {
+        async fn bridge(&self, _: u64, _: u64, asset: &str, amount: &str) -> Result<BridgeReceipt, String> {
+            Ok(BridgeReceipt { tx_hash: None, asset_address: asset.to_string(), amount: amount.to_string(), source_proof: None })
+        }
+    }
 
-struct MonadAsset {
-    asset_address: String,
-    amount: String,
-} 
\ No newline at end of file
+    /// Static Monad gas and a cached market for the request's asset, so a supply
+    /// runs up to signing without outcalls.
+    fn seed_supply_market(request: &CrossChainRequest) {
+        mutate_state(|s| {
+            s.gas_price_sources.insert(10143, gas_price::GasPriceSource::Static(2_000_000_000));
+            s.token_metadata.insert((10143, Address::repeat_byte(0x01)), crate::token_metadata::TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some(request.asset_address.clone()),
+            });
+        });
+    }
+
+    #[test]
+    fn a_failed_approve_cancels_its_linked_supply() {
+        crate::state::init_test_state();
+        let request = supply_request("100");
+        seed_supply_market(&request);
+        request_store::create_at("ccreq_1", &request, 10).unwrap();
+
+        // No signer yet, so the approve fails before anything is broadcast
+        let error = block_on(CrossChainTransactionHandler::execute_cross_chain_supply(
+            request, CrossChainConfig::default(), "ccreq_1".to_string(), &Delivered,
+        )).unwrap_err();
+        assert!(error.starts_with("LinkedFailure: supply cancelled because its approve failed: Signer not ready"), "{}", error);
+        let checkpoints = request_store::get("ccreq_1").unwrap().checkpoints;
+        assert!(checkpoints.contains_key(LINKED_GAS_PRICE_STEP));
+        assert!(!checkpoints.contains_key("supply_tx") && !checkpoints.contains_key(&broadcast_step("supply")));
+    }
+
+    #[test]
+    fn a_bumped_approve_reprices_its_linked_mint() {
+        assert_eq!(bumped_gas_price(100, 90), 113);
+        assert_eq!(bumped_gas_price(100, 200), 200);
+
+        crate::state::init_test_state();
+        request_store::create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+        let linked: u128 = block_on(request_store::run_step("ccreq_1", LINKED_GAS_PRICE_STEP, async { Ok(100u128) })).unwrap();
+        request_store::replace_checkpoint("ccreq_1", LINKED_GAS_PRICE_STEP, &bumped_gas_price(linked, 90)).unwrap();
+
+        // A retried supply reads the linked price from its checkpoint, not the chain
+        let resumed: u128 = block_on(request_store::run_step("ccreq_1", LINKED_GAS_PRICE_STEP, async {
+            Err::<u128, _>("gas price refetched".to_string())
+        })).unwrap();
+        assert_eq!(resumed, 113);
+    }
+
+    #[test]
+    fn action_calldata_names_the_market_it_is_sent_to() {
+        use crate::token_metadata::TokenMetadata;
+
+        crate::state::init_test_state();
+        let usdc = "0x00000000000000000000000000000000000000bb";
+        let market = Address::repeat_byte(0x01);
+        let borrow = PeridotAction::Borrow { underlying_asset: usdc.to_string(), recipient_address: None };
+        let error = CrossChainTransactionHandler::action_calldata(&borrow, usdc, "250").unwrap_err();
+        assert!(error.contains("not loaded yet"), "{}", error);
+
+        mutate_state(|s| s.token_metadata.insert((10143, market), TokenMetadata {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+            underlying_address: Some(usdc.to_string()),
+        }));
+        let calldata = CrossChainTransactionHandler::action_calldata(&borrow, usdc, "250").unwrap();
+        assert_eq!(calldata.to, market.to_string());
+        let data = hex::decode(&calldata.calldata).unwrap();
+        let call = IPToken::borrowCall::abi_decode(&data, true).unwrap();
+        assert_eq!(call.borrowAmount, U256::from(250));
+        assert_eq!(calldata.selector, hex::encode_prefixed(IPToken::borrowCall::SELECTOR));
+    }
+
+    #[test]
+    fn requests_past_the_execution_cap_wait_in_the_queue() {
+        crate::state::init_test_state();
+        mutate_state(|s| {
+            s.max_concurrent_executions = 2;
+            s.queue_busy_executions = true;
+        });
+        let request = supply_request("1000");
+        let held = [ExecutionGuard::new().unwrap(), ExecutionGuard::new().unwrap()];
+
+        // The third request finds both slots taken and is queued, not failed
+        let error = CrossChainTransactionHandler::admit("ccreq_3", &request).err().unwrap();
+        assert!(error.starts_with("Busy:") && error.ends_with("queued as ccreq_3"), "{}", error);
+        assert_eq!(request_store::get("ccreq_3").unwrap().status, TransactionStatus::Pending);
+
+        // Resuming after an upgrade respects the cap too
+        request_store::create("ccreq_resumed", &request).unwrap();
+        block_on(CrossChainTransactionHandler::resume_in_flight_with(&CrossChainTransactionHandler::default_bridge()));
+        read_state(|s| {
+            assert_eq!(s.in_flight_executions, 2);
+            assert!(s.retry_queue.contains_key("ccreq_3") && s.retry_queue.contains_key("ccreq_resumed"));
+            assert_eq!(s.cross_chain_requests["ccreq_resumed"].status, TransactionStatus::Pending);
+        });
+
+        drop(held);
+        assert!(CrossChainTransactionHandler::admit("ccreq_4", &request).is_ok());
+        assert!(request_store::get("ccreq_4").is_none());
+    }
+
+    #[test]
+    fn each_gas_leg_is_priced_in_its_own_chain_token() {
+        crate::state::init_test_state();
+        mutate_state(|s| {
+            for chain_id in [97, 11155111, 10143] {
+                s.gas_price_sources.insert(chain_id, gas_price::GasPriceSource::Static(5_000_000_000));
+            }
+        });
+        let from_bnb = supply_request("1000");
+        let from_sepolia = CrossChainRequest { source_chain_id: 11155111, ..from_bnb.clone() };
+
+        let bnb = block_on(CrossChainTransactionHandler::estimate_gas_costs(&from_bnb)).unwrap();
+        let eth = block_on(CrossChainTransactionHandler::estimate_gas_costs(&from_sepolia)).unwrap();
+
+        // 100k source gas at 5 gwei: 0.0005 BNB at $600 against 0.0005 ETH at $3500
+        assert!((eth.total_gas_cost_usd - bnb.total_gas_cost_usd - (1.75 - 0.3)).abs() < 1e-9);
+        // The shared Monad leg and cycles cost: 150k gas at 5 gwei in MON at $1, plus $0.045
+        assert!((bnb.total_gas_cost_usd - (0.3 + 0.00075 + 0.045)).abs() < 1e-9);
+
+        mutate_state(|s| s.native_token_prices.remove("BNB"));
+        let error = block_on(CrossChainTransactionHandler::estimate_gas_costs(&from_bnb)).unwrap_err();
+        assert!(error.contains("gas token BNB"), "{}", error);
+    }
+
+    #[test]
+    fn completion_estimates_follow_each_route() {
+        crate::state::init_test_state();
+        crate::test_support::set_now(1_000);
+        mutate_state(|s| {
+            for chain_id in [97, 11155111, 10143] {
+                s.gas_price_sources.insert(chain_id, gas_price::GasPriceSource::Static(1_000_000_000));
+            }
+        });
+        let from_bnb = supply_request("1000");
+        let from_sepolia = CrossChainRequest { source_chain_id: 11155111, ..from_bnb.clone() };
+
+        // 45s BNB finality vs 780s Sepolia finality, each plus 120s bridging and 2s on Monad
+        assert_eq!(CrossChainTransactionHandler::estimated_completion_time(&from_bnb), 1_000 + 167);
+        assert_eq!(CrossChainTransactionHandler::estimated_completion_time(&from_sepolia), 1_000 + 902);
+        let bnb = block_on(CrossChainTransactionHandler::estimate_gas_costs(&from_bnb)).unwrap();
+        let sepolia = block_on(CrossChainTransactionHandler::estimate_gas_costs(&from_sepolia)).unwrap();
+        assert_eq!((bnb.estimated_time_seconds, sepolia.estimated_time_seconds), (167, 902));
+
+        // A route without timings falls back to the default
+        mutate_state(|s| s.route_timings.remove(&(97, 10143)));
+        assert_eq!(
+            CrossChainTransactionHandler::estimated_completion_time(&from_bnb),
+            1_000 + bridge::DEFAULT_ROUTE_TIME_SECS
+        );
+    }
+
+    #[test]
+    fn disabled_actions_are_rejected_while_others_still_run() {
+        crate::state::init_test_state();
+        let supply = supply_request("100");
+        seed_supply_market(&supply);
+        let liquidation = CrossChainRequest {
+            action: PeridotAction::LiquidateBorrow {
+                borrower: "0x00000000000000000000000000000000000000dd".to_string(),
+                underlying_asset: supply.asset_address.clone(),
+                collateral_asset: "0x00000000000000000000000000000000000000ee".to_string(),
+                auto_claim: None,
+            },
+            ..supply.clone()
+        };
+        mutate_state(|s| s.disabled_actions.insert("LiquidateBorrow".to_string()));
+
+        request_store::create_at("ccreq_liquidation", &liquidation, 10).unwrap();
+        let error = block_on(CrossChainTransactionHandler::run_request(liquidation, "ccreq_liquidation".to_string(), &Delivered)).unwrap_err();
+        assert_eq!(error, "ActionDisabled: LiquidateBorrow actions are disabled");
+        assert_eq!(request_store::get("ccreq_liquidation").unwrap().status, TransactionStatus::Failed);
+
+        // Supply passes the switch and proceeds until it needs the signer
+        request_store::create_at("ccreq_supply", &supply, 10).unwrap();
+        let error = block_on(CrossChainTransactionHandler::run_request(supply, "ccreq_supply".to_string(), &Delivered)).unwrap_err();
+        assert!(error.contains("Signer not ready"), "{}", error);
+    }
+
+    #[test]
+    fn typed_execute_results_decode_from_candid() {
+        let response = CrossChainResponse {
+            target_tx_hash: Some("0xabc".to_string()),
+            status_history: Some(vec![(TransactionStatus::Pending, 10), (TransactionStatus::Completed, 12)]),
+            ..completed_response("ccreq_1")
+        };
+        let ok: Result<CrossChainResponse, PeridotError> = Ok(response);
+        let bytes = candid::encode_one(&ok).unwrap();
+        let decoded = candid::decode_one::<Result<CrossChainResponse, PeridotError>>(&bytes).unwrap().unwrap();
+        assert_eq!((decoded.request_id.as_str(), decoded.status), ("ccreq_1", TransactionStatus::Completed));
+        assert_eq!(decoded.target_tx_hash.as_deref(), Some("0xabc"));
+        assert_eq!(decoded.status_history.map(|history| history.len()), Some(2));
+
+        let err: Result<CrossChainResponse, PeridotError> = Err(PeridotError::from("Busy: 4 in flight".to_string()));
+        let bytes = candid::encode_one(&err).unwrap();
+        let decoded = candid::decode_one::<Result<CrossChainResponse, PeridotError>>(&bytes).unwrap();
+        assert_eq!(decoded.unwrap_err(), PeridotError::Busy("4 in flight".to_string()));
+    }
+
+    #[test]
+    fn omitted_source_chains_fall_back_to_the_configured_default() {
+        crate::state::init_test_state();
+        let error = CrossChainTransactionHandler::resolve_source_chain(None).unwrap_err();
+        assert!(error.contains("no default source chain"), "{}", error);
+
+        mutate_state(|s| s.default_source_chain = Some(97));
+        assert_eq!(CrossChainTransactionHandler::resolve_source_chain(None), Ok(97));
+        // An explicit source chain overrides the default
+        assert_eq!(CrossChainTransactionHandler::resolve_source_chain(Some(11155111)), Ok(11155111));
+    }
+
+    #[test]
+    fn self_liquidations_are_rejected_before_any_rpc_call() {
+        crate::state::init_test_state();
+        let liquidation = |borrower: &str| CrossChainRequest {
+            action: PeridotAction::LiquidateBorrow {
+                borrower: borrower.to_string(),
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+                collateral_asset: "0x00000000000000000000000000000000000000cc".to_string(),
+                auto_claim: None,
+            },
+            ..supply_request("1000")
+        };
+
+        // The liquidator's own address, however it is spelled
+        for borrower in [
+            "0x00000000000000000000000000000000000000aa",
+            "0x00000000000000000000000000000000000000AA",
+            " 0x00000000000000000000000000000000000000000000000000000000000000aa ",
+        ] {
+            let error = CrossChainTransactionHandler::validate_request(&liquidation(borrower)).unwrap_err();
+            assert!(error.contains("cannot liquidate their own position"), "{}", error);
+            // Estimation validates first, so it fails without reaching a provider
+            let error = block_on(CrossChainTransactionHandler::estimate_gas_costs(&liquidation(borrower))).unwrap_err();
+            assert!(error.contains("cannot liquidate their own position"), "{}", error);
+        }
+
+        assert!(CrossChainTransactionHandler::validate_request(
+            &liquidation("0x00000000000000000000000000000000000000dd")
+        ).is_ok());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::state::read_state;
+
+/// Balance kept in reserve by default: 500B cycles, well above a typical freezing
+/// threshold, so queries keep working once expensive updates are declined.
+pub const DEFAULT_CYCLE_FLOOR: u128 = 500_000_000_000;
+
+/// Decline `operation` with an `InsufficientCycles` error when the canister's
+/// balance is below the configured floor, instead of letting it trap midway.
+pub fn ensure_cycles(operation: &str) -> Result<(), String> {
+    check_balance(ic_cdk::api::canister_balance128(), read_state(|s| s.cycle_floor), operation)
+}
+
+fn check_balance(balance: u128, floor: u128, operation: &str) -> Result<(), String> {
+    if balance < floor {
+        return Err(format!(
+            "InsufficientCycles: {} declined, balance {} is below the {} cycle floor",
+            operation, balance, floor
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_are_declined_below_the_floor() {
+        assert!(check_balance(DEFAULT_CYCLE_FLOOR, DEFAULT_CYCLE_FLOOR, "full sync").is_ok());
+        let error = check_balance(DEFAULT_CYCLE_FLOOR - 1, DEFAULT_CYCLE_FLOOR, "full sync").unwrap_err();
+        assert_eq!(
+            error,
+            "InsufficientCycles: full sync declined, balance 499999999999 is below the 500000000000 cycle floor"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use crate::contracts::IERC20;
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::logger;
+use crate::nonce_manager;
+use crate::request_store;
+use crate::state::{mutate_state, read_state};
+use alloy::network::{EthereumWallet, TransactionBuilder, TxSigner};
+use alloy::primitives::{Address, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolCall;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+use std::str::FromStr;
+
+const NATIVE_TRANSFER_GAS: u64 = 21_000;
+const ERC20_TRANSFER_GAS: u64 = 100_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub enum WithdrawalStatus {
+    /// Accepted by the node; the receipt has not been seen yet.
+    Broadcast,
+    Confirmed,
+    Reverted,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct EmergencyWithdrawal {
+    pub chain_id: u64,
+    pub to_address: String,
+    pub token: Option<String>, // None for the native gas token
+    pub amount_wei: String,
+    pub tx_hash: String,
+    pub requested_by: Principal,
+    pub executed_at: u64, // seconds
+    /// `None` for withdrawals recorded before statuses were tracked.
+    pub status: Option<WithdrawalStatus>,
+}
+
+/// Move the canister's funds on `chain_id` to `to_address`: the native token, or
+/// `token` when given. `amount` of `None` sends the whole balance, less the gas
+/// for the transfer when sending native funds. Only allowed while the canister
+/// is paused and no cross-chain request is still in flight, so nothing can be
+/// spending the same funds. The withdrawal is recorded as soon as it is
+/// broadcast, and its status updated once the receipt arrives.
+pub async fn withdraw(
+    chain_id: u64,
+    to_address: &str,
+    token: Option<String>,
+    amount: Option<String>,
+    requested_by: Principal,
+) -> Result<EmergencyWithdrawal, String> {
+    if !read_state(|s| s.paused) {
+        return Err("Canister must be paused before an emergency withdrawal".to_string());
+    }
+    let in_flight = request_store::in_flight();
+    if !in_flight.is_empty() {
+        return Err(format!(
+            "{} cross-chain request(s) still in flight (e.g. {}); wait for them to finish before an emergency withdrawal",
+            in_flight.len(),
+            in_flight[0].request_id
+        ));
+    }
+    if !read_state(|s| s.chain_configs.contains_key(&chain_id)) {
+        return Err(format!("Chain {} not configured", chain_id));
+    }
+    let to = Address::from_str(to_address)
+        .map_err(|e| format!("Invalid destination address {}: {}", to_address, e))?;
+    if to == Address::ZERO {
+        return Err("Refusing to withdraw to the zero address".to_string());
+    }
+    let token = token
+        .map(|token| Address::from_str(&token).map_err(|e| format!("Invalid token address {}: {}", token, e)))
+        .transpose()?;
+    let requested = amount.as_deref().map(CrossChainTransactionHandler::parse_amount).transpose()?;
+
+    let signer = read_state(|s| s.signer.clone())
+        .ok_or("Signer not ready: threshold ECDSA key initialization is still in progress")?;
+    let from = signer.address();
+    if to == from {
+        return Err("Destination is the canister's own address".to_string());
+    }
+
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(signer))
+        .on_icp(IcpConfig::new(rpc_service));
+    let gas_price = provider.get_gas_price().await
+        .map_err(|e| format!("Failed to fetch gas price: {}", e))?;
+
+    let (tx_request, amount) = match token {
+        None => {
+            let amount = match requested {
+                Some(amount) => amount,
+                None => {
+                    let balance = provider.get_balance(from).await
+                        .map_err(|e| format!("Failed to fetch balance: {}", e))?;
+                    let fee = U256::from(gas_price) * U256::from(NATIVE_TRANSFER_GAS);
+                    balance.checked_sub(fee)
+                        .ok_or_else(|| format!("Balance {} does not cover the transfer fee {}", balance, fee))?
+                }
+            };
+            let tx = TransactionRequest::default()
+                .to(to)
+                .value(amount)
+                .gas_limit(NATIVE_TRANSFER_GAS);
+            (tx, amount)
+        }
+        Some(token) => {
+            let erc20 = IERC20::new(token, provider.clone());
+            let amount = match requested {
+                Some(amount) => amount,
+                None => erc20.balanceOf(from).call().await
+                    .map_err(|e| format!("Failed to fetch {} balance: {}", token, e))?._0,
+            };
+            let tx = TransactionRequest::default()
+                .to(token)
+                .input(IERC20::transferCall { to, amount }.abi_encode().into())
+                .gas_limit(ERC20_TRANSFER_GAS);
+            (tx, amount)
+        }
+    };
+    if amount.is_zero() {
+        return Err("Nothing to withdraw".to_string());
+    }
+
+    let mut tx_request = tx_request.with_gas_price(gas_price);
+    tx_request.set_chain_id(chain_id);
+    let nonce = match read_state(|s| s.nonces.get(&chain_id).copied()) {
+        Some(nonce) => nonce,
+        None => provider.get_transaction_count(from).await
+            .map_err(|e| format!("Failed to fetch nonce for {}: {}", from, e))?,
+    };
+    tx_request.set_nonce(nonce);
+
+    let pending_tx = match provider.send_transaction(tx_request).await {
+        Ok(pending_tx) => {
+            // A concurrent send may already have moved the nonce further
+            nonce_manager::raise_nonce(chain_id, nonce + 1);
+            pending_tx
+        }
+        Err(e) => {
+            mutate_state(|s| s.nonces.remove(&chain_id));
+            return Err(format!("Failed to send emergency withdrawal: {}", e));
+        }
+    };
+    let withdrawal = EmergencyWithdrawal {
+        chain_id,
+        to_address: to.to_string(),
+        token: token.map(|token| token.to_string()),
+        amount_wei: amount.to_string(),
+        tx_hash: format!("{:?}", pending_tx.tx_hash()),
+        requested_by,
+        executed_at: ic_cdk::api::time() / 1_000_000_000,
+        status: Some(WithdrawalStatus::Broadcast),
+    };
+    logger::log(format!(
+        "Emergency withdrawal of {} {} on chain {} to {} by {}: {}",
+        withdrawal.amount_wei,
+        withdrawal.token.as_deref().unwrap_or("native"),
+        chain_id,
+        withdrawal.to_address,
+        requested_by,
+        withdrawal.tx_hash
+    ));
+    mutate_state(|s| s.emergency_withdrawals.push(withdrawal.clone()));
+
+    let status = match pending_tx.get_receipt().await {
+        Ok(receipt) if receipt.status() => WithdrawalStatus::Confirmed,
+        Ok(_) => WithdrawalStatus::Reverted,
+        Err(e) => {
+            return Err(format!(
+                "Emergency withdrawal {} was sent and recorded, but its receipt could not be fetched: {}",
+                withdrawal.tx_hash, e
+            ));
+        }
+    };
+    settle(&withdrawal.tx_hash, status.clone());
+    if status == WithdrawalStatus::Reverted {
+        return Err(format!("Emergency withdrawal {} reverted", withdrawal.tx_hash));
+    }
+    Ok(EmergencyWithdrawal { status: Some(status), ..withdrawal })
+}
+
+/// Update the status of the recorded withdrawal sent as `tx_hash`.
+fn settle(tx_hash: &str, status: WithdrawalStatus) {
+    mutate_state(|s| {
+        if let Some(withdrawal) = s.emergency_withdrawals.iter_mut().rev().find(|w| w.tx_hash == tx_hash) {
+            withdrawal.status = Some(status);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use crate::test_support::block_on;
+
+    fn supply_request(amount: &str) -> crate::cross_chain_transactions::CrossChainRequest {
+        crate::cross_chain_transactions::CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: crate::cross_chain_transactions::PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    const DESTINATION: &str = "0x00000000000000000000000000000000000000dd";
+
+    #[test]
+    fn withdrawals_wait_for_in_flight_requests() {
+        init_test_state();
+        mutate_state(|s| s.paused = true);
+        request_store::create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+
+        let error = block_on(withdraw(10143, DESTINATION, None, None, Principal::anonymous())).unwrap_err();
+        assert!(error.starts_with("1 cross-chain request(s) still in flight (e.g. ccreq_1)"), "{}", error);
+
+        // Once the request has finished, the withdrawal gets as far as the signer
+        request_store::transition("ccreq_1", crate::cross_chain_transactions::TransactionStatus::Failed, None);
+        let error = block_on(withdraw(10143, DESTINATION, None, None, Principal::anonymous())).unwrap_err();
+        assert!(error.starts_with("Signer not ready"), "{}", error);
+    }
+
+    #[test]
+    fn broadcast_withdrawals_are_settled_by_hash() {
+        init_test_state();
+        let withdrawal = |tx_hash: &str| EmergencyWithdrawal {
+            chain_id: 10143,
+            to_address: DESTINATION.to_string(),
+            token: None,
+            amount_wei: "1".to_string(),
+            tx_hash: tx_hash.to_string(),
+            requested_by: Principal::anonymous(),
+            executed_at: 10,
+            status: Some(WithdrawalStatus::Broadcast),
+        };
+        mutate_state(|s| s.emergency_withdrawals.extend([withdrawal("0x01"), withdrawal("0x02")]));
+
+        settle("0x02", WithdrawalStatus::Reverted);
+        let statuses: Vec<_> = read_state(|s| s.emergency_withdrawals.iter().map(|w| w.status.clone()).collect());
+        assert_eq!(statuses, vec![Some(WithdrawalStatus::Broadcast), Some(WithdrawalStatus::Reverted)]);
+    }
+}
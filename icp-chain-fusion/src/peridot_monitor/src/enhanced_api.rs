@@ -1,8 +1,20 @@
+use crate::accrual;
 use crate::chain_fusion_manager::ChainFusionManager;
-use crate::state::{read_state, UserPosition, MarketState};
+use crate::cross_chain_transactions::{PeridotAction, TransactionStatus};
+use crate::gas_price;
+use crate::job::{self, CollateralFactor};
+use crate::liquidation_cooldown;
+use crate::liquidation_events::LiquidationEvent;
+use crate::math;
+use crate::price_oracle::{self, PriceBook};
+use crate::rates;
+use crate::state::{read_state, State, UserPosition, MarketState};
+use crate::token_metadata;
+use alloy::primitives::Address;
+use std::str::FromStr;
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct CrossChainUserPosition {
@@ -15,6 +27,29 @@ pub struct CrossChainUserPosition {
     pub arbitrage_opportunities: Vec<ArbitrageOpportunity>,
 }
 
+/// Outcome of looking a user up, telling an address the canister has never seen
+/// apart from one whose positions have been closed out.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+#[serde(tag = "status")]
+pub enum UserPositionLookup {
+    /// No event for the address has been observed.
+    Unknown,
+    /// The user has tracked positions, but every balance is zero.
+    Empty { user_address: String },
+    Found(CrossChainUserPosition),
+}
+
+/// Largest window `list_users` returns in one call.
+pub const MAX_USERS_PAGE_SIZE: u64 = 1000;
+
+/// One window of the observed user set, ordered by canonical address.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct UserPage {
+    pub users: Vec<String>,
+    pub offset: u64,
+    pub total: u64,
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct LiquidationRisk {
     pub risk_level: String, // "Low", "Medium", "High", "Critical"
@@ -23,6 +58,35 @@ pub struct LiquidationRisk {
     pub recommended_action: String,
 }
 
+/// Health-factor cut-offs for risk levels: below `critical` is Critical, below
+/// `high` is High, below `medium` is Medium, anything else is Low.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub struct RiskThresholds {
+    pub critical: f64,
+    pub high: f64,
+    pub medium: f64,
+}
+
+impl Default for RiskThresholds {
+    fn default() -> Self {
+        Self {
+            critical: 1.0,
+            high: 1.1,
+            medium: 1.3,
+        }
+    }
+}
+
+impl RiskThresholds {
+    pub fn validate(&self) -> Result<(), String> {
+        let finite = [self.critical, self.high, self.medium].iter().all(|t| t.is_finite());
+        if !finite || self.critical <= 0.0 || self.critical >= self.high || self.high >= self.medium {
+            return Err("Risk thresholds must be positive and satisfy critical < high < medium".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct ArbitrageOpportunity {
     pub strategy: String,
@@ -41,6 +105,9 @@ pub struct CrossChainMarketSummary {
     pub best_borrow_rates: HashMap<String, ChainRate>,
     pub liquidity_flows: Vec<LiquidityFlow>,
     pub market_health: MarketHealth,
+    pub window_seconds: Option<u64>, // None = all time
+    pub liquidation_count: u64,
+    pub liquidation_volume_usd: f64,
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
@@ -58,6 +125,39 @@ pub struct LiquidityFlow {
     pub asset: String,
     pub flow_direction: String, // "Supply", "Borrow"
     pub incentive_apy: f64,
+    pub volume_usd: f64,
+}
+
+/// Minimum bar an arbitrage opportunity must clear to be reported.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub struct ArbitrageFilter {
+    pub min_profit_usd: f64,
+    pub max_risk_score: f64,
+}
+
+impl Default for ArbitrageFilter {
+    fn default() -> Self {
+        Self {
+            min_profit_usd: 10.0,
+            max_risk_score: 0.5,
+        }
+    }
+}
+
+impl ArbitrageFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.min_profit_usd.is_finite() || self.min_profit_usd < 0.0 {
+            return Err("Minimum profit must be a non-negative USD amount".to_string());
+        }
+        if !self.max_risk_score.is_finite() || !(0.0..=1.0).contains(&self.max_risk_score) {
+            return Err("Maximum risk score must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn admits(&self, opportunity: &ArbitrageOpportunity) -> bool {
+        opportunity.estimated_profit_usd >= self.min_profit_usd && opportunity.risk_score <= self.max_risk_score
+    }
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
@@ -71,6 +171,8 @@ pub struct MarketHealth {
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub struct ChainAnalytics {
     pub chain_id: u64,
+    /// False until the chain has applied at least one event; every figure is then zero.
+    pub initialized: bool,
     pub total_events_processed: u64,
     pub active_users: u64,
     pub total_volume_24h: f64,
@@ -85,22 +187,62 @@ pub struct SyncStatus {
     pub last_synced_block: u64,
     pub latest_network_block: u64,
     pub sync_lag_blocks: u64,
-    pub estimated_sync_time_seconds: u64,
-    pub sync_health: String, // "Healthy", "Lagging", "Stalled"
+    pub estimated_sync_time_seconds: u64, // rounded up
+    pub estimated_sync_time_ms: u64,
+    pub estimated_sync_time: String, // e.g. "750ms", "3s", "2m 5s"
+    pub sync_health: String, // "Healthy", "Lagging", "Stalled", or "NotSynced" before the first event
 }
 
 // Enhanced API implementations
 impl ChainFusionManager {
+    /// Like `get_enhanced_user_position`, but separates unknown users from users
+    /// whose positions hold no balances.
+    pub fn lookup_user_position(&self, user_address: &str) -> UserPositionLookup {
+        let Some(position) = self.get_enhanced_user_position(user_address) else {
+            return UserPositionLookup::Unknown;
+        };
+        let has_balance = position.positions_by_chain.values().any(|p| {
+            p.p_token_balances.iter().chain(&p.borrow_balances).any(|(_, amount)| *amount > 0)
+        });
+        if has_balance {
+            UserPositionLookup::Found(position)
+        } else {
+            UserPositionLookup::Empty { user_address: position.user_address }
+        }
+    }
+
     pub fn get_enhanced_user_position(&self, user_address: &str) -> Option<CrossChainUserPosition> {
+        let canonical = canonical_address(user_address);
         read_state(|s| {
-            let user_positions: Vec<_> = s.user_positions.iter()
-                .filter(|((addr, _), _)| addr == user_address)
-                .map(|((_, chain_id), position)| (*chain_id, position.clone()))
-                .collect();
+            // Event processors have stored keys in different formats, so a user's
+            // positions on one chain may be split across several entries
+            let mut merged: BTreeMap<u64, UserPosition> = BTreeMap::new();
+            for ((addr, chain_id), position) in &s.user_positions {
+                if canonical_address(addr) != canonical {
+                    continue;
+                }
+                match merged.get_mut(chain_id) {
+                    Some(existing) => merge_position(
+                        existing,
+                        position,
+                        &s.prices,
+                        &s.market_states,
+                        &job::collateral_factor(s, *chain_id),
+                        current_timestamp(),
+                    ),
+                    None => {
+                        merged.insert(*chain_id, UserPosition { user_address: canonical.clone(), ..position.clone() });
+                    }
+                }
+            }
             
-            if user_positions.is_empty() {
+            if merged.is_empty() {
                 return None;
             }
+            for position in merged.values_mut() {
+                position.stale_since_blocks = accrual::position_stale_since_blocks(s, position);
+            }
+            let user_positions: Vec<(u64, UserPosition)> = merged.into_iter().collect();
             
             let total_collateral = user_positions.iter()
                 .map(|(_, pos)| pos.total_collateral_value_usd)
@@ -110,14 +252,15 @@ impl ChainFusionManager {
                 .map(|(_, pos)| pos.total_borrow_value_usd)
                 .sum();
             
-            let aggregate_health_factor = if total_borrow > 0.0 {
-                total_collateral / total_borrow
-            } else {
-                f64::MAX
-            };
+            let aggregate_health_factor = math::health_factor(total_collateral, total_borrow);
             
-            let liquidation_risk = calculate_liquidation_risk(aggregate_health_factor, total_borrow);
-            let arbitrage_opportunities = find_arbitrage_opportunities(&user_positions, &s.market_states);
+            let liquidation_risk = calculate_liquidation_risk(aggregate_health_factor, total_borrow, &s.risk_thresholds);
+            // Capital the user could still put to work without adding collateral
+            let headroom_usd = (total_collateral - total_borrow).max(0.0);
+            let arbitrage_opportunities = find_arbitrage_opportunities(s, Some(headroom_usd), current_timestamp())
+                .into_iter()
+                .filter(|opportunity| s.arbitrage_filter.admits(opportunity))
+                .collect();
             
             let mut positions_by_chain = HashMap::new();
             for (chain_id, position) in user_positions {
@@ -125,7 +268,7 @@ impl ChainFusionManager {
             }
             
             Some(CrossChainUserPosition {
-                user_address: user_address.to_string(),
+                user_address: canonical,
                 total_collateral_usd: total_collateral,
                 total_borrow_usd: total_borrow,
                 aggregate_health_factor,
@@ -136,14 +279,30 @@ impl ChainFusionManager {
         })
     }
     
-    pub fn get_cross_chain_market_summary(&self) -> CrossChainMarketSummary {
+    /// Arbitrage opportunities across every tracked market that clear `filter`,
+    /// most profitable first. Each is sized by the liquidity available to borrow.
+    pub fn get_arbitrage_opportunities(&self, filter: &ArbitrageFilter) -> Vec<ArbitrageOpportunity> {
+        arbitrage_opportunities_at(filter, current_timestamp())
+    }
+
+    /// Market totals and rates from current state. Liquidity flows, liquidation
+    /// count and volume only include requests and events within the last
+    /// `window_seconds` (liquidations by block time) when a window is given;
+    /// otherwise they cover everything retained.
+    pub fn get_cross_chain_market_summary(&self, window_seconds: Option<u64>) -> CrossChainMarketSummary {
+        self.market_summary_at(window_seconds, current_timestamp())
+    }
+
+    fn market_summary_at(&self, window_seconds: Option<u64>, now: u64) -> CrossChainMarketSummary {
+        let since = window_seconds.map(|window| now.saturating_sub(window));
         read_state(|s| {
             let mut total_supply = 0.0;
             let mut total_borrow = 0.0;
             let mut supply_rates = HashMap::new();
             let mut borrow_rates = HashMap::new();
             
-            for (chain_id, market) in &s.market_states {
+            for market in s.market_states.values() {
+                let chain_id = &market.chain_id;
                 total_supply += market.total_supply as f64;
                 total_borrow += market.total_borrows as f64;
                 
@@ -156,7 +315,7 @@ impl ChainFusionManager {
                     ChainRate {
                         chain_id: *chain_id,
                         chain_name: chain_name.clone(),
-                        rate: market.supply_rate as f64 / 1e18, // Convert from wei
+                        rate: math::wei_to_human(market.supply_rate as u128, 18),
                         available_liquidity: market.cash as f64,
                     }
                 );
@@ -166,15 +325,22 @@ impl ChainFusionManager {
                     ChainRate {
                         chain_id: *chain_id,
                         chain_name,
-                        rate: market.borrow_rate as f64 / 1e18,
+                        rate: math::wei_to_human(market.borrow_rate as u128, 18),
                         available_liquidity: market.cash as f64,
                     }
                 );
             }
             
-            let liquidity_flows = calculate_liquidity_flows(&s.market_states);
+            let liquidity_flows = calculate_liquidity_flows(s, since, now);
             let market_health = calculate_market_health(&s.user_positions, &s.market_states);
             
+            let windowed_liquidations: Vec<&LiquidationEvent> = s.liquidation_events.iter()
+                .filter(|e| since.is_none_or(|since| e.event_time() >= since))
+                .collect();
+            let liquidation_volume_usd = windowed_liquidations.iter()
+                .filter_map(|e| underlying_value_usd(s, e.chain_id, &e.market, &e.repay_amount, now))
+                .sum();
+
             CrossChainMarketSummary {
                 total_supply_usd: total_supply,
                 total_borrow_usd: total_borrow,
@@ -182,6 +348,9 @@ impl ChainFusionManager {
                 best_borrow_rates: borrow_rates,
                 liquidity_flows,
                 market_health,
+                window_seconds,
+                liquidation_count: windowed_liquidations.len() as u64,
+                liquidation_volume_usd,
             }
         })
     }
@@ -189,6 +358,30 @@ impl ChainFusionManager {
     pub fn get_chain_analytics(&self, chain_id: u64) -> Option<ChainAnalytics> {
         read_state(|s| {
             let config = self.chain_configs.get(&chain_id)?;
+            let last_synced = self.next_sync_block(chain_id).unwrap_or(0);
+
+            let events_processed = s.events_processed.get(&chain_id).copied().unwrap_or(0);
+            if events_processed == 0 {
+                return Some(ChainAnalytics {
+                    chain_id,
+                    initialized: false,
+                    total_events_processed: 0,
+                    active_users: 0,
+                    total_volume_24h: 0.0,
+                    average_health_factor: 0.0,
+                    liquidation_events_24h: 0,
+                    gas_cost_estimate: 0.0,
+                    sync_status: SyncStatus {
+                        last_synced_block: last_synced,
+                        latest_network_block: last_synced,
+                        sync_lag_blocks: 0,
+                        estimated_sync_time_seconds: 0,
+                        estimated_sync_time_ms: 0,
+                        estimated_sync_time: format_duration_ms(0),
+                        sync_health: "NotSynced".to_string(),
+                    },
+                });
+            }
             
             let user_positions: Vec<_> = s.user_positions.iter()
                 .filter(|((_, cid), _)| *cid == chain_id)
@@ -207,43 +400,37 @@ impl ChainFusionManager {
                 .filter(|(_, pos)| pos.health_factor < 1.0)
                 .count() as u64;
             
-            let last_synced = self.last_synced_blocks.get(&chain_id).unwrap_or(&0);
-            
             // Mock latest block - in real implementation, fetch from chain
             let latest_block = last_synced + 10; // Simulate some lag
-            let sync_lag = latest_block.saturating_sub(*last_synced);
-            
-            let sync_status = SyncStatus {
-                last_synced_block: *last_synced,
-                latest_network_block: latest_block,
-                sync_lag_blocks: sync_lag,
-                estimated_sync_time_seconds: sync_lag * config.block_time_ms / 1000,
-                sync_health: if sync_lag < 5 { "Healthy" } 
-                            else if sync_lag < 20 { "Lagging" } 
-                            else { "Stalled" }.to_string(),
-            };
+            let sync_status = sync_status(last_synced, latest_block, config.block_time_ms);
             
             Some(ChainAnalytics {
                 chain_id,
-                total_events_processed: user_positions.len() as u64 * 10, // Mock
+                initialized: true,
+                total_events_processed: events_processed,
                 active_users,
                 total_volume_24h: 1000000.0, // Mock
                 average_health_factor,
                 liquidation_events_24h: liquidation_events,
-                gas_cost_estimate: estimate_gas_cost(chain_id),
+                gas_cost_estimate: estimate_gas_cost(s, chain_id),
                 sync_status,
             })
         })
     }
     
+    /// Borrowers near or past liquidation, most critical first. Borrowers in their
+    /// post-attempt cooldown are left out.
     pub fn get_liquidation_opportunities_enhanced(&self) -> Vec<(String, CrossChainUserPosition)> {
+        let now = current_timestamp();
         read_state(|s| {
             let mut opportunities = Vec::new();
             let mut user_addresses: std::collections::HashSet<String> = std::collections::HashSet::new();
             
-            // Collect all unique user addresses
+            // Collect all unique user addresses, folding differently formatted keys together
             for ((user, _), _) in &s.user_positions {
-                user_addresses.insert(user.clone());
+                if liquidation_cooldown::remaining(s, user, now).is_none() {
+                    user_addresses.insert(canonical_address(user));
+                }
             }
             
             // Check each user's cross-chain position
@@ -264,12 +451,100 @@ impl ChainFusionManager {
 }
 
 // Helper functions
-fn calculate_liquidation_risk(health_factor: f64, total_borrow: f64) -> LiquidationRisk {
-    let (risk_level, recommended_action) = if health_factor < 1.0 {
+
+#[cfg(not(test))]
+fn current_timestamp() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
+
+// Unit tests run outside a canister, where the time system call is unavailable
+#[cfg(test)]
+fn current_timestamp() -> u64 {
+    crate::test_support::now()
+}
+
+/// Lowercase 20-byte hex form of an address, accepting checksummed addresses and
+/// 32-byte left-padded topic words.
+pub fn canonical_address(address: &str) -> String {
+    let hex = address.trim().trim_start_matches("0x").trim_start_matches("0X").to_lowercase();
+    let hex = if hex.len() == 64 && hex[..24].chars().all(|c| c == '0') { &hex[24..] } else { hex.as_str() };
+    format!("0x{}", hex)
+}
+
+/// Every user with a tracked position on any chain, deduplicated by canonical address.
+pub fn known_users(state: &State) -> BTreeSet<String> {
+    state.user_positions.keys().map(|(user, _)| canonical_address(user)).collect()
+}
+
+/// The `offset`..`offset + limit` slice of `known_users`; the sorted order keeps
+/// successive windows disjoint while the user set is unchanged.
+pub fn list_users(state: &State, offset: u64, limit: u64) -> UserPage {
+    let users = known_users(state);
+    let total = users.len() as u64;
+    let limit = limit.min(MAX_USERS_PAGE_SIZE);
+    UserPage {
+        users: users.into_iter()
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(limit as usize)
+            .collect(),
+        offset,
+        total,
+    }
+}
+
+/// Fold a second entry for the same user and chain into `existing`. Both entries
+/// may hold the same market, so balances are keyed by market with the more recently
+/// updated entry winning, and the merged position is revalued and scored with the
+/// chain's collateral factor rather than summing the two entries' totals.
+pub(crate) fn merge_position(
+    existing: &mut UserPosition,
+    other: &UserPosition,
+    prices: &PriceBook,
+    markets: &BTreeMap<(u64, String), MarketState>,
+    collateral_factor: &CollateralFactor,
+    now: u64,
+) {
+    let other_is_newer = other.updated_at >= existing.updated_at;
+    merge_balances(&mut existing.p_token_balances, &other.p_token_balances, other_is_newer);
+    merge_balances(&mut existing.borrow_balances, &other.borrow_balances, other_is_newer);
+    merge_balances(&mut existing.borrow_indexes, &other.borrow_indexes, other_is_newer);
+    for market in &other.collateral_enabled {
+        if !existing.collateral_enabled.iter().any(|m| m.eq_ignore_ascii_case(market)) {
+            existing.collateral_enabled.push(market.clone());
+        }
+    }
+    existing.updated_at = existing.updated_at.max(other.updated_at);
+
+    price_oracle::revalue_position(existing, prices, markets, now);
+    existing.health_factor = math::health_factor(
+        existing.total_collateral_value_usd * collateral_factor.value,
+        existing.total_borrow_value_usd,
+    );
+    existing.collateral_factor_capped = collateral_factor.caps(existing);
+}
+
+/// Add `other`'s per-market entries to `balances`. A market present in both keeps
+/// `other`'s amount only when it comes from the newer entry.
+fn merge_balances(balances: &mut Vec<(String, u128)>, other: &[(String, u128)], other_is_newer: bool) {
+    for (market, amount) in other {
+        match balances.iter_mut().find(|(m, _)| m.eq_ignore_ascii_case(market)) {
+            Some(entry) if other_is_newer => entry.1 = *amount,
+            Some(_) => {}
+            None => balances.push((market.clone(), *amount)),
+        }
+    }
+}
+
+/// Health factor below which a position can be liquidated on-chain. Risk
+/// thresholds only grade positions; they never move this line.
+const LIQUIDATION_HEALTH_FACTOR: f64 = 1.0;
+
+fn calculate_liquidation_risk(health_factor: f64, total_borrow: f64, thresholds: &RiskThresholds) -> LiquidationRisk {
+    let (risk_level, recommended_action) = if health_factor < thresholds.critical {
         ("Critical", "Immediate repayment or collateral addition required")
-    } else if health_factor < 1.1 {
+    } else if health_factor < thresholds.high {
         ("High", "Add collateral or repay debt soon")
-    } else if health_factor < 1.3 {
+    } else if health_factor < thresholds.medium {
         ("Medium", "Monitor position closely")
     } else {
         ("Low", "Position is healthy")
@@ -277,56 +552,134 @@ fn calculate_liquidation_risk(health_factor: f64, total_borrow: f64) -> Liquidat
     
     LiquidationRisk {
         risk_level: risk_level.to_string(),
-        liquidation_threshold: 1.0,
-        buffer_amount: (health_factor - 1.0) * total_borrow,
+        liquidation_threshold: LIQUIDATION_HEALTH_FACTOR,
+        buffer_amount: (health_factor - LIQUIDATION_HEALTH_FACTOR) * total_borrow,
         recommended_action: recommended_action.to_string(),
     }
 }
 
-fn find_arbitrage_opportunities(
-    user_positions: &[(u64, UserPosition)], 
-    _market_states: &std::collections::BTreeMap<u64, MarketState>
-) -> Vec<ArbitrageOpportunity> {
+fn arbitrage_opportunities_at(filter: &ArbitrageFilter, now: u64) -> Vec<ArbitrageOpportunity> {
+    read_state(|s| {
+        let mut opportunities: Vec<ArbitrageOpportunity> = find_arbitrage_opportunities(s, None, now)
+            .into_iter()
+            .filter(|opportunity| filter.admits(opportunity))
+            .collect();
+        opportunities.sort_by(|a, b| b.estimated_profit_usd.total_cmp(&a.estimated_profit_usd));
+        opportunities
+    })
+}
+
+/// Borrow-on-one-chain, supply-on-another strategies for an underlying asset
+/// listed on several chains, wherever the effective supply APY on one beats the
+/// effective borrow APY on the other. The estimate is one year of that spread on
+/// `notional_usd`, capped by the liquidity available to borrow (uncapped when no
+/// notional is given); the risk score is the borrow market's utilization.
+fn find_arbitrage_opportunities(state: &State, notional_usd: Option<f64>, now: u64) -> Vec<ArbitrageOpportunity> {
+    let markets: Vec<(&MarketState, rates::ChainAssetRate)> = state.market_states.values()
+        .filter(|m| !m.underlying_symbol.is_empty())
+        .filter_map(|m| {
+            let config = state.chain_configs.get(&m.chain_id)?;
+            Some((m, rates::chain_rate(m, &config.name, config.block_time_ms, &state.market_incentives)))
+        })
+        .collect();
+
     let mut opportunities = Vec::new();
-    
-    // Simple arbitrage detection based on rate differences
-    let chains: Vec<u64> = user_positions.iter().map(|(chain_id, _)| *chain_id).collect();
-    
-    for &chain_a in &chains {
-        for &chain_b in &chains {
-            if chain_a != chain_b {
-                // Mock arbitrage opportunity
-                opportunities.push(ArbitrageOpportunity {
-                    strategy: "Supply/Borrow Arbitrage".to_string(),
-                    source_chain: chain_a,
-                    target_chain: chain_b,
-                    estimated_profit_usd: 100.0, // Mock calculation
-                    risk_score: 0.3,
-                    execution_complexity: "Medium".to_string(),
-                });
+    for (supply_market, supply) in &markets {
+        for (borrow_market, borrow) in &markets {
+            if supply_market.chain_id == borrow_market.chain_id
+                || !supply_market.underlying_symbol.eq_ignore_ascii_case(&borrow_market.underlying_symbol)
+            {
+                continue;
+            }
+            let spread_apy = supply.supply.effective_apy - borrow.borrow.effective_apy;
+            let price = state.prices.resolve(&borrow_market.market_address, None, now);
+            let borrowable_usd = borrow.available_liquidity * price.price_usd;
+            let capital_usd = notional_usd.map_or(borrowable_usd, |notional| notional.min(borrowable_usd));
+            if spread_apy <= 0.0 || capital_usd <= 0.0 {
+                continue;
             }
+            opportunities.push(ArbitrageOpportunity {
+                strategy: format!(
+                    "Borrow {} on chain {}, supply on chain {}",
+                    borrow_market.underlying_symbol, borrow_market.chain_id, supply_market.chain_id
+                ),
+                source_chain: borrow_market.chain_id,
+                target_chain: supply_market.chain_id,
+                estimated_profit_usd: capital_usd * spread_apy / 100.0,
+                risk_score: utilization(borrow_market),
+                execution_complexity: "Medium".to_string(),
+            });
         }
     }
-    
     opportunities
 }
 
-fn calculate_liquidity_flows(_market_states: &std::collections::BTreeMap<u64, MarketState>) -> Vec<LiquidityFlow> {
-    // Mock implementation - in reality, analyze transaction patterns
-    vec![
-        LiquidityFlow {
-            from_chain: 10143,
-            to_chain: 97,
-            asset: "USDC".to_string(),
-            flow_direction: "Supply".to_string(),
-            incentive_apy: 2.5,
-        }
-    ]
+/// Share of a market's supplied funds that is borrowed, between 0 and 1.
+fn utilization(market: &MarketState) -> f64 {
+    let supplied = market.cash.saturating_add(market.total_borrows).saturating_sub(market.reserves);
+    if supplied == 0 {
+        return 0.0;
+    }
+    (market.total_borrows as f64 / supplied as f64).clamp(0.0, 1.0)
+}
+
+/// Cross-chain supplies and borrows completed since `since`, aggregated per route,
+/// asset and direction. Supplies move liquidity from the source chain to the
+/// lending chain; borrows move it back.
+fn calculate_liquidity_flows(state: &State, since: Option<u64>, now: u64) -> Vec<LiquidityFlow> {
+    let mut flows: BTreeMap<(u64, u64, String, &'static str), LiquidityFlow> = BTreeMap::new();
+    let completed = state.cross_chain_requests.values()
+        .filter(|r| r.status == TransactionStatus::Completed)
+        .filter(|r| since.is_none_or(|since| r.created_at >= since));
+    for record in completed {
+        let request = &record.request;
+        let (underlying, direction, from_chain, to_chain) = match &request.action {
+            PeridotAction::Supply { underlying_asset } => {
+                (underlying_asset, "Supply", request.source_chain_id, request.target_chain_id)
+            }
+            PeridotAction::Borrow { underlying_asset, .. } => {
+                (underlying_asset, "Borrow", request.target_chain_id, request.source_chain_id)
+            }
+            _ => continue,
+        };
+        let Some(market) = Address::from_str(underlying).ok()
+            .and_then(|underlying| token_metadata::cached_market_for(state, request.target_chain_id, underlying))
+            .map(|market| market.to_string())
+        else {
+            continue;
+        };
+        let asset = Address::from_str(&market).ok()
+            .and_then(|address| state.token_metadata.get(&(request.target_chain_id, address)))
+            .map_or_else(|| underlying.clone(), |metadata| metadata.symbol.clone());
+        let incentive = state.market_incentives.get(&market.to_lowercase()).cloned().unwrap_or_default();
+        let volume_usd = underlying_value_usd(state, request.target_chain_id, &market, &request.amount, now).unwrap_or(0.0);
+
+        flows.entry((from_chain, to_chain, asset.clone(), direction))
+            .or_insert_with(|| LiquidityFlow {
+                from_chain,
+                to_chain,
+                asset,
+                flow_direction: direction.to_string(),
+                incentive_apy: if direction == "Supply" { incentive.supply_apy } else { incentive.borrow_apy },
+                volume_usd: 0.0,
+            })
+            .volume_usd += volume_usd;
+    }
+    flows.into_values().collect()
+}
+
+/// USD value of an amount in `market`'s underlying token, scaled by the token's
+/// own decimals and priced at the market's pToken price. `None` when the amount
+/// does not parse or the decimals are not known yet.
+fn underlying_value_usd(state: &State, chain_id: u64, market: &str, amount: &str, now: u64) -> Option<f64> {
+    let amount = amount.parse::<u128>().ok()?;
+    let decimals = token_metadata::cached_decimals(state, chain_id, market)?;
+    Some(math::usd_value(amount, decimals, state.prices.resolve(market, None, now).price_usd))
 }
 
 fn calculate_market_health(
     user_positions: &std::collections::BTreeMap<(String, u64), UserPosition>,
-    _market_states: &std::collections::BTreeMap<u64, MarketState>
+    _market_states: &std::collections::BTreeMap<(u64, String), MarketState>
 ) -> MarketHealth {
     let total_positions = user_positions.len();
     let unhealthy_positions = user_positions.values()
@@ -354,11 +707,307 @@ fn calculate_market_health(
     }
 }
 
-fn estimate_gas_cost(chain_id: u64) -> f64 {
-    match chain_id {
-                    10143 => 0.001, // Monad - very low
-        97 => 0.01,     // BNB testnet
-        1 => 5.0,       // Ethereum mainnet
-        _ => 1.0,       // Default
+fn sync_status(last_synced: u64, latest_block: u64, block_time_ms: u64) -> SyncStatus {
+    let sync_lag = latest_block.saturating_sub(last_synced);
+    // Milliseconds, so sub-second block times do not truncate a real lag to zero
+    let estimated_ms = sync_lag.saturating_mul(block_time_ms);
+    SyncStatus {
+        last_synced_block: last_synced,
+        latest_network_block: latest_block,
+        sync_lag_blocks: sync_lag,
+        estimated_sync_time_seconds: estimated_ms.div_ceil(1000),
+        estimated_sync_time_ms: estimated_ms,
+        estimated_sync_time: format_duration_ms(estimated_ms),
+        sync_health: if sync_lag < 5 { "Healthy" }
+                    else if sync_lag < 20 { "Lagging" }
+                    else { "Stalled" }.to_string(),
     }
-} 
\ No newline at end of file
+}
+
+fn format_duration_ms(ms: u64) -> String {
+    if ms < 1000 {
+        return format!("{}ms", ms);
+    }
+    let secs = ms.div_ceil(1000);
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+/// USD cost of a typical liquidation on the chain at its last observed gas price,
+/// paid in the chain's own gas token. Zero when that token has no configured price.
+fn estimate_gas_cost(state: &State, chain_id: u64) -> f64 {
+    const LIQUIDATION_GAS: u64 = 180_000;
+    let gas_price_gwei = state.observed_gas_prices.get(&chain_id)
+        .map(|wei| math::wei_to_human(*wei, 9))
+        .unwrap_or(gas_price::DEFAULT_GAS_PRICE_GWEI);
+    state.chain_configs.get(&chain_id)
+        .and_then(|config| config.native_symbol.as_deref())
+        .and_then(|symbol| gas_price::gas_cost_usd(state, symbol, LIQUIDATION_GAS, gas_price_gwei).ok())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::new_market_state;
+    use crate::state::market_key;
+    use alloy::primitives::Address;
+
+    fn supply_request(amount: &str) -> crate::cross_chain_transactions::CrossChainRequest {
+        crate::cross_chain_transactions::CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    const NOW: u64 = 1_000;
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    fn position(market: &Address, supplied: u128, borrowed: u128, updated_at: u64) -> UserPosition {
+        UserPosition {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            chain_id: 10143,
+            p_token_balances: vec![(market.to_string(), supplied)],
+            borrow_balances: vec![(market.to_string().to_lowercase(), borrowed)],
+            collateral_enabled: vec![market.to_string()],
+            health_factor: 0.0,
+            total_collateral_value_usd: supplied as f64 / ONE as f64,
+            total_borrow_value_usd: borrowed as f64 / ONE as f64,
+            account_liquidity: 0.0,
+            price_stale: false,
+            stale_since_blocks: None,
+            borrow_indexes: Vec::new(),
+            collateral_factor_capped: false,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn merged_positions_keep_one_balance_per_market_and_apply_the_collateral_factor() {
+        let market = Address::repeat_byte(0x01);
+        let mut prices = PriceBook::default();
+        prices.record_live(&market.to_string(), 1.0, NOW);
+        let mut markets = BTreeMap::new();
+        let mut state = new_market_state(10143, market);
+        state.exchange_rate = ONE;
+        markets.insert(market_key(10143, &state.market_address), state);
+
+        let mut existing = position(&market, 10 * ONE, 2 * ONE, 1);
+        let newer = position(&market, 20 * ONE, 5 * ONE, 2);
+        let factor = CollateralFactor { value: 0.5, capped_markets: Vec::new() };
+        merge_position(&mut existing, &newer, &prices, &markets, &factor, NOW);
+
+        assert_eq!(existing.p_token_balances, vec![(market.to_string(), 20 * ONE)]);
+        assert_eq!(existing.borrow_balances.len(), 1);
+        assert_eq!(existing.collateral_enabled.len(), 1);
+        assert_eq!(existing.total_collateral_value_usd, 20.0);
+        assert_eq!(existing.total_borrow_value_usd, 5.0);
+        assert_eq!(existing.health_factor, 2.0);
+        assert_eq!(existing.updated_at, 2);
+
+        // An older duplicate does not overwrite the newer amounts
+        let older = position(&market, ONE, ONE, 0);
+        merge_position(&mut existing, &older, &prices, &markets, &factor, NOW);
+        assert_eq!(existing.p_token_balances[0].1, 20 * ONE);
+    }
+
+    #[test]
+    fn summary_windows_scope_flows_and_volume_in_underlying_decimals() {
+        use crate::request_store;
+        use crate::state::{init_test_state, mutate_state};
+        use crate::token_metadata::TokenMetadata;
+
+        init_test_state();
+        let market = Address::repeat_byte(0x01);
+        let usdc = Address::repeat_byte(0x02);
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, market), TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some(usdc.to_string()),
+            });
+            s.prices.record_live(&market.to_string(), 1.0, NOW);
+        });
+        // 5 USDC supplied from BNB long ago and again just now
+        let mut request = supply_request("5000000");
+        request.action = PeridotAction::Supply { underlying_asset: usdc.to_string() };
+        for (id, created_at) in [("old", 10), ("new", NOW - 10)] {
+            request_store::create_at(id, &request, created_at).unwrap();
+            mutate_state(|s| s.cross_chain_requests.get_mut(id).unwrap().status = TransactionStatus::Completed);
+        }
+        for observed_at in [10, NOW - 10] {
+            crate::liquidation_events::record(LiquidationEvent {
+                chain_id: 10143,
+                market: market.to_string(),
+                liquidator: String::new(),
+                borrower: String::new(),
+                repay_amount: "2000000".to_string(),
+                p_token_collateral: market.to_string(),
+                seize_tokens: "0".to_string(),
+                block_number: None,
+                transaction_hash: None,
+                block_timestamp: None,
+                observed_at,
+            });
+        }
+
+        let manager = ChainFusionManager::new();
+        let all_time = manager.market_summary_at(None, NOW);
+        assert_eq!(all_time.liquidation_count, 2);
+        assert!((all_time.liquidation_volume_usd - 4.0).abs() < 1e-9);
+        assert_eq!(all_time.liquidity_flows.len(), 1);
+        let flow = &all_time.liquidity_flows[0];
+        assert_eq!((flow.from_chain, flow.to_chain, flow.asset.as_str()), (97, 10143, "USDC"));
+        assert!((flow.volume_usd - 10.0).abs() < 1e-9);
+
+        let last_minute = manager.market_summary_at(Some(60), NOW);
+        assert_eq!(last_minute.liquidation_count, 1);
+        assert!((last_minute.liquidation_volume_usd - 2.0).abs() < 1e-9);
+        assert!((last_minute.liquidity_flows[0].volume_usd - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn custom_thresholds_grade_risk_without_moving_the_liquidation_line() {
+        let thresholds = RiskThresholds { critical: 1.05, high: 1.2, medium: 1.5 };
+
+        let risk = calculate_liquidation_risk(1.02, 100.0, &thresholds);
+        assert_eq!(risk.risk_level, "Critical");
+        assert_eq!(risk.liquidation_threshold, 1.0);
+        assert!((risk.buffer_amount - 2.0).abs() < 1e-9);
+
+        assert_eq!(calculate_liquidation_risk(1.1, 100.0, &thresholds).risk_level, "High");
+    }
+
+    #[test]
+    fn arbitrage_follows_rate_spreads_and_the_filter() {
+        use crate::state::{init_test_state, mutate_state};
+
+        init_test_state();
+        let add_usdc_market = |chain_id: u64, byte: u8, supply_rate: u64, borrow_rate: u64, cash: u128| {
+            let mut market = new_market_state(chain_id, Address::repeat_byte(byte));
+            market.underlying_symbol = "USDC".to_string();
+            market.underlying_decimals = 6;
+            market.supply_rate = supply_rate;
+            market.borrow_rate = borrow_rate;
+            market.cash = cash;
+            mutate_state(|s| {
+                s.prices.record_live(&market.market_address, 1.0, NOW);
+                s.market_states.insert(market_key(chain_id, &market.market_address), market);
+            });
+        };
+        // Monad pays suppliers far more than BNB charges borrowers, and BNB has 1M USDC to lend
+        add_usdc_market(10143, 0x01, 3_000_000_000, 4_000_000_000, 0);
+        add_usdc_market(97, 0x02, 10_000_000, 100_000_000, 1_000_000_000_000);
+
+        let all = arbitrage_opportunities_at(&ArbitrageFilter { min_profit_usd: 0.0, max_risk_score: 1.0 }, NOW);
+        assert_eq!(all.len(), 1);
+        let opportunity = &all[0];
+        assert_eq!((opportunity.source_chain, opportunity.target_chain), (97, 10143));
+        assert!(opportunity.estimated_profit_usd > 0.0);
+        assert_eq!(opportunity.risk_score, 0.0);
+
+        let marginal_bar = ArbitrageFilter { min_profit_usd: opportunity.estimated_profit_usd + 1.0, max_risk_score: 1.0 };
+        assert!(arbitrage_opportunities_at(&marginal_bar, NOW).is_empty());
+    }
+
+    #[test]
+    fn user_pages_are_disjoint_windows_over_every_user() {
+        crate::state::init_test_state();
+        crate::state::mutate_state(|s| {
+            for byte in 1..=5u8 {
+                let user = Address::repeat_byte(0xa0 + byte).to_string();
+                s.user_positions.insert((user.clone(), 10143), user_position(10143));
+                // The same user on a second chain, keyed in another case, is listed once
+                s.user_positions.insert((user.to_lowercase(), 97), user_position(97));
+            }
+        });
+
+        let pages: Vec<UserPage> = read_state(|s| (0..3).map(|page| list_users(s, page * 2, 2)).collect());
+        assert_eq!(pages.iter().map(|p| p.users.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+        assert!(pages.iter().all(|p| p.total == 5));
+
+        let listed: Vec<String> = pages.into_iter().flat_map(|p| p.users).collect();
+        let unique: BTreeSet<&String> = listed.iter().collect();
+        assert_eq!(unique.len(), listed.len());
+        assert_eq!(listed, read_state(known_users).into_iter().collect::<Vec<_>>());
+
+        assert!(read_state(|s| list_users(s, 5, 2)).users.is_empty());
+    }
+
+    #[test]
+    fn short_lags_on_fast_chains_are_not_reported_as_zero() {
+        // Monad: 3 blocks of 1000ms
+        let monad = sync_status(100, 103, 1000);
+        assert_eq!((monad.estimated_sync_time_ms, monad.estimated_sync_time_seconds), (3_000, 3));
+        assert_eq!((monad.estimated_sync_time.as_str(), monad.sync_health.as_str()), ("3s", "Healthy"));
+
+        // A sub-second chain one block behind rounds up to a whole second
+        let fast = sync_status(100, 101, 400);
+        assert_eq!((fast.estimated_sync_time_ms, fast.estimated_sync_time_seconds), (400, 1));
+        assert_eq!(fast.estimated_sync_time, "400ms");
+
+        assert_eq!(sync_status(100, 100, 1000).estimated_sync_time_seconds, 0);
+        assert_eq!(format_duration_ms(125_000), "2m 5s");
+    }
+
+    #[test]
+    fn chains_report_zeroed_analytics_until_their_first_event() {
+        crate::state::init_test_state();
+        crate::state::mutate_state(|s| {
+            s.user_positions.insert(("0x00000000000000000000000000000000000000aa".to_string(), 10143), user_position(10143));
+        });
+        let manager = ChainFusionManager::new();
+
+        let fresh = manager.get_chain_analytics(10143).unwrap();
+        assert!(!fresh.initialized);
+        assert_eq!((fresh.total_events_processed, fresh.active_users, fresh.liquidation_events_24h), (0, 0, 0));
+        assert_eq!((fresh.total_volume_24h, fresh.average_health_factor, fresh.gas_cost_estimate), (0.0, 0.0, 0.0));
+        assert_eq!((fresh.sync_status.sync_lag_blocks, fresh.sync_status.sync_health.as_str()), (0, "NotSynced"));
+
+        crate::state::mutate_state(|s| s.events_processed.insert(10143, 1));
+        let synced = manager.get_chain_analytics(10143).unwrap();
+        assert!(synced.initialized);
+        assert_eq!(synced.active_users, 1);
+        assert!(manager.get_chain_analytics(1).is_none());
+    }
+
+    #[test]
+    fn lookups_separate_unknown_users_from_empty_positions() {
+        crate::state::init_test_state();
+        let manager = ChainFusionManager::new();
+        let user = "0x00000000000000000000000000000000000000aa";
+        assert!(matches!(manager.lookup_user_position(user), UserPositionLookup::Unknown));
+
+        crate::state::mutate_state(|s| {
+            s.user_positions.insert((user.to_string(), 10143), user_position(10143));
+        });
+        match manager.lookup_user_position(&user.to_uppercase().replace("0X", "0x")) {
+            UserPositionLookup::Empty { user_address } => assert_eq!(user_address, user),
+            other => panic!("expected an empty position, got {:?}", other),
+        }
+
+        let market = Address::repeat_byte(0x01);
+        crate::state::mutate_state(|s| {
+            s.user_positions.insert((user.to_string(), 10143), position(&market, ONE, 0, NOW));
+        });
+        match manager.lookup_user_position(user) {
+            UserPositionLookup::Found(found) => assert!(found.positions_by_chain.contains_key(&10143)),
+            other => panic!("expected a funded position, got {:?}", other),
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use crate::{ChainId, EventLog, MarketState, UserPosition, EVENT_LOGS, MARKET_STATES, USER_POSITIONS};
+use crate::chain_fusion_manager::topic_address;
+use alloy::primitives::{hex, B256, U256};
+use alloy::sol_types::{sol_data, SolType};
 use candid::{CandidType, Deserialize, Nat};
 use ic_cdk::api::time;
 use std::str::FromStr;
@@ -25,8 +28,34 @@ pub struct LogEntry {
 }
 
 #[derive(CandidType, Deserialize, Debug)]
-pub struct GetLogsResponse {
-    pub result: Vec<LogEntry>,
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// JSON-RPC envelope for `eth_getLogs`: either a `result` or an `error` member.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum GetLogsResponse {
+    Success { result: Vec<LogEntry> },
+    Error { error: JsonRpcError },
+}
+
+impl GetLogsResponse {
+    /// Decode a raw JSON-RPC response body. A malformed body is an error, never an
+    /// empty result.
+    pub fn decode(body: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(body).map_err(|e| format!("Malformed eth_getLogs response: {}", e))
+    }
+
+    pub fn into_result(self) -> Result<Vec<LogEntry>, String> {
+        match self {
+            GetLogsResponse::Success { result } => Ok(result),
+            GetLogsResponse::Error { error } => {
+                Err(format!("eth_getLogs RPC error {}: {}", error.code, error.message))
+            }
+        }
+    }
 }
 
 // Peridot event signatures (keccak256 hashes)
@@ -107,7 +136,7 @@ async fn simulate_get_logs(
     ic_cdk::println!("Simulating eth_getLogs for contract {} on chain {}", contract_address, chain_id);
     
     // Return empty logs for now
-    Ok(vec![])
+    GetLogsResponse::decode(br#"{"jsonrpc":"2.0","id":1,"result":[]}"#)?.into_result()
 }
 
 async fn process_event_log(log: LogEntry, chain_id: ChainId) -> Result<(), String> {
@@ -125,21 +154,26 @@ async fn process_event_log(log: LogEntry, chain_id: ChainId) -> Result<(), Strin
         _ => return Err(format!("Unknown event signature: {}", event_signature)),
     };
     
-    let user_address = if log.topics.len() > 1 {
-        // Extract user address from topics[1] (first indexed parameter)
-        format!("0x{}", &log.topics[1][26..]) // Remove 0x and padding
-    } else {
-        "0x0000000000000000000000000000000000000000".to_string()
+    let user_address = match log.topics.get(1) {
+        // User address from topics[1] (first indexed parameter)
+        Some(topic) => match B256::from_str(topic) {
+            Ok(word) => format!("{:?}", topic_address(word)),
+            Err(_) => {
+                ic_cdk::println!("Skipping {} log with malformed topic {}", event_type, topic);
+                return Ok(());
+            }
+        },
+        None => "0x0000000000000000000000000000000000000000".to_string(),
     };
     
     let block_number = log.block_number
         .as_ref()
-        .and_then(|bn| u64::from_str_radix(&bn[2..], 16).ok())
+        .and_then(|bn| u64::from_str_radix(bn.get(2..)?, 16).ok())
         .unwrap_or(0);
     
     let log_index = log.log_index
         .as_ref()
-        .and_then(|li| u64::from_str_radix(&li[2..], 16).ok())
+        .and_then(|li| u64::from_str_radix(li.get(2..)?, 16).ok())
         .unwrap_or(0);
     
     let event_log = EventLog {
@@ -333,28 +367,43 @@ async fn get_current_block_number(chain_id: ChainId) -> Result<u64, String> {
 }
 
 // Helper functions for parsing event data
-fn parse_mint_data(_data: &str) -> Result<(u64, u64), String> {
-    // Parse mint event data: (minter, mintAmount, mintTokens)
-    // For MVP, return dummy values
-    Ok((1000000000000000000u64, 1000000000000000000u64)) // 1 token minted, 1 pToken received
+
+type Uint256 = sol_data::Uint<256>;
+
+/// Decode a log's hex `data` as the ABI-encoded non-indexed parameters `T`.
+/// alloy's decoder follows the head/tail layout, so dynamic fields (string,
+/// bytes, arrays) are read through their offset and length instead of being
+/// taken as fixed 32-byte words.
+fn decode_event_data<T: SolType>(data: &str) -> Result<T::RustType, String> {
+    let bytes = hex::decode(data).map_err(|e| format!("Event data is not hex: {}", e))?;
+    T::abi_decode_params(&bytes, true).map_err(|e| format!("Malformed event data: {}", e))
 }
 
-fn parse_redeem_data(_data: &str) -> Result<(u64, u64), String> {
-    // Parse redeem event data: (redeemer, redeemAmount, redeemTokens)
-    Ok((1000000000000000000u64, 1000000000000000000u64)) // 1 pToken redeemed, 1 token received
+/// Amounts are tracked as u64 in this monitor; larger values saturate.
+fn word_to_u64(value: U256) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
 }
 
-fn parse_borrow_data(_data: &str) -> Result<(u64, u64, u64), String> {
-    // Parse borrow event data: (borrower, borrowAmount, accountBorrows, totalBorrows)
-    Ok((500000000000000000u64, 500000000000000000u64, 500000000000000000u64)) // 0.5 tokens borrowed
+fn parse_mint_data(data: &str) -> Result<(u64, u64), String> {
+    // Mint(address indexed minter, uint256 mintAmount, uint256 mintTokens)
+    let (mint_amount, mint_tokens) = decode_event_data::<(Uint256, Uint256)>(data)?;
+    Ok((word_to_u64(mint_amount), word_to_u64(mint_tokens)))
 }
 
-fn parse_repay_data(_data: &str) -> Result<(u64, u64, u64), String> {
-    // Parse repay event data: (payer, borrower, repayAmount, accountBorrows, totalBorrows)
-    Ok((500000000000000000u64, 0u64, 0u64)) // 0.5 tokens repaid
+fn parse_redeem_data(data: &str) -> Result<(u64, u64), String> {
+    // Redeem(address indexed redeemer, uint256 redeemAmount, uint256 redeemTokens)
+    let (redeem_amount, redeem_tokens) = decode_event_data::<(Uint256, Uint256)>(data)?;
+    Ok((word_to_u64(redeem_amount), word_to_u64(redeem_tokens)))
 }
 
-fn parse_liquidate_data(_data: &str) -> Result<(u64, String, u64), String> {
-    // Parse liquidate event data: (liquidator, borrower, repayAmount, pTokenCollateral, seizeTokens)
-    Ok((250000000000000000u64, "0x1234567890123456789012345678901234567890".to_string(), 300000000000000000u64)) // 0.25 tokens repaid, 0.3 collateral seized
-} 
\ No newline at end of file
+fn parse_borrow_data(data: &str) -> Result<(u64, u64, u64), String> {
+    // Borrow(address indexed borrower, uint256 borrowAmount, uint256 accountBorrows, uint256 totalBorrows)
+    let (borrow_amount, account_borrows, total_borrows) = decode_event_data::<(Uint256, Uint256, Uint256)>(data)?;
+    Ok((word_to_u64(borrow_amount), word_to_u64(account_borrows), word_to_u64(total_borrows)))
+}
+
+fn parse_repay_data(data: &str) -> Result<(u64, u64, u64), String> {
+    // RepayBorrow(address indexed payer, address indexed borrower, uint256 repayAmount, uint256 accountBorrows, uint256 totalBorrows)
+    let (repay_amount, account_borrows, total_borrows) = decode_event_data::<(Uint256, Uint256, Uint256)>(data)?;
+    Ok((word_to_u64(repay_amount), word_to_u64(account_borrows), word_to_u64(total_borrows)))
+}
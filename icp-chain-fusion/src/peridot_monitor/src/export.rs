@@ -0,0 +1,49 @@
+use crate::state::read_state;
+
+// ===== BULK EXPORTS =====
+
+const POSITIONS_CSV_HEADER: &str = "user,chain_id,collateral_usd,borrow_usd,health_factor,updated_at\n";
+
+/// Current position book as CSV, optionally limited to one chain. Rows stop
+/// once the output would exceed the response byte budget; the last line is
+/// then a `# truncated` comment so consumers can tell the export is partial.
+pub fn positions_csv(chain_filter: Option<u64>) -> String {
+    read_state(|s| {
+        let budget = s.max_response_bytes;
+        let mut out = String::from(POSITIONS_CSV_HEADER);
+        let mut truncated = false;
+
+        for position in s.user_positions.values() {
+            if chain_filter.is_some_and(|chain_id| chain_id != position.chain_id) {
+                continue;
+            }
+            let row = format!(
+                "{},{},{},{},{},{}\n",
+                csv_field(&position.user_address),
+                position.chain_id,
+                position.total_collateral_value_usd,
+                position.total_borrow_value_usd,
+                position.health_factor,
+                position.updated_at
+            );
+            if out.len() + row.len() > budget {
+                truncated = true;
+                break;
+            }
+            out.push_str(&row);
+        }
+
+        if truncated {
+            out.push_str("# truncated\n");
+        }
+        out
+    })
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::rpc_manager;
+use crate::state::{mutate_state, read_state, State};
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::FeeHistory;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Blocks sampled by `eth_feeHistory` when pricing with `FeeHistory`.
+pub const FEE_HISTORY_BLOCKS: u64 = 10;
+/// Gas price assumed when a chain's source can't be reached or hasn't been observed.
+pub const DEFAULT_GAS_PRICE_GWEI: f64 = 20.0;
+
+/// Where a chain's gas price comes from.
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum GasPriceSource {
+    /// `eth_gasPrice`.
+    #[default]
+    RpcGasPrice,
+    /// Latest base fee plus the given percentile (0-100) of priority fees paid in
+    /// recent blocks, from `eth_feeHistory`.
+    FeeHistory { percentile: f64 },
+    /// Fixed price in wei, for chains whose RPC pricing is unreliable.
+    Static(u64),
+}
+
+impl GasPriceSource {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            GasPriceSource::FeeHistory { percentile } if !(0.0..=100.0).contains(&percentile) => {
+                Err(format!("Fee history percentile must be between 0 and 100, got {}", percentile))
+            }
+            GasPriceSource::Static(0) => Err("Static gas price must be greater than zero".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn source_for_chain(state: &State, chain_id: u64) -> GasPriceSource {
+    state.gas_price_sources.get(&chain_id).copied().unwrap_or_default()
+}
+
+/// USD prices of native gas tokens seeded at init; override them with
+/// `set_native_token_price`. The testnet MON value is a placeholder.
+pub fn default_native_token_prices() -> BTreeMap<String, f64> {
+    [("ETH", 3500.0), ("BNB", 600.0), ("MON", 1.0)]
+        .into_iter()
+        .map(|(symbol, price)| (symbol.to_string(), price))
+        .collect()
+}
+
+pub fn native_price_usd(state: &State, symbol: &str) -> Result<f64, String> {
+    state.native_token_prices.get(&symbol.to_uppercase()).copied()
+        .ok_or_else(|| format!("No USD price configured for gas token {}", symbol))
+}
+
+/// USD cost of `gas_units` at `gas_price_gwei`, paid in the gas token `symbol`.
+pub fn gas_cost_usd(state: &State, symbol: &str, gas_units: u64, gas_price_gwei: f64) -> Result<f64, String> {
+    Ok(gas_units as f64 * gas_price_gwei * 1e-9 * native_price_usd(state, symbol)?)
+}
+
+/// Current gas price on a chain in wei, from the chain's configured source. The
+/// result is kept in `observed_gas_prices` for estimates that can't make outcalls.
+pub async fn gas_price_wei(chain_id: u64) -> Result<u128, String> {
+    gas_price_wei_with(chain_id, &ProviderRpc).await
+}
+
+async fn gas_price_wei_with<R: GasPriceRpc>(chain_id: u64, rpc: &R) -> Result<u128, String> {
+    let source = read_state(|s| source_for_chain(s, chain_id));
+    let price = fetch_gas_price_wei(chain_id, source, rpc).await?;
+    mutate_state(|s| s.observed_gas_prices.insert(chain_id, price));
+    Ok(price)
+}
+
+/// The RPC calls behind the gas price sources.
+#[allow(async_fn_in_trait)]
+trait GasPriceRpc {
+    async fn gas_price(&self, chain_id: u64) -> Result<u128, String>;
+    async fn fee_history(&self, chain_id: u64, percentile: f64) -> Result<FeeHistory, String>;
+}
+
+/// Calls the chain's configured providers, with fallback.
+struct ProviderRpc;
+
+impl GasPriceRpc for ProviderRpc {
+    async fn gas_price(&self, chain_id: u64) -> Result<u128, String> {
+        rpc_manager::call_with_fallback(chain_id, |service| async move {
+            let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+            provider.get_gas_price().await.map_err(|e| e.to_string())
+        }).await
+    }
+
+    async fn fee_history(&self, chain_id: u64, percentile: f64) -> Result<FeeHistory, String> {
+        rpc_manager::call_with_fallback(chain_id, |service| async move {
+            let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+            provider
+                .get_fee_history(FEE_HISTORY_BLOCKS, BlockNumberOrTag::Latest, &[percentile])
+                .await
+                .map_err(|e| e.to_string())
+        }).await
+    }
+}
+
+async fn fetch_gas_price_wei<R: GasPriceRpc>(chain_id: u64, source: GasPriceSource, rpc: &R) -> Result<u128, String> {
+    match source {
+        GasPriceSource::Static(wei) => Ok(wei as u128),
+        GasPriceSource::RpcGasPrice => rpc.gas_price(chain_id).await,
+        GasPriceSource::FeeHistory { percentile } => {
+            price_from_fee_history(&rpc.fee_history(chain_id, percentile).await?)
+        }
+    }
+}
+
+/// Newest base fee plus the mean of the per-block priority fees at the requested
+/// percentile. Blocks without a reward sample are ignored.
+fn price_from_fee_history(history: &FeeHistory) -> Result<u128, String> {
+    let base_fee = history
+        .base_fee_per_gas
+        .last()
+        .copied()
+        .ok_or("Fee history returned no base fee")?;
+
+    let rewards: Vec<u128> = history
+        .reward
+        .iter()
+        .flatten()
+        .filter_map(|block| block.first().copied())
+        .collect();
+    let priority_fee = if rewards.is_empty() {
+        0
+    } else {
+        rewards.iter().sum::<u128>() / rewards.len() as u128
+    };
+
+    Ok(base_fee.saturating_add(priority_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use crate::test_support::block_on;
+
+    /// Canned provider answers: 7 gwei from `eth_gasPrice`, and three blocks of fee
+    /// history whose newest base fee is 12 gwei.
+    struct Canned;
+
+    impl GasPriceRpc for Canned {
+        async fn gas_price(&self, _: u64) -> Result<u128, String> {
+            Ok(7_000_000_000)
+        }
+
+        async fn fee_history(&self, _: u64, percentile: f64) -> Result<FeeHistory, String> {
+            assert_eq!(percentile, 50.0);
+            Ok(FeeHistory {
+                base_fee_per_gas: vec![10_000_000_000, 11_000_000_000, 12_000_000_000],
+                reward: Some(vec![vec![1_000_000_000], vec![3_000_000_000], vec![]]),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn each_source_prices_from_its_own_call() {
+        init_test_state();
+        let sources = [
+            (GasPriceSource::RpcGasPrice, 7_000_000_000),
+            // Newest base fee plus the mean of the sampled priority fees
+            (GasPriceSource::FeeHistory { percentile: 50.0 }, 14_000_000_000),
+            (GasPriceSource::Static(3_000_000_000), 3_000_000_000),
+        ];
+        for (source, expected) in sources {
+            mutate_state(|s| s.gas_price_sources.insert(10143, source));
+            assert_eq!(block_on(gas_price_wei_with(10143, &Canned)), Ok(expected), "{:?}", source);
+            assert_eq!(read_state(|s| s.observed_gas_prices.get(&10143).copied()), Some(expected));
+        }
+
+        // Chains without a configured source use eth_gasPrice
+        assert_eq!(read_state(|s| source_for_chain(s, 97)), GasPriceSource::RpcGasPrice);
+    }
+
+    #[test]
+    fn fee_history_without_rewards_is_the_base_fee() {
+        let history = FeeHistory { base_fee_per_gas: vec![5, 9], reward: None, ..Default::default() };
+        assert_eq!(price_from_fee_history(&history), Ok(9));
+        assert!(price_from_fee_history(&FeeHistory::default()).is_err());
+        assert!(GasPriceSource::FeeHistory { percentile: 101.0 }.validate().is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::state::{mutate_state, TaskType};
 
+/// Cross-chain executions allowed in flight at once unless configured otherwise.
+pub const DEFAULT_MAX_CONCURRENT_EXECUTIONS: u64 = 5;
+
 pub struct TimerGuard {
     task_type: TaskType,
 }
@@ -22,4 +25,31 @@ impl Drop for TimerGuard {
             s.active_tasks.remove(&self.task_type);
         });
     }
+}
+
+/// Holds one of the `max_concurrent_executions` slots for a cross-chain execution.
+/// The slot is released on drop, which also runs when a trap unwinds the call.
+pub struct ExecutionGuard;
+
+impl ExecutionGuard {
+    pub fn new() -> Result<Self, String> {
+        mutate_state(|s| {
+            if s.in_flight_executions >= s.max_concurrent_executions {
+                return Err(format!(
+                    "Busy: {} cross-chain executions already in flight (max {})",
+                    s.in_flight_executions, s.max_concurrent_executions
+                ));
+            }
+            s.in_flight_executions += 1;
+            Ok(ExecutionGuard)
+        })
+    }
+}
+
+impl Drop for ExecutionGuard {
+    fn drop(&mut self) {
+        mutate_state(|s| {
+            s.in_flight_executions = s.in_flight_executions.saturating_sub(1);
+        });
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,42 @@
+use crate::state::read_state;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub signer_initialized: bool,
+    pub evm_address: Option<String>,
+    pub degraded_chains: Vec<u64>,
+    pub paused_chains: Vec<u64>,
+    pub warnings: Vec<String>,
+}
+
+/// Operational health derived from state. Balance warnings reflect the last
+/// `get_evm_balance` call for each chain; degraded chains come from the sync watchdog.
+pub fn health_report() -> HealthReport {
+    read_state(|s| {
+        let mut warnings = Vec::new();
+
+        for balance in s.native_balances.values().filter(|b| b.is_low()) {
+            warnings.push(format!(
+                "Low {} balance on chain {}: {} wei",
+                balance.symbol, balance.chain_id, balance.balance_wei
+            ));
+        }
+
+        for chain_id in &s.sync_degraded {
+            warnings.push(format!(
+                "Chain {} has not synced successfully in over {}s",
+                chain_id, s.sync_stall_threshold_secs
+            ));
+        }
+
+        HealthReport {
+            signer_initialized: s.signer.is_some(),
+            evm_address: s.canister_evm_address.map(|a| a.to_string()),
+            degraded_chains: s.sync_degraded.iter().copied().collect(),
+            paused_chains: s.sync_paused_chains.iter().copied().collect(),
+            warnings,
+        }
+    })
+}
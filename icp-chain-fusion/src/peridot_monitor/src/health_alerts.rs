@@ -0,0 +1,183 @@
+use crate::enhanced_api::canonical_address;
+use crate::state::{mutate_state, State};
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// How far above its threshold a user's health factor must recover before the
+/// subscription can alert again.
+pub const ALERT_HYSTERESIS: f64 = 0.05;
+/// Subscriptions one principal may hold.
+pub const MAX_SUBSCRIPTIONS_PER_PRINCIPAL: usize = 20;
+/// Subscriptions held across all principals.
+pub const MAX_SUBSCRIPTIONS: usize = 10_000;
+/// Undrained alerts kept per principal; the oldest are dropped first.
+pub const MAX_PENDING_ALERTS: usize = 100;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct HealthAlertSubscription {
+    pub subscriber: Principal,
+    pub user_address: String, // canonical
+    pub threshold: f64,
+    /// Set when an alert fires; cleared once the factor recovers past the hysteresis band.
+    pub alerted: bool,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct HealthAlert {
+    pub user_address: String,
+    pub health_factor: f64,
+    pub threshold: f64,
+    pub triggered_at: u64, // seconds
+}
+
+pub fn subscribe(subscriber: Principal, user_address: &str, threshold: f64) -> Result<(), String> {
+    if subscriber == Principal::anonymous() {
+        return Err("Anonymous callers cannot subscribe to alerts".to_string());
+    }
+    if !threshold.is_finite() || threshold <= 0.0 {
+        return Err(format!("Alert threshold must be a positive health factor, got {}", threshold));
+    }
+    let user_address = canonical_address(user_address);
+    mutate_state(|s| {
+        let key = (user_address.clone(), subscriber);
+        if !s.health_alert_subscriptions.contains_key(&key) {
+            if s.health_alert_subscriptions.len() >= MAX_SUBSCRIPTIONS {
+                return Err(format!("The canister already holds the maximum of {} alert subscriptions", MAX_SUBSCRIPTIONS));
+            }
+            let held = s.health_alert_subscriptions.keys().filter(|(_, p)| *p == subscriber).count();
+            if held >= MAX_SUBSCRIPTIONS_PER_PRINCIPAL {
+                return Err(format!("At most {} alert subscriptions per principal", MAX_SUBSCRIPTIONS_PER_PRINCIPAL));
+            }
+        }
+        s.health_alert_subscriptions.insert(key, HealthAlertSubscription {
+            subscriber,
+            user_address,
+            threshold,
+            alerted: false,
+        });
+        Ok(())
+    })
+}
+
+pub fn unsubscribe(subscriber: Principal, user_address: &str) -> bool {
+    mutate_state(|s| s.health_alert_subscriptions.remove(&(canonical_address(user_address), subscriber)).is_some())
+}
+
+/// Take every pending alert for the subscriber, oldest first.
+pub fn poll(subscriber: Principal) -> Vec<HealthAlert> {
+    mutate_state(|s| s.pending_health_alerts.remove(&subscriber).unwrap_or_default())
+}
+
+/// The user's lowest health factor across chains, or `None` if no position is
+/// tracked. Positions without debt count as fully healthy.
+fn lowest_health_factor(state: &State, user_address: &str) -> Option<f64> {
+    state.user_positions.iter()
+        .filter(|((addr, _), _)| canonical_address(addr) == user_address)
+        .map(|(_, position)| position.health_factor)
+        .reduce(f64::min)
+}
+
+/// Compare every subscription with current health factors, queuing an alert
+/// when a user drops below the threshold. For changes that move many positions
+/// at once, such as a price or collateral factor update.
+pub fn evaluate(state: &mut State, now: u64) {
+    let users: BTreeSet<String> = state.health_alert_subscriptions.keys().map(|(user, _)| user.clone()).collect();
+    for user in users {
+        evaluate_user(state, &user, now);
+    }
+}
+
+/// `evaluate` for the subscriptions on a single user, after an event that only
+/// touched that user's positions.
+pub fn evaluate_user(state: &mut State, user_address: &str, now: u64) {
+    let user_address = canonical_address(user_address);
+    let Some(health_factor) = lowest_health_factor(state, &user_address) else {
+        return;
+    };
+    let from = (user_address.clone(), Principal::management_canister());
+    let subscriptions = state.health_alert_subscriptions.range_mut(from..)
+        .take_while(|((user, _), _)| *user == user_address);
+    for (_, subscription) in subscriptions {
+        if !subscription.alerted && health_factor < subscription.threshold {
+            subscription.alerted = true;
+            let pending = state.pending_health_alerts.entry(subscription.subscriber).or_default();
+            pending.push(HealthAlert {
+                user_address: subscription.user_address.clone(),
+                health_factor,
+                threshold: subscription.threshold,
+                triggered_at: now,
+            });
+            if pending.len() > MAX_PENDING_ALERTS {
+                pending.remove(0);
+            }
+        } else if subscription.alerted && health_factor >= subscription.threshold + ALERT_HYSTERESIS {
+            subscription.alerted = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::PersistedState;
+    use crate::state::{init_test_state, read_state, UserPosition};
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    const ALICE: &str = "0x00000000000000000000000000000000000000aa";
+    const BOB: &str = "0x00000000000000000000000000000000000000bb";
+
+    fn track(user: &str, health_factor: f64) {
+        mutate_state(|s| {
+            let position = UserPosition { user_address: user.to_string(), health_factor, ..user_position(10143) };
+            s.user_positions.insert((user.to_string(), 10143), position);
+        });
+    }
+
+    #[test]
+    fn events_only_evaluate_the_affected_user() {
+        init_test_state();
+        let subscriber = Principal::from_slice(&[1]);
+        subscribe(subscriber, ALICE, 1.2).unwrap();
+        subscribe(subscriber, BOB, 1.2).unwrap();
+        track(ALICE, 1.1);
+        track(BOB, 1.1);
+
+        mutate_state(|s| evaluate_user(s, &ALICE.to_uppercase(), 10));
+        let alerted: Vec<String> = read_state(|s| s.pending_health_alerts[&subscriber].iter().map(|a| a.user_address.clone()).collect());
+        assert_eq!(alerted, vec![ALICE.to_string()]);
+        assert!(read_state(|s| !s.health_alert_subscriptions[&(BOB.to_string(), subscriber)].alerted));
+    }
+
+    #[test]
+    fn subscriptions_are_capped() {
+        init_test_state();
+        let subscriber = Principal::from_slice(&[1]);
+        for i in 0..MAX_SUBSCRIPTIONS_PER_PRINCIPAL {
+            subscribe(subscriber, &format!("0x{:040x}", i + 1), 1.2).unwrap();
+        }
+        let error = subscribe(subscriber, &format!("0x{:040x}", 999), 1.2).unwrap_err();
+        assert!(error.contains("per principal"), "{}", error);
+        // Updating a held subscription is not a new one
+        assert!(subscribe(subscriber, &format!("0x{:040x}", 1), 1.5).is_ok());
+    }
+
+    #[test]
+    fn undrained_alerts_survive_an_upgrade() {
+        init_test_state();
+        let subscriber = Principal::from_slice(&[1]);
+        subscribe(subscriber, ALICE, 1.2).unwrap();
+        track(ALICE, 1.1);
+        mutate_state(|s| evaluate(s, 10));
+
+        let snapshot = read_state(PersistedState::capture);
+        init_test_state();
+        mutate_state(|s| snapshot.restore(s));
+        let alerts = poll(subscriber);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].user_address.as_str(), alerts[0].triggered_at), (ALICE, 10));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{export, metrics};
+use candid::{CandidType, Deserialize};
+use serde_bytes::ByteBuf;
+
+// ===== CANISTER HTTP INTERFACE =====
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+/// Serve `GET /metrics` in Prometheus text format and `GET /export/positions.csv`
+/// (optionally `?chain_id=N`); every other path is a 404.
+pub fn handle(request: HttpRequest) -> HttpResponse {
+    let (path, query) = request.url.split_once('?').unwrap_or((request.url.as_str(), ""));
+    match (request.method.as_str(), path) {
+        ("GET", "/metrics") => HttpResponse {
+            status_code: 200,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/plain; version=0.0.4".to_string(),
+            )],
+            body: ByteBuf::from(metrics::prometheus_text().into_bytes()),
+        },
+        ("GET", "/export/positions.csv") => {
+            let chain_filter = match query_param(query, "chain_id").map(str::parse::<u64>) {
+                None => None,
+                Some(Ok(chain_id)) => Some(chain_id),
+                Some(Err(_)) => {
+                    return HttpResponse {
+                        status_code: 400,
+                        headers: Vec::new(),
+                        body: ByteBuf::from(b"Invalid chain_id".to_vec()),
+                    };
+                }
+            };
+            HttpResponse {
+                status_code: 200,
+                headers: vec![("Content-Type".to_string(), "text/csv".to_string())],
+                body: ByteBuf::from(export::positions_csv(chain_filter).into_bytes()),
+            }
+        }
+        _ => HttpResponse {
+            status_code: 404,
+            headers: Vec::new(),
+            body: ByteBuf::from(b"Not found".to_vec()),
+        },
+    }
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
@@ -1,163 +1,627 @@
-use crate::state::{mutate_state, LogSource, UserPosition};
+use crate::chain_fusion_manager::topic_address;
+use crate::contracts::IPToken;
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::enhanced_api::canonical_address;
+use crate::health_alerts;
+use crate::liquidation_events;
+use crate::logger;
+use crate::math;
+use crate::metrics;
+use crate::price_oracle::{self, PriceBook};
+use crate::state::{market_key, mutate_state, read_state, LogSource, MarketState, State, UserPosition};
+use crate::token_metadata;
+use crate::unrecognized_events;
 use crate::PeridotEvents;
+use alloy::primitives::{Address, U256};
+use alloy::providers::ProviderBuilder;
 use alloy::rpc::types::Log;
 use alloy::sol_types::SolEvent;
+use alloy::transports::icp::IcpConfig;
+use std::collections::BTreeMap;
 
 pub async fn job(log_source: LogSource, log: Log) {
-    mutate_state(|s| s.record_processed_log(log_source.clone()));
-    
+    // The scraper reads the canister's own chain
+    let chain_id = mutate_state(|s| {
+        s.record_processed_log(log_source.clone());
+        s.chain_id
+    });
+    if !from_monitored_contract(chain_id, &log) {
+        return;
+    }
+    if apply_event(chain_id, &log).await {
+        mutate_state(|s| s.record_applied_log(chain_id, &log));
+    } else {
+        unrecognized_events::handle(chain_id, &log);
+    }
+    if let Some(block) = log.block_number {
+        mutate_state(|s| s.record_synced_block(chain_id, block));
+    }
+}
+
+/// Whether `log` was emitted by a contract monitored on `chain_id`. Same-signature
+/// events from unrelated contracts, and logs on a chain whose contract set cannot
+/// be resolved, are skipped instead of touching positions.
+pub fn from_monitored_contract(chain_id: u64, log: &Log) -> bool {
+    match read_state(|s| s.monitored_contracts(chain_id)) {
+        Ok(contracts) if contracts.contains(&log.address()) => true,
+        Ok(_) => {
+            logger::log(format!("Skipping event from unmonitored contract {} on chain {}", log.address(), chain_id));
+            false
+        }
+        Err(e) => {
+            logger::log(format!("Skipping event from {} on chain {}: {}", log.address(), chain_id, e));
+            false
+        }
+    }
+}
+
+/// Apply a single Peridot event emitted on `chain_id` to the tracked positions.
+/// Returns `false` when the log is not a recognised Peridot event.
+pub async fn apply_event(chain_id: u64, log: &Log) -> bool {
     // For now, let's process events based on topics (event signatures)
     // This is a simplified approach that doesn't rely on complex type conversions
     let topics = log.topics();
-    if !topics.is_empty() {
-        let event_signature = topics[0];
-        
-        // Check against known Peridot event signatures
-        if event_signature == PeridotEvents::Mint::SIGNATURE_HASH {
-            process_mint_event_simple(&log).await;
-        } else if event_signature == PeridotEvents::Redeem::SIGNATURE_HASH {
-            process_redeem_event_simple(&log).await;
-        } else if event_signature == PeridotEvents::Borrow::SIGNATURE_HASH {
-            process_borrow_event_simple(&log).await;
-        } else if event_signature == PeridotEvents::RepayBorrow::SIGNATURE_HASH {
-            process_repay_event_simple(&log).await;
-        } else if event_signature == PeridotEvents::LiquidateBorrow::SIGNATURE_HASH {
-            process_liquidation_event_simple(&log).await;
+    if topics.is_empty() {
+        return false;
+    }
+    let event_signature = topics[0];
+
+    // Check against known Peridot event signatures. Position events name the one
+    // account they touched; market-wide events re-evaluate alerts themselves.
+    let account = if event_signature == PeridotEvents::Mint::SIGNATURE_HASH {
+        process_mint_event_simple(chain_id, log).await
+    } else if event_signature == PeridotEvents::Redeem::SIGNATURE_HASH {
+        process_redeem_event_simple(chain_id, log).await
+    } else if event_signature == PeridotEvents::Borrow::SIGNATURE_HASH {
+        process_borrow_event_simple(chain_id, log).await
+    } else if event_signature == PeridotEvents::RepayBorrow::SIGNATURE_HASH {
+        process_repay_event_simple(chain_id, log).await
+    } else if event_signature == PeridotEvents::LiquidateBorrow::SIGNATURE_HASH {
+        process_liquidation_event_simple(chain_id, log).await
+    } else if event_signature == PeridotEvents::AccrueInterest::SIGNATURE_HASH {
+        process_accrue_interest_event(chain_id, log).await;
+        None
+    } else if event_signature == PeridotEvents::NewCollateralFactor::SIGNATURE_HASH {
+        process_collateral_factor_event(chain_id, log);
+        None
+    } else if event_signature == PeridotEvents::MarketEntered::SIGNATURE_HASH {
+        decode_event::<PeridotEvents::MarketEntered>(log).map(|event| {
+            update_collateral_markets(chain_id, event.account, event.pToken, true);
+            event.account
+        })
+    } else if event_signature == PeridotEvents::MarketExited::SIGNATURE_HASH {
+        decode_event::<PeridotEvents::MarketExited>(log).map(|event| {
+            update_collateral_markets(chain_id, event.account, event.pToken, false);
+            event.account
+        })
+    } else {
+        return false;
+    };
+    if let Some(account) = account {
+        mutate_state(|s| health_alerts::evaluate_user(s, &position_key(account), current_time() / 1_000_000_000));
+    }
+    metrics::record_event_processed(chain_id);
+    true
+}
+
+/// Decode a log's topics and ABI data, including dynamic (offset/length encoded)
+/// fields, as event `E`. Logs that do not decode are logged and skipped.
+fn decode_event<E: SolEvent>(log: &Log) -> Option<E> {
+    match E::decode_log(&log.inner, true) {
+        Ok(decoded) => Some(decoded.data),
+        Err(e) => {
+            logger::log(format!("Failed to decode {} log from {}: {}", E::SIGNATURE, log.address(), e));
+            None
         }
     }
 }
 
-async fn process_mint_event_simple(log: &Log) {
-    let topics = log.topics();
-    if topics.len() >= 2 {
-        let user_address = format!("{:?}", topics[1]); // minter address from indexed parameter
-        let chain_id = get_chain_id_from_log(log);
-        
-        ic_cdk::println!("Processing Mint event for user: {}", user_address);
-        
-        mutate_state(|s| {
-            let position = s.user_positions.entry((user_address.clone(), chain_id))
-                .or_insert_with(|| UserPosition {
-                    user_address: user_address.clone(),
-                    chain_id,
-                    p_token_balances: Vec::new(),
-                    borrow_balances: Vec::new(),
-                    collateral_enabled: Vec::new(),
-                    health_factor: 1.0,
-                    total_collateral_value_usd: 0.0,
-                    total_borrow_value_usd: 0.0,
-                    account_liquidity: 0.0,
-                    updated_at: ic_cdk::api::time(),
-                });
-            
-            // Update position with mint data
-            position.updated_at = ic_cdk::api::time();
-            // Add logic to update p_token_balances based on mint amount
-        });
+/// Logs without the expected indexed topics are skipped rather than trapping the sync.
+fn indexed_address(log: &Log, index: usize, event: &str) -> Option<Address> {
+    let topic = log.topics().get(index).copied();
+    if topic.is_none() {
+        logger::log(format!(
+            "Skipping malformed {} log from {}: expected {} topics, got {}",
+            event, log.address(), index + 1, log.topics().len()
+        ));
     }
+    topic.map(topic_address)
 }
 
-async fn process_redeem_event_simple(log: &Log) {
-    let topics = log.topics();
-    if topics.len() >= 2 {
-        let user_address = format!("{:?}", topics[1]); // redeemer address from indexed parameter
-        let chain_id = get_chain_id_from_log(log);
+async fn process_mint_event_simple(chain_id: u64, log: &Log) -> Option<Address> {
+    let Some(minter) = indexed_address(log, 1, "Mint") else {
+        return None;
+    };
+    let user_address = position_key(minter);
+
+    logger::log(format!("Processing Mint event for user: {}", user_address));
+
+    mutate_state(|s| {
+        let position = s.user_positions.entry((user_address.clone(), chain_id))
+            .or_insert_with(|| new_position(&user_address, chain_id));
         
-        ic_cdk::println!("Processing Redeem event for user: {}", user_address);
+        // Update position with mint data
+        position.updated_at = current_time();
+        // Add logic to update p_token_balances based on mint amount
+    });
+    Some(minter)
+}
+
+async fn process_redeem_event_simple(chain_id: u64, log: &Log) -> Option<Address> {
+    let Some(redeemer) = indexed_address(log, 1, "Redeem") else {
+        return None;
+    };
+    let user_address = position_key(redeemer);
+
+    logger::log(format!("Processing Redeem event for redeemer: {}", user_address));
+    ensure_position(&user_address, redeemer, log.address(), chain_id).await;
+
+    mutate_state(|s| {
+        if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
+            position.updated_at = current_time();
+            // Add logic to update p_token_balances based on redeem amount
+        }
+    });
+    Some(redeemer)
+}
+
+async fn process_borrow_event_simple(chain_id: u64, log: &Log) -> Option<Address> {
+    let Some(borrower) = indexed_address(log, 1, "Borrow") else {
+        return None;
+    };
+    let user_address = position_key(borrower);
+
+    logger::log(format!("Processing Borrow event for user: {}", user_address));
+
+    mutate_state(|s| {
+        let factor = collateral_factor(s, chain_id);
+        let position = s.user_positions.entry((user_address.clone(), chain_id))
+            .or_insert_with(|| new_position(&user_address, chain_id));
         
-        mutate_state(|s| {
-            if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
-                position.updated_at = ic_cdk::api::time();
-                // Add logic to update p_token_balances based on redeem amount
-            }
-        });
+        position.updated_at = current_time();
+        // Add logic to update borrow_balances based on borrow amount
+        // Calculate new health factor
+        calculate_health_factor(position, &s.prices, &s.market_states, &factor);
+    });
+    Some(borrower)
+}
+
+async fn process_repay_event_simple(chain_id: u64, log: &Log) -> Option<Address> {
+    let Some(borrower) = indexed_address(log, 2, "RepayBorrow") else {
+        return None;
+    };
+    let user_address = position_key(borrower);
+
+    logger::log(format!("Processing RepayBorrow event for borrower: {}", user_address));
+    ensure_position(&user_address, borrower, log.address(), chain_id).await;
+
+    mutate_state(|s| {
+        let factor = collateral_factor(s, chain_id);
+        if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
+            position.updated_at = current_time();
+            // Add logic to update borrow_balances based on repay amount
+            calculate_health_factor(position, &s.prices, &s.market_states, &factor);
+        }
+    });
+    Some(borrower)
+}
+
+async fn process_liquidation_event_simple(chain_id: u64, log: &Log) -> Option<Address> {
+    let Some(borrower) = indexed_address(log, 2, "LiquidateBorrow") else {
+        return None;
+    };
+    let user_address = position_key(borrower);
+
+    logger::log(format!("Processing LiquidateBorrow event for borrower: {}", user_address));
+    match liquidation_events::decode_liquidate_borrow(log, chain_id, current_time() / 1_000_000_000) {
+        Ok(event) => liquidation_events::record(event),
+        Err(e) => logger::log(&e),
     }
+    ensure_position(&user_address, borrower, log.address(), chain_id).await;
+
+    mutate_state(|s| {
+        let factor = collateral_factor(s, chain_id);
+        if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
+            position.updated_at = current_time();
+            // Add logic to update balances based on liquidation
+            calculate_health_factor(position, &s.prices, &s.market_states, &factor);
+        }
+    });
+    Some(borrower)
 }
 
-async fn process_borrow_event_simple(log: &Log) {
-    let topics = log.topics();
-    if topics.len() >= 2 {
-        let user_address = format!("{:?}", topics[1]); // borrower address from indexed parameter
-        let chain_id = get_chain_id_from_log(log);
-        
-        ic_cdk::println!("Processing Borrow event for user: {}", user_address);
-        
-        mutate_state(|s| {
-            let position = s.user_positions.entry((user_address.clone(), chain_id))
-                .or_insert_with(|| UserPosition {
-                    user_address: user_address.clone(),
-                    chain_id,
-                    p_token_balances: Vec::new(),
-                    borrow_balances: Vec::new(),
-                    collateral_enabled: Vec::new(),
-                    health_factor: 1.0,
-                    total_collateral_value_usd: 0.0,
-                    total_borrow_value_usd: 0.0,
-                    account_liquidity: 0.0,
-                    updated_at: ic_cdk::api::time(),
-                });
-            
-            position.updated_at = ic_cdk::api::time();
-            // Add logic to update borrow_balances based on borrow amount
-            // Calculate new health factor
-            calculate_health_factor(position);
-        });
+/// Refresh the market's totals from the event and its per-block rates from the
+/// pToken, then re-evaluate positions on the chain.
+async fn process_accrue_interest_event(chain_id: u64, log: &Log) {
+    let market = log.address();
+    let Some(event) = decode_event::<PeridotEvents::AccrueInterest>(log) else {
+        return;
+    };
+
+    let rates = read_market_rates(chain_id, market).await;
+    if let Err(e) = &rates {
+        logger::log(format!("Keeping previous rates for {} on chain {}: {}", market, chain_id, e));
     }
+    let metadata = token_metadata::market_underlying_metadata(chain_id, market).await;
+
+    mutate_state(|s| {
+        let state = s.market_states.entry(market_key(chain_id, &market.to_string())).or_insert_with(|| new_market_state(chain_id, market));
+        token_metadata::apply_metadata(state, metadata);
+        apply_accrue_interest(state, &event, log.block_number);
+        if let Ok((supply_rate, borrow_rate)) = rates {
+            state.supply_rate = supply_rate;
+            state.borrow_rate = borrow_rate;
+        }
+        state.updated_at = current_time();
+    });
+
+    recompute_all_health_factors(chain_id);
 }
 
-async fn process_repay_event_simple(log: &Log) {
-    let topics = log.topics();
-    if topics.len() >= 3 {
-        let user_address = format!("{:?}", topics[2]); // borrower address from indexed parameter
-        let chain_id = get_chain_id_from_log(log);
+/// Copy an `AccrueInterest` event's cash, totals and index onto the market.
+fn apply_accrue_interest(state: &mut MarketState, event: &PeridotEvents::AccrueInterest, block: Option<u64>) {
+    state.cash = saturating_u128(event.cashPrior);
+    state.total_borrows = saturating_u128(event.totalBorrows);
+    state.borrow_index = saturating_u128(event.borrowIndex);
+    if let Some(block) = block {
+        state.last_accrual_block = block;
+    }
+}
+
+pub fn new_market_state(chain_id: u64, market: Address) -> MarketState {
+    MarketState {
+        market_address: market.to_string(),
+        chain_id,
+        underlying_symbol: String::new(),
+        underlying_decimals: token_metadata::DEFAULT_DECIMALS,
+        supply_rate: 0,
+        borrow_rate: 0,
+        total_supply: 0,
+        total_borrows: 0,
+        cash: 0,
+        reserves: 0,
+        collateral_factor: 0,
+        exchange_rate: 0,
+        borrow_index: 0,
+        last_accrual_block: 0,
+        updated_at: 0,
+    }
+}
+
+/// Read a market's current per-block supply and borrow rates.
+async fn read_market_rates(chain_id: u64, market: Address) -> Result<(u64, u64), String> {
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let p_token = IPToken::new(market, provider);
+
+    let supply_rate = p_token.supplyRatePerBlock().call().await
+        .map_err(|e| format!("supplyRatePerBlock failed: {}", e))?._0;
+    let borrow_rate = p_token.borrowRatePerBlock().call().await
+        .map_err(|e| format!("borrowRatePerBlock failed: {}", e))?._0;
+
+    Ok((saturating_u64(supply_rate), saturating_u64(borrow_rate)))
+}
+
+fn process_collateral_factor_event(chain_id: u64, log: &Log) {
+    let Some(event) = decode_event::<PeridotEvents::NewCollateralFactor>(log) else {
+        return;
+    };
+
+    logger::log(format!("Collateral factor for {} on chain {} changed to {}", event.pToken, chain_id, event.newCollateralFactorMantissa));
+    mutate_state(|s| {
+        if let Some(market) = s.market_states.get_mut(&market_key(chain_id, &event.pToken.to_string())) {
+            market.collateral_factor = saturating_u64(event.newCollateralFactorMantissa);
+        }
+    });
+    recompute_all_health_factors(chain_id);
+}
+
+/// Add or remove a market from the account's collateral set and re-evaluate its health.
+fn update_collateral_markets(chain_id: u64, account: Address, market: Address, entered: bool) {
+    let user_address = position_key(account);
+    let market = market.to_string();
+
+    logger::log(format!("{} market {} for {}", if entered { "Entered" } else { "Exited" }, market, user_address));
+    mutate_state(|s| {
+        let factor = collateral_factor(s, chain_id);
+        let position = s.user_positions.entry((user_address.clone(), chain_id))
+            .or_insert_with(|| new_position(&user_address, chain_id));
         
-        ic_cdk::println!("Processing RepayBorrow event for borrower: {}", user_address);
+        let enabled = position.collateral_enabled.iter().any(|m| m.eq_ignore_ascii_case(&market));
+        if entered && !enabled {
+            position.collateral_enabled.push(market);
+        } else if !entered {
+            position.collateral_enabled.retain(|m| !m.eq_ignore_ascii_case(&market));
+        }
         
-        mutate_state(|s| {
-            if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
-                position.updated_at = ic_cdk::api::time();
-                // Add logic to update borrow_balances based on repay amount
-                calculate_health_factor(position);
+        position.updated_at = current_time();
+        calculate_health_factor(position, &s.prices, &s.market_states, &factor);
+    });
+}
+
+/// Refresh every tracked position on a chain against current prices and collateral
+/// factors, e.g. after a market parameter change. Returns the number of positions updated.
+pub fn recompute_all_health_factors(chain_id: u64) -> usize {
+    mutate_state(|s| {
+        let factor = collateral_factor(s, chain_id);
+        let mut updated = 0;
+        for ((_, position_chain_id), position) in s.user_positions.iter_mut() {
+            if *position_chain_id == chain_id {
+                calculate_health_factor(position, &s.prices, &s.market_states, &factor);
+                updated += 1;
             }
-        });
+        }
+        health_alerts::evaluate(s, current_time() / 1_000_000_000);
+        updated
+    })
+}
+
+pub fn new_position(user_address: &str, chain_id: u64) -> UserPosition {
+    UserPosition {
+        user_address: user_address.to_string(),
+        chain_id,
+        p_token_balances: Vec::new(),
+        borrow_balances: Vec::new(),
+        collateral_enabled: Vec::new(),
+        health_factor: 1.0,
+        total_collateral_value_usd: 0.0,
+        total_borrow_value_usd: 0.0,
+        account_liquidity: 0.0,
+        price_stale: false,
+        stale_since_blocks: None,
+        borrow_indexes: Vec::new(),
+        collateral_factor_capped: false,
+        updated_at: current_time(),
     }
 }
 
-async fn process_liquidation_event_simple(log: &Log) {
-    let topics = log.topics();
-    if topics.len() >= 3 {
-        let user_address = format!("{:?}", topics[2]); // borrower address from indexed parameter
-        let chain_id = get_chain_id_from_log(log);
-        
-        ic_cdk::println!("Processing LiquidateBorrow event for borrower: {}", user_address);
-        
-        mutate_state(|s| {
-            if let Some(position) = s.user_positions.get_mut(&(user_address, chain_id)) {
-                position.updated_at = ic_cdk::api::time();
-                // Add logic to update balances based on liquidation
-                calculate_health_factor(position);
+/// Key under which a user's positions are stored: the lowercase 0x-prefixed address,
+/// whichever event the user was seen in.
+pub fn position_key(user: Address) -> String {
+    canonical_address(&user.to_string())
+}
+
+/// Create the position if the canister never saw the user's earlier events (e.g. a
+/// RepayBorrow after syncing started mid-history), seeding its balances for the
+/// emitting market from the chain instead of dropping the event.
+async fn ensure_position(user_key: &str, user: Address, market: Address, chain_id: u64) {
+    if read_state(|s| s.user_positions.contains_key(&(user_key.to_string(), chain_id))) {
+        return;
+    }
+
+    logger::log(format!("No position for {} on chain {}, seeding from market {}", user_key, chain_id, market));
+    let balances = read_market_balances(chain_id, market, user).await;
+
+    mutate_state(|s| {
+        let position = s.user_positions.entry((user_key.to_string(), chain_id))
+            .or_insert_with(|| new_position(user_key, chain_id));
+        match balances {
+            Ok((p_token_balance, borrow_balance, borrow_index)) => {
+                position.p_token_balances.push((market.to_string(), p_token_balance));
+                position.borrow_balances.push((market.to_string(), borrow_balance));
+                position.borrow_indexes.push((market.to_string(), borrow_index));
             }
-        });
+            Err(e) => logger::log(format!("Failed to seed position for {}: {}", user_key, e)),
+        }
+    });
+}
+
+/// Read a user's pToken balance and stored borrow balance from a market, with the
+/// market's borrow index the stored balance is expressed at.
+async fn read_market_balances(chain_id: u64, market: Address, user: Address) -> Result<(u128, u128, u128), String> {
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)
+        .unwrap_or_else(|_| read_state(|s| s.rpc_service.clone()));
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let p_token = IPToken::new(market, provider);
+
+    let p_token_balance = p_token.balanceOf(user).call().await
+        .map_err(|e| format!("balanceOf failed: {}", e))?._0;
+    let borrow_balance = p_token.borrowBalanceStored(user).call().await
+        .map_err(|e| format!("borrowBalanceStored failed: {}", e))?._0;
+    let borrow_index = p_token.borrowIndex().call().await
+        .map_err(|e| format!("borrowIndex failed: {}", e))?._0;
+
+    Ok((saturating_u128(p_token_balance), saturating_u128(borrow_balance), saturating_u128(borrow_index)))
+}
+
+pub fn saturating_u64(value: U256) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+pub fn saturating_u128(value: U256) -> u128 {
+    u128::try_from(value).unwrap_or(u128::MAX)
+}
+
+#[derive(Debug, Clone)]
+pub struct CollateralFactor {
+    pub value: f64,
+    /// Markets (lowercase) whose on-chain factor was above its configured cap and clamped.
+    pub capped_markets: Vec<String>,
+}
+
+impl CollateralFactor {
+    /// Whether a cap clamped one of the markets the position uses as collateral.
+    pub fn caps(&self, position: &UserPosition) -> bool {
+        position.collateral_enabled.iter()
+            .any(|market| self.capped_markets.iter().any(|capped| capped.eq_ignore_ascii_case(market)))
     }
 }
 
-fn get_chain_id_from_log(log: &Log) -> u64 {
-    // This would be determined by the contract address or other log properties
-    // For now, we'll use a simple mapping based on contract addresses
-    let address = log.address();
-    match address.to_string().as_str() {
-        "0xe797a0001a3bc1b2760a24c3d7fdd172906bccd6" => 97,    // BNB testnet
-        "0xa41d586530bc7bc872095950ae03a780d5114445" => 10143, // Monad testnet
-        _ => 10143, // Default to Monad testnet
+/// Lowest collateral factor among the chain's loaded markets as a fraction, or
+/// 1.0 when none has been loaded yet. Positions are valued per chain, so the most
+/// conservative market factor applies. Each market's factor is clamped to its
+/// entry in `collateral_factor_caps` first.
+pub fn collateral_factor(state: &State, chain_id: u64) -> CollateralFactor {
+    let mut capped_markets = Vec::new();
+    let value = state.chain_markets(chain_id)
+        .filter(|market| market.collateral_factor > 0)
+        .map(|market| {
+            let factor = math::wei_to_human(market.collateral_factor as u128, 18);
+            let key = market.market_address.to_lowercase();
+            match state.collateral_factor_caps.get(&key) {
+                Some(cap) if factor > *cap => {
+                    capped_markets.push(key);
+                    *cap
+                }
+                _ => factor,
+            }
+        })
+        .reduce(f64::min)
+        .unwrap_or(1.0);
+    CollateralFactor { value, capped_markets }
+}
+
+pub fn calculate_health_factor(
+    position: &mut UserPosition,
+    prices: &PriceBook,
+    markets: &BTreeMap<(u64, String), MarketState>,
+    collateral_factor: &CollateralFactor,
+) {
+    calculate_health_factor_at(position, prices, markets, collateral_factor, current_time() / 1_000_000_000);
+}
+
+pub fn calculate_health_factor_at(
+    position: &mut UserPosition,
+    prices: &PriceBook,
+    markets: &BTreeMap<(u64, String), MarketState>,
+    collateral_factor: &CollateralFactor,
+    now: u64,
+) {
+    // Value balances with the best available price (live, cached, or fallback)
+    if !position.p_token_balances.is_empty() || !position.borrow_balances.is_empty() {
+        price_oracle::revalue_position(position, prices, markets, now);
     }
+
+    position.health_factor = math::health_factor(
+        position.total_collateral_value_usd * collateral_factor.value,
+        position.total_borrow_value_usd,
+    );
+    position.collateral_factor_capped = collateral_factor.caps(position);
 }
 
-fn calculate_health_factor(position: &mut UserPosition) {
-    // Simplified health factor calculation
-    // In production, this would involve complex calculations with oracle prices
-    if position.total_borrow_value_usd > 0.0 {
-        position.health_factor = position.total_collateral_value_usd / position.total_borrow_value_usd;
-    } else {
-        position.health_factor = f64::INFINITY;
+/// Canister time in nanoseconds.
+#[cfg(not(test))]
+fn current_time() -> u64 {
+    ic_cdk::api::time()
+}
+
+// Unit tests run outside a canister, where the time system call is unavailable
+#[cfg(test)]
+fn current_time() -> u64 {
+    crate::test_support::now() * 1_000_000_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use crate::test_support;
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    fn log_from(address: Address) -> Log {
+        let mut log = Log::default();
+        log.inner.address = address;
+        log
+    }
+
+    #[test]
+    fn only_monitored_contracts_are_applied() {
+        init_test_state();
+        let controller = read_state(|s| s.chain_configs[&10143].controller_address().unwrap());
+
+        assert!(from_monitored_contract(10143, &log_from(controller)));
+        assert!(!from_monitored_contract(10143, &log_from(Address::repeat_byte(0x99))));
+        // An unconfigured chain skips the log instead of failing the batch
+        assert!(!from_monitored_contract(1, &log_from(controller)));
+    }
+
+    #[test]
+    fn accrue_interest_keeps_totals_beyond_u64() {
+        let mut market = new_market_state(10143, Address::repeat_byte(0x01));
+        let total_borrows = u64::MAX as u128 * 1_000;
+        let event = PeridotEvents::AccrueInterest {
+            cashPrior: U256::from(5u64),
+            interestAccumulated: U256::from(1u64),
+            borrowIndex: U256::from(1_100_000_000_000_000_000u128),
+            totalBorrows: U256::from(total_borrows),
+        };
+
+        apply_accrue_interest(&mut market, &event, Some(42));
+        assert_eq!(market.total_borrows, total_borrows);
+        assert_eq!(market.cash, 5);
+        assert_eq!(market.borrow_index, 1_100_000_000_000_000_000);
+        assert_eq!(market.last_accrual_block, 42);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn logs_missing_indexed_topics_are_skipped() {
+        init_test_state();
+        let mut log = log_from(Address::repeat_byte(0x01));
+        log.inner.data = alloy::primitives::LogData::new_unchecked(vec![PeridotEvents::Mint::SIGNATURE_HASH], Default::default());
+
+        assert_eq!(indexed_address(&log, 1, "Mint"), None);
+        test_support::block_on(process_mint_event_simple(10143, &log));
+        assert!(read_state(|s| s.user_positions.is_empty()));
+
+        let minter = Address::repeat_byte(0x22);
+        log.inner.data = alloy::primitives::LogData::new_unchecked(
+            vec![PeridotEvents::Mint::SIGNATURE_HASH, minter.into_word()],
+            Default::default(),
+        );
+        assert_eq!(indexed_address(&log, 1, "Mint"), Some(minter));
+        assert_eq!(indexed_address(&log, 2, "RepayBorrow"), None);
+    }
+    alloy::sol! {
+        event NewMarket(address indexed market, uint256 collateralFactor, string name);
+    }
+
+    #[test]
+    fn events_with_dynamic_data_decode_both_parts() {
+        let market = Address::repeat_byte(0x01);
+        let event = NewMarket {
+            market,
+            collateralFactor: U256::from(750_000_000_000_000_000u128),
+            name: "Peridot Wrapped Monad".to_string(),
+        };
+        let mut log = log_from(market);
+        log.inner.data = event.encode_log_data();
+
+        let decoded = decode_event::<NewMarket>(&log).unwrap();
+        assert_eq!(decoded.market, market);
+        assert_eq!(decoded.collateralFactor, U256::from(750_000_000_000_000_000u128));
+        assert_eq!(decoded.name, "Peridot Wrapped Monad");
+        // A log of another event is skipped, not mis-parsed
+        assert!(decode_event::<PeridotEvents::AccrueInterest>(&log).is_none());
+    }
+
+    #[test]
+    fn caps_clamp_the_factor_and_flag_only_positions_using_the_market() {
+        init_test_state();
+        let capped_market = Address::repeat_byte(0x01);
+        let other_market = Address::repeat_byte(0x02);
+        mutate_state(|s| {
+            for (market, factor) in [(capped_market, 900_000_000_000_000_000u64), (other_market, 800_000_000_000_000_000)] {
+                let mut state = new_market_state(10143, market);
+                state.collateral_factor = factor;
+                s.market_states.insert(market_key(10143, &market.to_string()), state);
+            }
+            s.collateral_factor_caps.insert(capped_market.to_string().to_lowercase(), 0.5);
+        });
+
+        let factor = read_state(|s| collateral_factor(s, 10143));
+        assert_eq!(factor.value, 0.5);
+
+        let mut uses_capped = user_position(10143);
+        uses_capped.collateral_enabled = vec![capped_market.to_string()];
+        let mut uses_other = user_position(10143);
+        uses_other.collateral_enabled = vec![other_market.to_string()];
+        for position in [&mut uses_capped, &mut uses_other] {
+            read_state(|s| calculate_health_factor_at(position, &s.prices, &s.market_states, &factor, 1_000));
+        }
+        assert!(uses_capped.collateral_factor_capped);
+        assert!(!uses_other.collateral_factor_capped);
+    }
+}
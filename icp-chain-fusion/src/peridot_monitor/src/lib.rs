@@ -1,4 +1,4 @@
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use ic_cdk;
 
 mod guard;
@@ -12,20 +12,57 @@ mod rpc_manager;
 mod chain_fusion_manager;
 mod enhanced_api;
 mod cross_chain_transactions;
+mod access_control;
+mod bridge;
+mod logger;
+mod config;
+mod contracts;
+mod response_guard;
+mod notifications;
+mod watchdog;
+mod token_metadata;
+mod export;
+mod market_refresh;
+mod liquidation_cooldown;
+mod overview;
+mod gas_price;
+mod position_limits;
+mod emergency_withdraw;
+mod health_alerts;
+mod cycles;
+mod source_proof;
+mod unrecognized_events;
+mod http;
+mod accrual;
+mod retry_queue;
+mod simulation;
+mod nonce_manager;
+mod liquidation_approvals;
+mod liquidation_events;
+mod price_oracle;
+mod metrics;
+mod risk_parameters;
+mod canister_wallet;
+mod health;
+mod request_store;
+mod rates;
+mod math;
+#[cfg(test)]
+mod test_support;
 
 use std::time::Duration;
 
 use alloy::{network::TxSigner, signers::icp::IcpSigner, sol};
 
-use lifecycle::InitArg;
+use lifecycle::{InitArg, PersistedState};
 use state::{read_state, State};
 
 use crate::state::{initialize_state, mutate_state};
 
 // Import new cross-chain functionality
 use cross_chain_transactions::{
-    CrossChainRequest, CrossChainTransactionHandler, 
-    PeridotAction
+    BatchResponse, CrossChainConfig, CrossChainRequest, CrossChainResponse, CrossChainTransactionHandler,
+    PeridotAction, PeridotError
 };
 use chain_fusion_manager::ChainFusionManager;
 
@@ -48,6 +85,7 @@ impl From<Result<String, String>> for ApiResult {
 }
 
 pub const SCRAPING_LOGS_INTERVAL: Duration = Duration::from_secs(60);
+pub const SIGNER_RETRY_INTERVAL: Duration = Duration::from_secs(10);
 
 // Peridot Protocol event signatures
 sol!(
@@ -58,29 +96,66 @@ sol!(
         event Borrow(address indexed borrower, uint256 borrowAmount, uint256 accountBorrows, uint256 totalBorrows);
         event RepayBorrow(address indexed payer, address indexed borrower, uint256 repayAmount, uint256 accountBorrows, uint256 totalBorrows);
         event LiquidateBorrow(address indexed liquidator, address indexed borrower, uint256 repayAmount, address indexed pTokenCollateral, uint256 seizeTokens);
+        event AccrueInterest(uint256 cashPrior, uint256 interestAccumulated, uint256 borrowIndex, uint256 totalBorrows);
+        event NewCollateralFactor(address pToken, uint256 oldCollateralFactorMantissa, uint256 newCollateralFactorMantissa);
+        event MarketEntered(address pToken, address account);
+        event MarketExited(address pToken, address account);
     }
 );
 
 fn setup_timers() {
+    ic_cdk_timers::set_timer(Duration::ZERO, || ic_cdk::spawn(initialize_signer()));
+    ic_cdk_timers::set_timer_interval(watchdog::WATCHDOG_INTERVAL, || ic_cdk::spawn(watchdog::check_sync_health()));
+    ic_cdk_timers::set_timer_interval(retry_queue::RETRY_INTERVAL, || ic_cdk::spawn(retry_queue::process_due()));
+    ic_cdk_timers::set_timer_interval(nonce_manager::NONCE_RECONCILE_INTERVAL, || ic_cdk::spawn(nonce_manager::reconcile_all()));
+    // Each chain's successful sync is what keeps it out of the watchdog's degraded set
+    ic_cdk_timers::set_timer_interval(chain_fusion_manager::CHAIN_SYNC_INTERVAL, || ic_cdk::spawn(ChainFusionManager::sync_scheduled()));
+
+    // Start scraping logs after initialization (disabled for testing)
+    // ic_cdk_timers::set_timer(Duration::from_secs(10), || ic_cdk::spawn(scrape_eth_logs()));
+}
+
+async fn initialize_signer() {
     let ecdsa_key_name = read_state(State::key_id).name.clone();
-    ic_cdk_timers::set_timer(Duration::ZERO, || {
-        ic_cdk::spawn(async move {
-            let signer = IcpSigner::new(vec![], &ecdsa_key_name, None).await.unwrap();
+    match IcpSigner::new(vec![], &ecdsa_key_name, None).await {
+        Ok(signer) => {
             let address = signer.address();
             mutate_state(|s| {
                 s.signer = Some(signer);
                 s.canister_evm_address = Some(address);
             });
-        })
-    });
-    
-    // Start scraping logs after initialization (disabled for testing)
-    // ic_cdk_timers::set_timer(Duration::from_secs(10), || ic_cdk::spawn(scrape_eth_logs()));
+            // Requests interrupted by an upgrade need the signer to continue
+            ic_cdk::spawn(CrossChainTransactionHandler::resume_in_flight());
+        }
+        Err(e) => {
+            // Transaction endpoints report "signer not ready" until this succeeds
+            ic_cdk::println!("Failed to initialize signer: {}, retrying in {:?}", e, SIGNER_RETRY_INTERVAL);
+            ic_cdk_timers::set_timer(SIGNER_RETRY_INTERVAL, || ic_cdk::spawn(initialize_signer()));
+        }
+    }
 }
 
 #[ic_cdk::init]
 fn init(arg: InitArg) {
     initialize_state(state::State::try_from(arg).expect("BUG: failed to initialize canister"));
+    mutate_state(|s| s.started_at = ic_cdk::api::time() / 1_000_000_000);
+    setup_timers();
+}
+
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {
+    let snapshot = read_state(PersistedState::capture);
+    ic_cdk::storage::stable_save((snapshot,)).expect("BUG: failed to save state to stable memory");
+}
+
+#[ic_cdk::post_upgrade]
+fn post_upgrade(arg: InitArg) {
+    initialize_state(state::State::try_from(arg).expect("BUG: failed to initialize canister"));
+    mutate_state(|s| s.started_at = ic_cdk::api::time() / 1_000_000_000);
+    match ic_cdk::storage::stable_restore::<(PersistedState,)>() {
+        Ok((snapshot,)) => mutate_state(|s| snapshot.restore(s)),
+        Err(e) => ic_cdk::println!("No persisted state restored: {}", e),
+    }
     setup_timers();
 }
 
@@ -91,71 +166,316 @@ fn get_evm_address() -> Option<String> {
     read_state(|s| s.canister_evm_address.map(|x| x.to_string()))
 }
 
+/// EVM address derived from the canister's key under `derivation_path`.
+/// Update call: deriving the key requires an ECDSA public-key request.
+#[ic_cdk::update]
+async fn get_derived_address(derivation_path: Vec<Vec<u8>>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    canister_wallet::derived_address(derivation_path).await.into()
+}
+
+/// Compare the tracked nonce with the chain's pending count and resync on divergence.
+#[ic_cdk::update]
+async fn resync_nonce(chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match nonce_manager::reconcile(chain_id).await {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::update]
+async fn get_evm_balance(chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match canister_wallet::fetch_native_balance(chain_id).await {
+        Ok(balance) => match serde_json::to_string(&balance) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::query]
+fn get_health() -> ApiResult {
+    match serde_json::to_string(&health::health_report()) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+#[ic_cdk::query]
+fn get_protocol_overview() -> ApiResult {
+    match serde_json::to_string(&overview::protocol_overview()) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
 #[ic_cdk::query]
 fn get_user_position(user: String, chain_id: u64) -> Option<String> {
     read_state(|s| {
-        s.user_positions.get(&(user, chain_id)).map(|pos| {
-            serde_json::to_string(pos).unwrap_or_default()
+        s.user_positions.get(&(enhanced_api::canonical_address(&user), chain_id)).map(|pos| {
+            let mut pos = pos.clone();
+            pos.stale_since_blocks = accrual::position_stale_since_blocks(s, &pos);
+            serde_json::to_string(&pos).unwrap_or_default()
         })
     })
 }
 
+/// Stored vs. interest-accrued borrow balance, read live via `borrowBalanceCurrent`.
+#[ic_cdk::update]
+async fn get_current_borrow_balance(user: String, market: String, chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match accrual::current_borrow_balance(&user, &market, chain_id).await {
+        Ok(comparison) => match serde_json::to_string(&comparison) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+/// Project the user's health factor after a hypothetical action, without changing state.
+#[ic_cdk::query]
+fn simulate_action_health(user: String, chain_id: u64, action: PeridotAction, amount: String) -> ApiResult {
+    match simulation::simulate_action_health(&user, chain_id, &action, &amount) {
+        Ok(simulation) => match serde_json::to_string(&simulation) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+/// Markets the user has entered as collateral on a chain.
+#[ic_cdk::query]
+fn get_enabled_collateral(user: String, chain_id: u64) -> ApiResult {
+    read_state(|s| match s.user_positions.get(&(enhanced_api::canonical_address(&user), chain_id)) {
+        Some(position) => match serde_json::to_string(&position.collateral_enabled) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        None => ApiResult::Err(format!("No position for {} on chain {}", user, chain_id)),
+    })
+}
+
 #[ic_cdk::query]
 fn get_market_state(chain_id: u64) -> Option<String> {
     read_state(|s| {
-        s.market_states.get(&chain_id).map(|state| {
-            serde_json::to_string(state).unwrap_or_default()
+        market_refresh::chain_market_state(s, chain_id).map(|state| {
+            serde_json::to_string(&state).unwrap_or_default()
         })
     })
 }
 
 #[ic_cdk::query]
 fn get_liquidation_opportunities(chain_id: u64) -> Vec<String> {
+    let now = ic_cdk::api::time() / 1_000_000_000;
     read_state(|s| {
+        let params = s.risk_parameters.get(&chain_id);
         s.user_positions.iter()
             .filter(|((_, cid), pos)| *cid == chain_id && pos.health_factor < 1.0)
-            .map(|((user, _), pos)| {
-                format!("User: {}, Health Factor: {:.4}", user, pos.health_factor)
+            .filter(|((user, _), _)| liquidation_cooldown::remaining(s, user, now).is_none())
+            .map(|((user, _), pos)| match params {
+                Some(params) => {
+                    let max_repay_usd = pos.total_borrow_value_usd * params.close_factor();
+                    format!(
+                        "User: {}, Health Factor: {:.4}, Max Repay USD: {:.2}, Seize Value USD: {:.2}",
+                        user, pos.health_factor, max_repay_usd, params.estimate_seize_value_usd(max_repay_usd)
+                    )
+                }
+                None => format!("User: {}, Health Factor: {:.4}", user, pos.health_factor),
             })
             .collect()
     })
 }
 
+#[ic_cdk::update]
+fn set_risk_thresholds(critical: f64, high: f64, medium: f64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let thresholds = enhanced_api::RiskThresholds { critical, high, medium };
+    if let Err(e) = thresholds.validate() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.risk_thresholds = thresholds);
+    ApiResult::Ok(format!("Risk thresholds set to critical < {}, high < {}, medium < {}", critical, high, medium))
+}
+
+/// Kill switch for one `PeridotAction` variant, e.g. `set_action_enabled("LiquidateBorrow", false)`.
+#[ic_cdk::update]
+fn set_action_enabled(action: String, enabled: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let Some(kind) = PeridotAction::KINDS.iter().find(|kind| kind.eq_ignore_ascii_case(&action)) else {
+        return ApiResult::Err(format!(
+            "Unknown action '{}'; expected one of {}",
+            action,
+            PeridotAction::KINDS.join(", ")
+        ));
+    };
+    mutate_state(|s| {
+        if enabled {
+            s.disabled_actions.remove(*kind);
+        } else {
+            s.disabled_actions.insert(kind.to_string());
+        }
+    });
+    ApiResult::Ok(format!("{} actions {}", kind, if enabled { "enabled" } else { "disabled" }))
+}
+
+#[ic_cdk::update]
+fn set_liquidation_cooldown(cooldown_secs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.liquidation_cooldown_secs = cooldown_secs);
+    ApiResult::Ok(format!("Liquidation cooldown set to {}s", cooldown_secs))
+}
+
+#[ic_cdk::update]
+async fn get_liquidation_parameters(chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match risk_parameters::get_or_fetch(chain_id).await {
+        Ok(params) => match serde_json::to_string(&params) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+/// Exact collateral pTokens the controller would award for a liquidation.
+/// `underlying` is the borrowed asset, resolved to its pToken market;
+/// `collateral` is the collateral pToken market.
+#[ic_cdk::update]
+async fn compute_seize_amount(
+    borrower: String,
+    repay_amount: String,
+    underlying: String,
+    collateral: String,
+    chain_id: u64,
+) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let parse = |label: &str, value: &str| {
+        value.parse::<alloy::primitives::Address>()
+            .map_err(|e| format!("Invalid {} address '{}': {}", label, value, e))
+    };
+    let (borrower, underlying, collateral) = match (
+        parse("borrower", &borrower),
+        parse("underlying", &underlying),
+        parse("collateral", &collateral),
+    ) {
+        (Ok(b), Ok(u), Ok(c)) => (b, u, c),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return ApiResult::Err(e),
+    };
+    let borrowed = match token_metadata::market_for_underlying(chain_id, underlying).await {
+        Ok(market) => market,
+        Err(e) => return ApiResult::Err(e),
+    };
+    let repay_amount = match CrossChainTransactionHandler::parse_amount(&repay_amount) {
+        Ok(amount) => amount,
+        Err(e) => return ApiResult::Err(e),
+    };
+
+    match risk_parameters::simulate_seize(chain_id, borrower, borrowed, collateral, repay_amount).await {
+        Ok(simulation) => match serde_json::to_string(&simulation) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::update]
+async fn refresh_all_markets(chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match market_refresh::refresh_all_markets(chain_id).await {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
 #[ic_cdk::query]
 fn get_cross_chain_rates() -> String {
-    read_state(|s| {
-        let mut rates = std::collections::HashMap::new();
-        for (chain_id, market) in &s.market_states {
-            rates.insert(*chain_id, &market.supply_rate);
-        }
-        serde_json::to_string(&rates).unwrap_or_default()
-    })
+    read_state(|s| serde_json::to_string(&rates::cross_chain_rates(s)).unwrap_or_default())
+}
+
+#[ic_cdk::query]
+fn compare_asset_rates(symbol: String) -> ApiResult {
+    let comparison = rates::compare_asset_rates(&symbol);
+    if comparison.chains.is_empty() {
+        return ApiResult::Err(format!("No markets found for {}", symbol));
+    }
+    match serde_json::to_string(&comparison) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
 }
 
 // ===== NEW ENHANCED API FUNCTIONS =====
 
+/// `{"status":"Unknown"}` for an address never observed, `{"status":"Empty",...}`
+/// when its positions hold no balances, else `{"status":"Found",...}` with the position.
 #[ic_cdk::query]
 fn get_enhanced_user_position(user_address: String) -> ApiResult {
     let manager = ChainFusionManager::new();
-    match manager.get_enhanced_user_position(&user_address) {
-        Some(position) => match serde_json::to_string(&position) {
-            Ok(json) => ApiResult::Ok(json),
-            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
-        },
-        None => ApiResult::Ok("null".to_string()),
+    match serde_json::to_string(&manager.lookup_user_position(&user_address)) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
     }
 }
 
+/// Distinct users observed across all chains, by canonical address, in windows of at
+/// most `MAX_USERS_PAGE_SIZE`. `total` counts every user for paging to the end.
 #[ic_cdk::query]
-fn get_cross_chain_market_summary() -> ApiResult {
-    let manager = ChainFusionManager::new();
-    let summary = manager.get_cross_chain_market_summary();
-    match serde_json::to_string(&summary) {
+fn list_users(offset: u64, limit: u64) -> ApiResult {
+    let page = read_state(|s| enhanced_api::list_users(s, offset, limit));
+    match serde_json::to_string(&page) {
         Ok(json) => ApiResult::Ok(json),
         Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
     }
 }
 
+#[ic_cdk::query]
+fn get_cross_chain_market_summary(window_seconds: Option<u64>) -> ApiResult {
+    let manager = ChainFusionManager::new();
+    let summary = manager.get_cross_chain_market_summary(window_seconds);
+
+    // Liquidity flows grow with the number of markets; trim them if the summary is too large
+    let flows = summary.liquidity_flows.clone();
+    response_guard::fit_to_budget(&flows, |kept, truncated| {
+        let mut view = serde_json::to_value(&enhanced_api::CrossChainMarketSummary { liquidity_flows: kept.to_vec(), ..summary.clone() })
+            .map_err(|e| format!("Serialization error: {}", e))?;
+        view["truncated"] = truncated.into();
+        view["total_available"] = flows.len().into();
+        Ok(view.to_string())
+    }).into()
+}
+
 #[ic_cdk::query]
 fn get_chain_analytics(chain_id: u64) -> ApiResult {
     let manager = ChainFusionManager::new();
@@ -168,14 +488,44 @@ fn get_chain_analytics(chain_id: u64) -> ApiResult {
     }
 }
 
+#[ic_cdk::query]
+fn get_arbitrage_opportunities(min_profit_usd: f64, max_risk_score: f64) -> ApiResult {
+    let filter = enhanced_api::ArbitrageFilter { min_profit_usd, max_risk_score };
+    if let Err(e) = filter.validate() {
+        return ApiResult::Err(e);
+    }
+    let opportunities = ChainFusionManager::new().get_arbitrage_opportunities(&filter);
+    response_guard::list_json(&opportunities).into()
+}
+
+/// Default bar for arbitrage opportunities attached to `get_enhanced_user_position`.
+#[ic_cdk::update]
+fn set_arbitrage_filter(min_profit_usd: f64, max_risk_score: f64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let filter = enhanced_api::ArbitrageFilter { min_profit_usd, max_risk_score };
+    if let Err(e) = filter.validate() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.arbitrage_filter = filter);
+    ApiResult::Ok(format!("Arbitrage filter set to profit >= ${}, risk <= {}", min_profit_usd, max_risk_score))
+}
+
 #[ic_cdk::query]
 fn get_liquidation_opportunities_enhanced() -> ApiResult {
     let manager = ChainFusionManager::new();
     let opportunities = manager.get_liquidation_opportunities_enhanced();
-    match serde_json::to_string(&opportunities) {
-        Ok(json) => ApiResult::Ok(json),
-        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
-    }
+    response_guard::list_json(&opportunities).into()
+}
+
+/// `get_liquidation_opportunities_enhanced` as `{items, truncated, total_available}`,
+/// so clients can tell when the book was cut to fit the response budget.
+#[ic_cdk::query]
+fn get_liquidation_opportunities_page() -> ApiResult {
+    let manager = ChainFusionManager::new();
+    let opportunities = manager.get_liquidation_opportunities_enhanced();
+    response_guard::page_json(&opportunities).into()
 }
 
 // ===== CROSS-CHAIN TRANSACTION FUNCTIONS =====
@@ -183,13 +533,15 @@ fn get_liquidation_opportunities_enhanced() -> ApiResult {
 #[ic_cdk::update]
 async fn execute_cross_chain_supply(
     user_address: String,
-    source_chain_id: u64,
+    source_chain_id: Option<u64>,
     target_chain_id: u64,
     asset_address: String,
     amount: String,
     max_gas_price: u64,
     deadline: u64,
-) -> ApiResult {
+) -> Result<CrossChainResponse, PeridotError> {
+    let source_chain_id = CrossChainTransactionHandler::resolve_source_chain(source_chain_id)
+        .map_err(PeridotError::from)?;
     let request = CrossChainRequest {
         user_address,
         source_chain_id,
@@ -203,33 +555,29 @@ async fn execute_cross_chain_supply(
         deadline,
     };
     
-    match CrossChainTransactionHandler::execute_cross_chain_action(request).await {
-        Ok(response) => {
-            match serde_json::to_string(&response) {
-                Ok(json) => ApiResult::Ok(json),
-                Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
-            }
-        }
-        Err(e) => ApiResult::Err(e)
-    }
+    CrossChainTransactionHandler::execute_cross_chain_action(request).await.map_err(PeridotError::from)
 }
 
 #[ic_cdk::update]
 async fn execute_cross_chain_borrow(
     user_address: String,
-    source_chain_id: u64,
+    source_chain_id: Option<u64>,
     target_chain_id: u64,
     asset_address: String,
     amount: String,
     max_gas_price: u64,
     deadline: u64,
-) -> ApiResult {
+    recipient_address: Option<String>,
+) -> Result<CrossChainResponse, PeridotError> {
+    let source_chain_id = CrossChainTransactionHandler::resolve_source_chain(source_chain_id)
+        .map_err(PeridotError::from)?;
     let request = CrossChainRequest {
         user_address,
         source_chain_id,
         target_chain_id,
         action: PeridotAction::Borrow { 
-            underlying_asset: asset_address.clone() 
+            underlying_asset: asset_address.clone(),
+            recipient_address,
         },
         amount,
         asset_address,
@@ -237,21 +585,42 @@ async fn execute_cross_chain_borrow(
         deadline,
     };
     
-    match CrossChainTransactionHandler::execute_cross_chain_action(request).await {
-        Ok(response) => {
-            match serde_json::to_string(&response) {
-                Ok(json) => ApiResult::Ok(json),
-                Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
-            }
-        }
-        Err(e) => ApiResult::Err(e)
-    }
+    CrossChainTransactionHandler::execute_cross_chain_action(request).await.map_err(PeridotError::from)
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_redeem_underlying(
+    user_address: String,
+    source_chain_id: Option<u64>,
+    target_chain_id: u64,
+    asset_address: String,
+    underlying_amount: String,
+    max_gas_price: u64,
+    deadline: u64,
+) -> Result<CrossChainResponse, PeridotError> {
+    let source_chain_id = CrossChainTransactionHandler::resolve_source_chain(source_chain_id)
+        .map_err(PeridotError::from)?;
+    let request = CrossChainRequest {
+        user_address,
+        source_chain_id,
+        target_chain_id,
+        action: PeridotAction::RedeemUnderlying {
+            underlying_asset: asset_address.clone(),
+            underlying_amount: underlying_amount.clone(),
+        },
+        amount: underlying_amount,
+        asset_address,
+        max_gas_price,
+        deadline,
+    };
+
+    CrossChainTransactionHandler::execute_cross_chain_action(request).await.map_err(PeridotError::from)
 }
 
 #[ic_cdk::update]
 async fn execute_cross_chain_liquidation(
     liquidator_address: String,
-    source_chain_id: u64,
+    source_chain_id: Option<u64>,
     target_chain_id: u64,
     borrower: String,
     underlying_asset: String,
@@ -259,7 +628,10 @@ async fn execute_cross_chain_liquidation(
     repay_amount: String,
     max_gas_price: u64,
     deadline: u64,
-) -> ApiResult {
+    auto_claim: Option<bool>,
+) -> Result<CrossChainResponse, PeridotError> {
+    let source_chain_id = CrossChainTransactionHandler::resolve_source_chain(source_chain_id)
+        .map_err(PeridotError::from)?;
     let request = CrossChainRequest {
         user_address: liquidator_address,
         source_chain_id,
@@ -268,6 +640,7 @@ async fn execute_cross_chain_liquidation(
             borrower,
             underlying_asset: underlying_asset.clone(),
             collateral_asset,
+            auto_claim,
         },
         amount: repay_amount,
         asset_address: underlying_asset,
@@ -275,50 +648,525 @@ async fn execute_cross_chain_liquidation(
         deadline,
     };
     
-    match CrossChainTransactionHandler::execute_cross_chain_action(request).await {
-        Ok(response) => {
-            match serde_json::to_string(&response) {
-                Ok(json) => ApiResult::Ok(json),
-                Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
-            }
-        }
-        Err(e) => ApiResult::Err(e)
+    if liquidation_approvals::requires_approval(&request) {
+        return Err(PeridotError::ApprovalRequired(format!(
+            "Liquidation value ${:.2} requires multi-signature approval; submit it via propose_liquidation",
+            liquidation_approvals::estimate_liquidation_value_usd(&request)
+        )));
     }
+
+    CrossChainTransactionHandler::execute_cross_chain_action(request).await.map_err(PeridotError::from)
 }
 
+/// Requests that failed permanently or exhausted their retries.
 #[ic_cdk::query]
-async fn estimate_cross_chain_gas(
-    user_address: String,
-    source_chain_id: u64,
-    target_chain_id: u64,
-    action: String, // "supply", "borrow", "liquidate"
-    amount: String,
-) -> ApiResult {
-    let action_enum = match action.as_str() {
-        "supply" => PeridotAction::Supply { underlying_asset: "USDC".to_string() },
-        "borrow" => PeridotAction::Borrow { underlying_asset: "USDC".to_string() },
-        "liquidate" => PeridotAction::LiquidateBorrow {
-            borrower: "0x000".to_string(),
-            underlying_asset: "USDC".to_string(),
-            collateral_asset: "ETH".to_string(),
-        },
-        _ => return ApiResult::Err("Invalid action".to_string()),
-    };
-    
-    let request = CrossChainRequest {
-        user_address,
-        source_chain_id,
-        target_chain_id,
-        action: action_enum,
-        amount,
-        asset_address: "0x000".to_string(), // Mock
-        max_gas_price: 0,
-        deadline: ic_cdk::api::time() / 1_000_000_000 + 86400, // 24 hours from now
+fn get_failed_requests() -> ApiResult {
+    response_guard::list_json(&retry_queue::dead_letters()).into()
+}
+
+/// Attempts (including the first execution) before a failing request is dead-lettered.
+#[ic_cdk::update]
+fn set_max_retry_attempts(max_attempts: u32) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    if max_attempts == 0 {
+        return ApiResult::Err("At least one attempt is required".to_string());
+    }
+    mutate_state(|s| s.max_retry_attempts = max_attempts);
+    ApiResult::Ok(format!("Max retry attempts set to {}", max_attempts))
+}
+
+/// Cycle balance below which transaction sending and chain sync are declined.
+#[ic_cdk::update]
+fn set_cycle_floor(floor: u128) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.cycle_floor = floor);
+    ApiResult::Ok(format!("Cycle floor set to {}", floor))
+}
+
+/// Stop (or resume) executing cross-chain requests. Emergency withdrawals require a pause.
+#[ic_cdk::update]
+fn set_paused(paused: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.paused = paused);
+    ApiResult::Ok(format!("Canister {}", if paused { "paused" } else { "resumed" }))
+}
+
+/// Send the canister's native balance, or `token` when given, on `chain_id` to
+/// `to_address`. `amount` of `None` withdraws everything. Controller-only and
+/// only while paused.
+#[ic_cdk::update]
+async fn emergency_withdraw(chain_id: u64, to_address: String, token: Option<String>, amount: Option<String>) -> ApiResult {
+    let caller = match access_control::ensure_controller() {
+        Ok(caller) => caller,
+        Err(e) => return ApiResult::Err(e),
     };
-    
-    match CrossChainTransactionHandler::estimate_gas_costs(&request).await {
-        Ok(estimate) => {
-            match serde_json::to_string(&estimate) {
+    match emergency_withdraw::withdraw(chain_id, &to_address, token, amount, caller).await {
+        Ok(withdrawal) => match serde_json::to_string(&withdrawal) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::query]
+fn get_emergency_withdrawals() -> ApiResult {
+    response_guard::list_json(&read_state(|s| s.emergency_withdrawals.clone())).into()
+}
+
+/// Queue an alert for the caller whenever `user_address`'s lowest health factor
+/// drops below `threshold`. Re-subscribing replaces the threshold.
+#[ic_cdk::update]
+fn subscribe_health_alert(user_address: String, threshold: f64) -> ApiResult {
+    match health_alerts::subscribe(ic_cdk::caller(), &user_address, threshold) {
+        Ok(()) => ApiResult::Ok(format!("Subscribed to health alerts for {} below {}", user_address, threshold)),
+        Err(e) => ApiResult::Err(e),
+    }
+}
+
+#[ic_cdk::update]
+fn unsubscribe_health_alert(user_address: String) -> ApiResult {
+    if health_alerts::unsubscribe(ic_cdk::caller(), &user_address) {
+        ApiResult::Ok(format!("Unsubscribed from health alerts for {}", user_address))
+    } else {
+        ApiResult::Err(format!("No health alert subscription for {}", user_address))
+    }
+}
+
+/// Drain the caller's pending health alerts.
+#[ic_cdk::update]
+fn poll_alerts() -> ApiResult {
+    match serde_json::to_string(&health_alerts::poll(ic_cdk::caller())) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+/// Underlying token of a pToken market; update because a cache miss makes outcalls.
+#[ic_cdk::update]
+async fn get_ptoken_underlying(ptoken_address: String, chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let market = match ptoken_address.parse::<alloy::primitives::Address>() {
+        Ok(market) => market,
+        Err(e) => return ApiResult::Err(format!("Invalid pToken address '{}': {}", ptoken_address, e)),
+    };
+    match token_metadata::ptoken_underlying(chain_id, market).await {
+        Ok(underlying) => match serde_json::to_string(&underlying) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+/// Probe every configured RPC provider once and report reachability and latency.
+#[ic_cdk::update]
+async fn test_rpc_connectivity() -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = cycles::ensure_cycles("RPC connectivity test") {
+        return ApiResult::Err(e);
+    }
+    match serde_json::to_string(&rpc_manager::probe_providers().await) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+/// Re-check a request's stored source-chain deposit against the request and that
+/// chain. Authorized callers only, since every call makes outcalls.
+#[ic_cdk::update]
+async fn verify_source_proof(request_id: String) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    match source_proof::verify(&request_id).await {
+        Ok(verification) => match serde_json::to_string(&verification) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::query]
+fn get_cross_chain_request(request_id: String) -> ApiResult {
+    match request_store::get(&request_id) {
+        Some(record) => match serde_json::to_string(&record) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        None => ApiResult::Err(format!("Request {} not found", request_id)),
+    }
+}
+
+#[ic_cdk::query]
+fn get_active_requests() -> ApiResult {
+    response_guard::list_json(&request_store::active()).into()
+}
+
+/// Execute several actions sequentially. Not atomic on-chain; see `execute_batch`.
+#[ic_cdk::update]
+async fn execute_cross_chain_batch(actions: Vec<CrossChainRequest>) -> Result<BatchResponse, PeridotError> {
+    if let Some(index) = actions.iter().position(liquidation_approvals::requires_approval) {
+        return Err(PeridotError::ApprovalRequired(format!(
+            "Action {} is a high-value liquidation and must go through propose_liquidation",
+            index
+        )));
+    }
+
+    CrossChainTransactionHandler::execute_batch(actions).await.map_err(PeridotError::from)
+}
+
+// JSON-in-ApiResult variants of the execute endpoints, kept for existing clients.
+
+fn json_result<T: serde::Serialize>(result: Result<T, PeridotError>) -> ApiResult {
+    match result {
+        Ok(response) => match serde_json::to_string(&response) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e.to_string())
+    }
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_supply_json(
+    user_address: String,
+    source_chain_id: Option<u64>,
+    target_chain_id: u64,
+    asset_address: String,
+    amount: String,
+    max_gas_price: u64,
+    deadline: u64,
+) -> ApiResult {
+    json_result(execute_cross_chain_supply(
+        user_address, source_chain_id, target_chain_id, asset_address, amount, max_gas_price, deadline,
+    ).await)
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_borrow_json(
+    user_address: String,
+    source_chain_id: Option<u64>,
+    target_chain_id: u64,
+    asset_address: String,
+    amount: String,
+    max_gas_price: u64,
+    deadline: u64,
+    recipient_address: Option<String>,
+) -> ApiResult {
+    json_result(execute_cross_chain_borrow(
+        user_address, source_chain_id, target_chain_id, asset_address, amount, max_gas_price, deadline, recipient_address,
+    ).await)
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_redeem_underlying_json(
+    user_address: String,
+    source_chain_id: Option<u64>,
+    target_chain_id: u64,
+    asset_address: String,
+    underlying_amount: String,
+    max_gas_price: u64,
+    deadline: u64,
+) -> ApiResult {
+    json_result(execute_cross_chain_redeem_underlying(
+        user_address, source_chain_id, target_chain_id, asset_address, underlying_amount, max_gas_price, deadline,
+    ).await)
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_liquidation_json(
+    liquidator_address: String,
+    source_chain_id: Option<u64>,
+    target_chain_id: u64,
+    borrower: String,
+    underlying_asset: String,
+    collateral_asset: String,
+    repay_amount: String,
+    max_gas_price: u64,
+    deadline: u64,
+    auto_claim: Option<bool>,
+) -> ApiResult {
+    json_result(execute_cross_chain_liquidation(
+        liquidator_address, source_chain_id, target_chain_id, borrower, underlying_asset,
+        collateral_asset, repay_amount, max_gas_price, deadline, auto_claim,
+    ).await)
+}
+
+#[ic_cdk::update]
+async fn execute_cross_chain_batch_json(actions: Vec<CrossChainRequest>) -> ApiResult {
+    json_result(execute_cross_chain_batch(actions).await)
+}
+
+// ===== HIGH-VALUE LIQUIDATION APPROVALS =====
+
+#[ic_cdk::update]
+fn propose_liquidation(request: CrossChainRequest) -> ApiResult {
+    let proposer = match access_control::ensure_authorized() {
+        Ok(caller) => caller,
+        Err(e) => return ApiResult::Err(e),
+    };
+    liquidation_approvals::propose(request, proposer)
+        .map(|id| id.to_string())
+        .into()
+}
+
+#[ic_cdk::update]
+async fn approve_liquidation(proposal_id: u64) -> ApiResult {
+    let approver = match access_control::ensure_authorized() {
+        Ok(caller) => caller,
+        Err(e) => return ApiResult::Err(e),
+    };
+    match liquidation_approvals::approve(proposal_id, approver).await {
+        Ok(proposal) => match serde_json::to_string(&proposal) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::query]
+fn get_pending_liquidations() -> ApiResult {
+    let proposals = liquidation_approvals::pending_proposals();
+    response_guard::list_json(&proposals).into()
+}
+
+#[ic_cdk::update]
+fn set_liquidation_approval_policy(threshold: u32, high_value_usd: f64) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    if threshold == 0 {
+        return ApiResult::Err("Approval threshold must be at least 1".to_string());
+    }
+    if !high_value_usd.is_finite() || high_value_usd < 0.0 {
+        return ApiResult::Err("High-value threshold must be a non-negative USD amount".to_string());
+    }
+    mutate_state(|s| {
+        s.liquidation_approval_threshold = threshold;
+        s.high_value_liquidation_usd = high_value_usd;
+    });
+    ApiResult::Ok(format!("Liquidations >= ${} now require {} approvals", high_value_usd, threshold))
+}
+
+/// When enabled, execute endpoints check request deadlines against the target
+/// chain's latest block timestamp, at the cost of one extra RPC call per request.
+#[ic_cdk::update]
+fn set_chain_time_deadlines(enabled: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.chain_time_deadlines = enabled);
+    ApiResult::Ok(format!("Chain-time deadline checks {}", if enabled { "enabled" } else { "disabled" }))
+}
+
+/// Blocks a Monad transaction must be buried under before it counts as complete.
+/// `0` completes as soon as a successful receipt arrives.
+#[ic_cdk::update]
+fn set_target_confirmations(confirmations: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.target_confirmations = confirmations);
+    ApiResult::Ok(format!("Target confirmations set to {}", confirmations))
+}
+
+/// Allow or reject Ethereum mainnet (chain 1) as a cross-chain source.
+#[ic_cdk::update]
+fn set_mainnet_sources_enabled(enabled: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.mainnet_sources_enabled = enabled);
+    ApiResult::Ok(format!("Ethereum mainnet source {}", if enabled { "enabled" } else { "disabled" }))
+}
+
+#[ic_cdk::update]
+fn add_authorized_principal(principal: Principal) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.authorized_principals.insert(principal));
+    ApiResult::Ok(format!("Authorized {}", principal))
+}
+
+#[ic_cdk::update]
+fn remove_authorized_principal(principal: Principal) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    if mutate_state(|s| s.authorized_principals.remove(&principal)) {
+        ApiResult::Ok(format!("Removed {}", principal))
+    } else {
+        ApiResult::Err(format!("{} was not authorized", principal))
+    }
+}
+
+// ===== PRICE CONFIGURATION =====
+
+#[ic_cdk::update]
+fn set_fallback_price(asset: String, price_usd: f64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if !price_usd.is_finite() || price_usd <= 0.0 {
+        return ApiResult::Err("Fallback price must be a positive USD amount".to_string());
+    }
+    mutate_state(|s| s.prices.set_fallback(&asset, price_usd));
+    ApiResult::Ok(format!("Fallback price for {} set to ${}", asset, price_usd))
+}
+
+#[ic_cdk::update]
+fn set_price_cache_ttl(ttl_secs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.prices.cache_ttl_secs = ttl_secs);
+    ApiResult::Ok(format!("Price cache TTL set to {}s", ttl_secs))
+}
+
+#[ic_cdk::update]
+fn set_max_price_staleness(max_age_secs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if max_age_secs == 0 {
+        return ApiResult::Err("Max price staleness must be greater than zero".to_string());
+    }
+    mutate_state(|s| s.max_price_staleness_secs = max_age_secs);
+    ApiResult::Ok(format!("Max price staleness set to {}s", max_age_secs))
+}
+
+/// Cap each user's total borrow across chains; `None` removes the cap.
+#[ic_cdk::update]
+fn set_max_user_borrow(max_borrow_usd: Option<f64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = position_limits::validate_cap(max_borrow_usd) {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.position_limits.max_borrow_usd = max_borrow_usd);
+    ApiResult::Ok(format!("Max borrow per user set to {:?}", max_borrow_usd))
+}
+
+/// Cap each user's total collateral across chains; `None` removes the cap.
+#[ic_cdk::update]
+fn set_max_user_collateral(max_collateral_usd: Option<f64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = position_limits::validate_cap(max_collateral_usd) {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.position_limits.max_collateral_usd = max_collateral_usd);
+    ApiResult::Ok(format!("Max collateral per user set to {:?}", max_collateral_usd))
+}
+
+#[ic_cdk::query]
+fn get_user_limits(user_address: String) -> ApiResult {
+    let limits = read_state(|s| position_limits::user_limits(s, &user_address));
+    match serde_json::to_string(&limits) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e)),
+    }
+}
+
+#[ic_cdk::update]
+async fn refresh_asset_price(chain_id: u64, p_token: String) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let p_token = match p_token.parse() {
+        Ok(address) => address,
+        Err(e) => return ApiResult::Err(format!("Invalid pToken address: {}", e)),
+    };
+    match price_oracle::refresh_underlying_price(chain_id, p_token).await {
+        Ok(price) => match serde_json::to_string(&price) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+#[ic_cdk::query]
+fn get_asset_price(asset: String) -> ApiResult {
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    let price = read_state(|s| s.prices.resolve(&asset, None, now));
+    match serde_json::to_string(&price) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+/// Hex calldata the canister would send for an action, the market it is sent to,
+/// and its decoded arguments.
+#[ic_cdk::query]
+fn get_action_calldata(action: PeridotAction, asset_address: String, amount: String) -> ApiResult {
+    match CrossChainTransactionHandler::action_calldata(&action, &asset_address, &amount) {
+        Ok(calldata) => match serde_json::to_string(&calldata) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e)
+    }
+}
+
+/// Update rather than query: estimating fees makes live `eth_gasPrice` outcalls.
+#[ic_cdk::update]
+async fn estimate_cross_chain_gas(
+    user_address: String,
+    source_chain_id: u64,
+    target_chain_id: u64,
+    action: String, // "supply", "borrow", "liquidate"
+    amount: String,
+) -> ApiResult {
+    if let Err(e) = CrossChainTransactionHandler::validate_chains(source_chain_id, target_chain_id) {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = CrossChainTransactionHandler::parse_amount(&amount) {
+        return ApiResult::Err(e);
+    }
+
+    let action_enum = match action.as_str() {
+        "supply" => PeridotAction::Supply { underlying_asset: "USDC".to_string() },
+        "borrow" => PeridotAction::Borrow { underlying_asset: "USDC".to_string(), recipient_address: None },
+        "liquidate" => PeridotAction::LiquidateBorrow {
+            borrower: "0x000".to_string(),
+            underlying_asset: "USDC".to_string(),
+            collateral_asset: "ETH".to_string(),
+            auto_claim: None,
+        },
+        _ => return ApiResult::Err(format!("Invalid action '{}': expected supply, borrow or liquidate", action)),
+    };
+
+    let request = CrossChainRequest {
+        user_address,
+        source_chain_id,
+        target_chain_id,
+        action: action_enum,
+        amount,
+        asset_address: "0x000".to_string(), // Mock
+        max_gas_price: 0,
+        deadline: ic_cdk::api::time() / 1_000_000_000 + 86400, // 24 hours from now
+    };
+
+    match CrossChainTransactionHandler::estimate_gas_costs(&request).await {
+        Ok(estimate) => {
+            match serde_json::to_string(&estimate) {
                 Ok(json) => ApiResult::Ok(json),
                 Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
             }
@@ -327,6 +1175,516 @@ async fn estimate_cross_chain_gas(
     }
 }
 
+#[ic_cdk::query]
+fn get_transaction_metrics() -> ApiResult {
+    match serde_json::to_string(&metrics::snapshot()) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+#[ic_cdk::query]
+fn get_liquidation_events(chain_id: Option<u64>, since_timestamp: Option<u64>, limit: u64) -> ApiResult {
+    let events = liquidation_events::query(chain_id, since_timestamp, limit as usize);
+    response_guard::list_json(&events).into()
+}
+
+// ===== CHAIN CONTRACT CONFIGURATION =====
+
+#[ic_cdk::update]
+fn set_chain_contracts(
+    chain_id: u64,
+    controller: String,
+    price_oracle: String,
+    lens: Option<String>,
+) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| {
+        let config = s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+
+        let mut updated = config.clone();
+        updated.controller = controller;
+        updated.price_oracle = price_oracle;
+        updated.lens = lens;
+        updated.validate()?;
+
+        s.chain_configs.insert(chain_id, updated);
+        Ok(format!("Updated contracts for chain {}", chain_id))
+    }).into()
+}
+
+/// Set the event signatures (topic0 hashes) monitored on a chain. `None` restores
+/// the core Peridot events.
+#[ic_cdk::update]
+fn set_chain_event_topics(chain_id: u64, event_topics: Option<Vec<String>>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| {
+        let config = s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+
+        let mut updated = config.clone();
+        updated.event_topics = event_topics;
+        updated.validate()?;
+        let count = updated.monitored_topics()?.len();
+
+        s.chain_configs.insert(chain_id, updated);
+        Ok(format!("Monitoring {} event signature(s) on chain {}", count, chain_id))
+    }).into()
+}
+
+/// Register a new chain. Fails if the chain id or its Peridot contract is already configured.
+#[ic_cdk::update]
+fn add_chain_config(config: chain_fusion_manager::ChainConfig) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    let chain_id = config.chain_id;
+    mutate_state(|s| chain_fusion_manager::insert_chain_config(&mut s.chain_configs, config))
+        .map(|()| format!("Added chain {}", chain_id))
+        .into()
+}
+
+/// Pause or resume event sync for one chain, e.g. while its RPC providers misbehave.
+#[ic_cdk::update]
+fn set_chain_sync_enabled(chain_id: u64, enabled: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| {
+        if !s.chain_configs.contains_key(&chain_id) {
+            return Err(format!("Chain {} not configured", chain_id));
+        }
+        if enabled {
+            s.sync_paused_chains.remove(&chain_id);
+        } else {
+            s.sync_paused_chains.insert(chain_id);
+            s.sync_degraded.remove(&chain_id);
+        }
+        Ok(format!("Sync {} for chain {}", if enabled { "enabled" } else { "paused" }, chain_id))
+    }).into()
+}
+
+/// Choose how gas is priced on a chain; chains without a setting use `eth_gasPrice`.
+#[ic_cdk::update]
+fn set_gas_price_source(chain_id: u64, source: gas_price::GasPriceSource) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = source.validate() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| {
+        if !s.chain_configs.contains_key(&chain_id) {
+            return Err(format!("Chain {} not configured", chain_id));
+        }
+        s.gas_price_sources.insert(chain_id, source);
+        Ok(format!("Gas price source for chain {} set to {:?}", chain_id, source))
+    }).into()
+}
+
+/// Cap the topic0 alternatives sent to a provider (by host) in one log filter;
+/// larger topic sets are split across several `eth_getLogs` calls. `None` lifts the cap.
+#[ic_cdk::update]
+fn set_provider_topic_limit(host: String, limit: Option<u64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if limit == Some(0) {
+        return ApiResult::Err("Topic limit must be greater than zero".to_string());
+    }
+    let host = rpc_manager::normalize_host(&host);
+    mutate_state(|s| match limit {
+        Some(limit) => s.provider_topic_limits.insert(host.clone(), limit),
+        None => s.provider_topic_limits.remove(&host),
+    });
+    ApiResult::Ok(format!("Topic limit for {} set to {:?}", host, limit))
+}
+
+/// Leg durations for a bridge route, used for `estimate_cross_chain_gas` time
+/// estimates. `None` reverts the route to the default estimate.
+#[ic_cdk::update]
+fn set_route_timing(source_chain_id: u64, target_chain_id: u64, timing: Option<bridge::RouteTiming>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let route = (source_chain_id, target_chain_id);
+    mutate_state(|s| match timing {
+        Some(timing) => s.route_timings.insert(route, timing),
+        None => s.route_timings.remove(&route),
+    });
+    ApiResult::Ok(format!("Route {} -> {} timing set to {:?}", source_chain_id, target_chain_id, timing))
+}
+
+/// What to do with logs no event handler recognizes: ignore, log, or also store them.
+#[ic_cdk::update]
+fn set_unknown_event_policy(policy: unrecognized_events::UnknownEventPolicy) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| s.unknown_event_policy = policy);
+    ApiResult::Ok(format!("Unknown event policy set to {:?}", policy))
+}
+
+#[ic_cdk::query]
+fn get_unrecognized_events() -> ApiResult {
+    let events = read_state(|s| s.unrecognized_events.clone());
+    response_guard::list_json(&events).into()
+}
+
+/// USD price of a native gas token (e.g. ETH, BNB, MON), used to value gas estimates.
+#[ic_cdk::update]
+fn set_native_token_price(symbol: String, price_usd: f64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if !price_usd.is_finite() || price_usd <= 0.0 {
+        return ApiResult::Err(format!("Price must be a positive USD value, got {}", price_usd));
+    }
+    let symbol = symbol.trim().to_uppercase();
+    mutate_state(|s| s.native_token_prices.insert(symbol.clone(), price_usd));
+    ApiResult::Ok(format!("{} priced at ${}", symbol, price_usd))
+}
+
+/// Cap concurrent cross-chain executions. Excess requests fail with `Busy`, or are
+/// queued for retry when `queue_excess` is set.
+#[ic_cdk::update]
+fn set_execution_concurrency(max_concurrent: u64, queue_excess: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if max_concurrent == 0 {
+        return ApiResult::Err("Concurrency limit must be greater than zero".to_string());
+    }
+    mutate_state(|s| {
+        s.max_concurrent_executions = max_concurrent;
+        s.queue_busy_executions = queue_excess;
+    });
+    ApiResult::Ok(format!("At most {} concurrent executions, queue excess: {}", max_concurrent, queue_excess))
+}
+
+/// Record liquidity-mining rewards for a market, reported in `compare_asset_rates`
+/// as the gap between base and effective APY. `None` removes them.
+#[ic_cdk::update]
+fn set_market_incentive(market_address: String, incentive: Option<rates::MarketIncentive>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Some(incentive) = &incentive {
+        if let Err(e) = incentive.validate() {
+            return ApiResult::Err(e);
+        }
+    }
+    let market = enhanced_api::canonical_address(&market_address);
+    mutate_state(|s| match incentive.clone() {
+        Some(incentive) => s.market_incentives.insert(market.clone(), incentive),
+        None => s.market_incentives.remove(&market),
+    });
+    ApiResult::Ok(format!("Incentive for {} set to {:?}", market, incentive))
+}
+
+/// Source chain assumed when an execute call omits `source_chain_id`; `None` clears it.
+#[ic_cdk::update]
+fn set_default_source_chain(chain_id: Option<u64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Some(chain_id) = chain_id {
+        if !CrossChainConfig::default().supported_source_chains.contains_key(&chain_id) {
+            return ApiResult::Err(format!("Source chain {} not supported", chain_id));
+        }
+    }
+    mutate_state(|s| s.default_source_chain = chain_id);
+    ApiResult::Ok(format!("Default source chain set to {:?}", chain_id))
+}
+
+/// Cap the collateral factor (a fraction in (0, 1]) used for a market's health
+/// math; higher on-chain factors are clamped and the position flagged. `None` removes the cap.
+#[ic_cdk::update]
+fn set_collateral_factor_cap(market_address: String, cap: Option<f64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Some(cap) = cap {
+        if !cap.is_finite() || cap <= 0.0 || cap > 1.0 {
+            return ApiResult::Err(format!("Collateral factor cap must be in (0, 1], got {}", cap));
+        }
+    }
+    let market = enhanced_api::canonical_address(&market_address);
+    let chains: Vec<u64> = mutate_state(|s| {
+        match cap {
+            Some(cap) => s.collateral_factor_caps.insert(market.clone(), cap),
+            None => s.collateral_factor_caps.remove(&market),
+        };
+        s.market_states.keys()
+            .filter(|(_, address)| *address == market)
+            .map(|(chain_id, _)| *chain_id)
+            .collect()
+    });
+    for chain_id in chains {
+        job::recompute_all_health_factors(chain_id);
+    }
+    ApiResult::Ok(format!("Collateral factor cap for {} set to {:?}", market, cap))
+}
+
+/// Widest block range fetched per `eth_getLogs`; longer gaps are synced in several cycles.
+#[ic_cdk::update]
+fn set_max_block_span(max_span: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if max_span == 0 {
+        return ApiResult::Err("Block span must be greater than zero".to_string());
+    }
+    mutate_state(|s| s.max_block_span = max_span);
+    ApiResult::Ok(format!("Max block span set to {}", max_span))
+}
+
+/// Block scanned first when a chain has no sync progress (e.g. the Peridot deployment block).
+#[ic_cdk::update]
+fn set_genesis_sync_block(chain_id: u64, block: Option<u64>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    mutate_state(|s| {
+        let config = s.chain_configs.get_mut(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+        config.genesis_sync_block = block;
+        Ok(match block {
+            Some(block) => format!("Genesis sync block for chain {} set to {}", chain_id, block),
+            None => format!("Genesis sync block for chain {} cleared; a fresh sync starts at the chain head", chain_id),
+        })
+    }).into()
+}
+
+#[ic_cdk::query]
+fn get_chain_contracts(chain_id: u64) -> ApiResult {
+    read_state(|s| match s.chain_configs.get(&chain_id) {
+        Some(config) => match serde_json::to_string(config) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        None => ApiResult::Err(format!("Chain {} not configured", chain_id)),
+    })
+}
+
+/// Addresses whose logs are currently fetched and applied on a chain.
+#[ic_cdk::query]
+fn get_monitored_contracts(chain_id: u64) -> ApiResult {
+    let contracts = match read_state(|s| s.monitored_contracts(chain_id)) {
+        Ok(contracts) => contracts,
+        Err(e) => return ApiResult::Err(e),
+    };
+    let contracts: Vec<String> = contracts.iter().map(|address| address.to_string()).collect();
+    match serde_json::to_string(&contracts) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+// ===== RUNTIME CONFIGURATION =====
+
+/// Effective runtime configuration. RPC secrets are redacted and principals truncated.
+#[ic_cdk::query]
+fn get_config() -> ApiResult {
+    match serde_json::to_string(&config::snapshot()) {
+        Ok(json) => ApiResult::Ok(json),
+        Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+    }
+}
+
+// ===== RPC CONFIGURATION =====
+
+#[ic_cdk::update]
+fn set_rpc_urls(chain_id: u64, urls: Vec<String>) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if let Err(e) = rpc_manager::validate_rpc_urls(&urls) {
+        return ApiResult::Err(e);
+    }
+    let count = urls.len();
+    mutate_state(|s| s.rpc_urls.insert(chain_id, urls));
+    ApiResult::Ok(format!("Set {} RPC URL(s) for chain {}", count, chain_id))
+}
+
+#[ic_cdk::update]
+fn set_rpc_timeout(timeout_secs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if timeout_secs == 0 {
+        return ApiResult::Err("RPC timeout must be greater than zero".to_string());
+    }
+    mutate_state(|s| s.rpc_timeout_secs = timeout_secs);
+    ApiResult::Ok(format!("RPC timeout set to {}s", timeout_secs))
+}
+
+/// A chain's RPC URLs, for authorized callers. API keys are redacted as in `get_config`.
+#[ic_cdk::query]
+fn get_rpc_urls(chain_id: u64) -> ApiResult {
+    match config::rpc_urls(chain_id) {
+        Ok(urls) => match serde_json::to_string(&urls) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e),
+    }
+}
+
+// ===== MAINTENANCE =====
+
+#[ic_cdk::query]
+fn transform_rpc_response(args: ic_cdk::api::management_canister::http_request::TransformArgs)
+    -> ic_cdk::api::management_canister::http_request::HttpResponse {
+    rpc_manager::transform_rpc_response(args)
+}
+
+/// HTTP gateway entry point; serves Prometheus metrics at `/metrics`.
+#[ic_cdk::query]
+fn http_request(request: http::HttpRequest) -> http::HttpResponse {
+    http::handle(request)
+}
+
+#[ic_cdk::query]
+fn transform_webhook_response(args: ic_cdk::api::management_canister::http_request::TransformArgs)
+    -> ic_cdk::api::management_canister::http_request::HttpResponse {
+    notifications::transform_webhook_response(args)
+}
+
+/// Seconds without a successful sync before a chain is reported as degraded.
+#[ic_cdk::update]
+fn set_sync_stall_threshold(threshold_secs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    if threshold_secs == 0 {
+        return ApiResult::Err("Threshold must be greater than zero".to_string());
+    }
+    mutate_state(|s| s.sync_stall_threshold_secs = threshold_secs);
+    ApiResult::Ok(format!("Sync stall threshold set to {}s", threshold_secs))
+}
+
+/// Webhook that receives operational alerts; `None` disables notifications.
+#[ic_cdk::update]
+fn set_notification_webhook(url: Option<String>) -> ApiResult {
+    if let Err(e) = access_control::ensure_controller() {
+        return ApiResult::Err(e);
+    }
+    if let Some(url) = &url {
+        if !url.starts_with("https://") {
+            return ApiResult::Err("Webhook URL must use https".to_string());
+        }
+    }
+    mutate_state(|s| s.notification_webhook = url.clone());
+    match url {
+        Some(_) => ApiResult::Ok("Notification webhook set".to_string()),
+        None => ApiResult::Ok("Notification webhook cleared".to_string()),
+    }
+}
+
+/// Recompute health factors for every tracked position on a chain.
+#[ic_cdk::update]
+fn recompute_health_factors(chain_id: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let updated = job::recompute_all_health_factors(chain_id);
+    ApiResult::Ok(format!("Recomputed {} position(s) on chain {}", updated, chain_id))
+}
+
+/// Replay a known transaction's Peridot logs into state, e.g. to recover a missed event.
+#[ic_cdk::update]
+async fn ingest_transaction(chain_id: u64, tx_hash: String) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let tx_hash = match tx_hash.parse::<alloy::primitives::B256>() {
+        Ok(hash) => hash,
+        Err(e) => return ApiResult::Err(format!("Invalid transaction hash '{}': {}", tx_hash, e)),
+    };
+
+    let manager = ChainFusionManager::new();
+    match manager.ingest_transaction(chain_id, tx_hash).await {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e),
+    }
+}
+
+/// Re-scan a missed block range without moving the sync cursor; duplicates are skipped.
+#[ic_cdk::update]
+async fn sync_block_range(chain_id: u64, from_block: u64, to_block: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+
+    let mut manager = ChainFusionManager::new();
+    match manager.sync_block_range(chain_id, from_block, to_block).await {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => ApiResult::Ok(json),
+            Err(e) => ApiResult::Err(format!("Serialization error: {}", e))
+        },
+        Err(e) => ApiResult::Err(e),
+    }
+}
+
+#[ic_cdk::update]
+fn compact_logs() -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    let (removed, remaining, pending) = mutate_state(|s| {
+        let removed = s.compact_processed_logs();
+        (removed, s.processed_logs.len(), s.logs_to_process.len())
+    });
+    ApiResult::Ok(format!(
+        "{{\"removed\":{},\"processed_logs\":{},\"logs_to_process\":{}}}",
+        removed, remaining, pending
+    ))
+}
+
+/// Byte budget for large query responses; collections beyond it are truncated.
+#[ic_cdk::update]
+fn set_max_response_bytes(max_bytes: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if max_bytes == 0 || max_bytes > 2_000_000 {
+        return ApiResult::Err("Response budget must be between 1 and 2,000,000 bytes".to_string());
+    }
+    mutate_state(|s| s.max_response_bytes = max_bytes as usize);
+    ApiResult::Ok(format!("Response budget set to {} bytes", max_bytes))
+}
+
+/// Strip non-ASCII characters (emoji) from canister logs for ingestion tools.
+#[ic_cdk::update]
+fn set_log_ascii_only(enabled: bool) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    logger::set_ascii_only(enabled);
+    ApiResult::Ok(format!("ASCII-only logging {}", if enabled { "enabled" } else { "disabled" }))
+}
+
+#[ic_cdk::update]
+fn set_log_retention(max_processed_logs: u64) -> ApiResult {
+    if let Err(e) = access_control::ensure_authorized() {
+        return ApiResult::Err(e);
+    }
+    if max_processed_logs == 0 {
+        return ApiResult::Err("Retention must keep at least one processed log".to_string());
+    }
+    mutate_state(|s| s.processed_logs_retention = max_processed_logs as usize);
+    ApiResult::Ok(format!("Keeping the most recent {} processed logs", max_processed_logs))
+}
+
 // ===== TESTING AND DEBUG FUNCTIONS =====
 
 #[ic_cdk::query]
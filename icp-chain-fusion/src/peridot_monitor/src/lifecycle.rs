@@ -1,10 +1,40 @@
-use crate::state::{InvalidStateError, State};
+use crate::bridge::{default_route_timings, route_timing_list, RouteTiming};
+use crate::chain_fusion_manager::{
+    default_chain_configs, partition_chain_configs, validate_chain_configs, ChainConfig, DEFAULT_MAX_BLOCK_SPAN,
+};
+use crate::emergency_withdraw::EmergencyWithdrawal;
+use crate::enhanced_api::{ArbitrageFilter, RiskThresholds};
+use crate::health_alerts::{HealthAlert, HealthAlertSubscription};
+use crate::liquidation_approvals::LiquidationProposal;
+use crate::liquidation_events::LiquidationEvent;
+use crate::gas_price::{default_native_token_prices, GasPriceSource};
+use crate::guard::DEFAULT_MAX_CONCURRENT_EXECUTIONS;
+use crate::liquidation_cooldown::DEFAULT_LIQUIDATION_COOLDOWN_SECS;
+use crate::cycles::DEFAULT_CYCLE_FLOOR;
+use crate::logger;
+use crate::position_limits::PositionLimits;
+use crate::price_oracle::{PriceBook, DEFAULT_MAX_PRICE_STALENESS_SECS};
+use crate::rates::MarketIncentive;
+use crate::request_store::CrossChainRequestRecord;
+use crate::retry_queue::{DeadLetter, RetryEntry, DEFAULT_MAX_RETRY_ATTEMPTS};
+use crate::response_guard::DEFAULT_MAX_RESPONSE_BYTES;
+use crate::token_metadata::TokenMetadata;
+use crate::rpc_manager::{default_rpc_urls, validate_rpc_service, DEFAULT_RPC_TIMEOUT_SECS};
+use crate::unrecognized_events::{UnknownEventPolicy, UnrecognizedEvent};
+use crate::watchdog::DEFAULT_SYNC_STALL_THRESHOLD_SECS;
+use crate::state::{AppliedLogKey, InvalidStateError, MarketState, State, UserPosition, DEFAULT_PROCESSED_LOGS_RETENTION};
 use alloy::primitives::Address;
 use alloy::transports::icp::RpcService;
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use ic_cdk::api::management_canister::ecdsa::EcdsaKeyId;
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+/// Number of distinct approvals required to execute a high-value liquidation.
+pub const DEFAULT_LIQUIDATION_APPROVAL_THRESHOLD: u32 = 2;
+/// Liquidations at or above this USD value go through the approval flow.
+pub const DEFAULT_HIGH_VALUE_LIQUIDATION_USD: f64 = 100_000.0;
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct InitArg {
     pub rpc_service: RpcService,
@@ -26,6 +56,8 @@ impl TryFrom<InitArg> for State {
             ecdsa_key_id,
         }: InitArg,
     ) -> Result<Self, Self::Error> {
+        validate_rpc_service(&rpc_service).map_err(InvalidStateError::InvalidRpcService)?;
+
         let validated_filter_addresses: Vec<Address> = filter_addresses
             .iter()
             .map(|address| {
@@ -35,6 +67,9 @@ impl TryFrom<InitArg> for State {
             })
             .collect::<Result<_, _>>()?;
 
+        let chain_configs = default_chain_configs();
+        validate_chain_configs(&chain_configs).map_err(InvalidStateError::InvalidEthereumContractAddress)?;
+
         let state = Self {
             rpc_service,
             chain_id,
@@ -42,14 +77,357 @@ impl TryFrom<InitArg> for State {
             filter_events,
             logs_to_process: Default::default(),
             processed_logs: Default::default(),
+            processed_logs_retention: DEFAULT_PROCESSED_LOGS_RETENTION,
             active_tasks: Default::default(),
             signer: None,
             ecdsa_key_id,
             canister_evm_address: None,
-            nonce: None,
+            nonces: Default::default(),
             user_positions: Default::default(),
             market_states: Default::default(),
+            authorized_principals: Default::default(),
+            liquidation_approval_threshold: DEFAULT_LIQUIDATION_APPROVAL_THRESHOLD,
+            high_value_liquidation_usd: DEFAULT_HIGH_VALUE_LIQUIDATION_USD,
+            liquidation_proposals: Default::default(),
+            next_liquidation_proposal_id: 0,
+            prices: Default::default(),
+            tx_metrics: Default::default(),
+            chain_configs,
+            rpc_urls: default_rpc_urls(),
+            risk_parameters: Default::default(),
+            native_balances: Default::default(),
+            cross_chain_requests: Default::default(),
+            liquidation_events: Default::default(),
+            mainnet_sources_enabled: false,
+            target_confirmations: 0,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            started_at: 0, // stamped by init/post_upgrade
+            last_successful_sync: Default::default(),
+            sync_degraded: Default::default(),
+            sync_stall_threshold_secs: DEFAULT_SYNC_STALL_THRESHOLD_SECS,
+            sync_paused_chains: Default::default(),
+            notification_webhook: None,
+            token_metadata: Default::default(),
+            head_blocks: Default::default(),
+            events_processed: Default::default(),
+            rpc_failures: Default::default(),
+            retry_queue: Default::default(),
+            dead_letters: Default::default(),
+            max_retry_attempts: DEFAULT_MAX_RETRY_ATTEMPTS,
+            max_block_span: DEFAULT_MAX_BLOCK_SPAN,
+            max_price_staleness_secs: DEFAULT_MAX_PRICE_STALENESS_SECS,
+            liquidation_attempts: Default::default(),
+            liquidation_cooldown_secs: DEFAULT_LIQUIDATION_COOLDOWN_SECS,
+            disabled_actions: Default::default(),
+            rpc_timeout_secs: DEFAULT_RPC_TIMEOUT_SECS,
+            chain_time_deadlines: false,
+            risk_thresholds: RiskThresholds::default(),
+            arbitrage_filter: ArbitrageFilter::default(),
+            gas_price_sources: Default::default(),
+            request_counter: 0,
+            position_limits: PositionLimits::default(),
+            paused: false,
+            emergency_withdrawals: Default::default(),
+            provider_topic_limits: Default::default(),
+            health_alert_subscriptions: Default::default(),
+            pending_health_alerts: Default::default(),
+            cycle_floor: DEFAULT_CYCLE_FLOOR,
+            collateral_factor_caps: Default::default(),
+            default_source_chain: None,
+            market_incentives: Default::default(),
+            max_concurrent_executions: DEFAULT_MAX_CONCURRENT_EXECUTIONS,
+            queue_busy_executions: false,
+            in_flight_executions: 0,
+            native_token_prices: default_native_token_prices(),
+            observed_gas_prices: Default::default(),
+            unknown_event_policy: UnknownEventPolicy::default(),
+            unrecognized_events: Default::default(),
+            route_timings: default_route_timings(),
+            applied_logs: Default::default(),
         };
         Ok(state)
     }
-} 
\ No newline at end of file
+}
+
+/// Runtime configuration carried across upgrades in stable memory. Fields are
+/// optional so snapshots written by older versions still decode.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct PersistedState {
+    pub rpc_urls: Option<BTreeMap<u64, Vec<String>>>,
+    pub chain_configs: Option<BTreeMap<u64, ChainConfig>>,
+    pub authorized_principals: Option<Vec<Principal>>,
+    pub prices: Option<PriceBook>,
+    pub mainnet_sources_enabled: Option<bool>,
+    pub log_ascii_only: Option<bool>,
+    pub target_confirmations: Option<u64>,
+    pub max_response_bytes: Option<u64>,
+    pub sync_stall_threshold_secs: Option<u64>,
+    pub notification_webhook: Option<String>,
+    pub cross_chain_requests: Option<BTreeMap<String, CrossChainRequestRecord>>,
+    pub retry_queue: Option<BTreeMap<String, RetryEntry>>,
+    pub dead_letters: Option<Vec<DeadLetter>>,
+    pub max_retry_attempts: Option<u32>,
+    pub max_block_span: Option<u64>,
+    pub max_price_staleness_secs: Option<u64>,
+    pub liquidation_cooldown_secs: Option<u64>,
+    pub disabled_actions: Option<Vec<String>>,
+    pub rpc_timeout_secs: Option<u64>,
+    pub risk_thresholds: Option<RiskThresholds>,
+    pub sync_paused_chains: Option<Vec<u64>>,
+    pub chain_time_deadlines: Option<bool>,
+    pub arbitrage_filter: Option<ArbitrageFilter>,
+    pub gas_price_sources: Option<BTreeMap<u64, GasPriceSource>>,
+    pub request_counter: Option<u64>,
+    pub position_limits: Option<PositionLimits>,
+    pub paused: Option<bool>,
+    pub emergency_withdrawals: Option<Vec<EmergencyWithdrawal>>,
+    pub provider_topic_limits: Option<BTreeMap<String, u64>>,
+    pub health_alert_subscriptions: Option<Vec<HealthAlertSubscription>>,
+    pub pending_health_alerts: Option<BTreeMap<Principal, Vec<HealthAlert>>>,
+    pub cycle_floor: Option<u128>,
+    pub collateral_factor_caps: Option<BTreeMap<String, f64>>,
+    pub default_source_chain: Option<u64>,
+    pub market_incentives: Option<BTreeMap<String, MarketIncentive>>,
+    pub max_concurrent_executions: Option<u64>,
+    pub queue_busy_executions: Option<bool>,
+    pub native_token_prices: Option<BTreeMap<String, f64>>,
+    pub unknown_event_policy: Option<UnknownEventPolicy>,
+    pub unrecognized_events: Option<Vec<UnrecognizedEvent>>,
+    pub route_timings: Option<Vec<(u64, u64, RouteTiming)>>,
+    pub liquidation_proposals: Option<BTreeMap<u64, LiquidationProposal>>,
+    pub next_liquidation_proposal_id: Option<u64>,
+    pub head_blocks: Option<BTreeMap<u64, u64>>,
+    pub applied_logs: Option<Vec<AppliedLogKey>>,
+    pub user_positions: Option<BTreeMap<(String, u64), UserPosition>>,
+    pub market_states: Option<BTreeMap<(u64, String), MarketState>>,
+    pub token_metadata: Option<Vec<((u64, String), TokenMetadata)>>,
+    pub liquidation_events: Option<Vec<LiquidationEvent>>,
+}
+
+impl PersistedState {
+    pub fn capture(state: &State) -> Self {
+        Self {
+            rpc_urls: Some(state.rpc_urls.clone()),
+            chain_configs: Some(state.chain_configs.clone()),
+            authorized_principals: Some(state.authorized_principals.iter().copied().collect()),
+            prices: Some(state.prices.clone()),
+            mainnet_sources_enabled: Some(state.mainnet_sources_enabled),
+            log_ascii_only: Some(logger::ascii_only()),
+            target_confirmations: Some(state.target_confirmations),
+            max_response_bytes: Some(state.max_response_bytes as u64),
+            sync_stall_threshold_secs: Some(state.sync_stall_threshold_secs),
+            notification_webhook: state.notification_webhook.clone(),
+            cross_chain_requests: Some(state.cross_chain_requests.clone()),
+            retry_queue: Some(state.retry_queue.clone()),
+            dead_letters: Some(state.dead_letters.clone()),
+            max_retry_attempts: Some(state.max_retry_attempts),
+            max_block_span: Some(state.max_block_span),
+            max_price_staleness_secs: Some(state.max_price_staleness_secs),
+            liquidation_cooldown_secs: Some(state.liquidation_cooldown_secs),
+            disabled_actions: Some(state.disabled_actions.iter().cloned().collect()),
+            rpc_timeout_secs: Some(state.rpc_timeout_secs),
+            risk_thresholds: Some(state.risk_thresholds.clone()),
+            sync_paused_chains: Some(state.sync_paused_chains.iter().copied().collect()),
+            chain_time_deadlines: Some(state.chain_time_deadlines),
+            arbitrage_filter: Some(state.arbitrage_filter.clone()),
+            gas_price_sources: Some(state.gas_price_sources.clone()),
+            request_counter: Some(state.request_counter),
+            position_limits: Some(state.position_limits.clone()),
+            paused: Some(state.paused),
+            emergency_withdrawals: Some(state.emergency_withdrawals.clone()),
+            provider_topic_limits: Some(state.provider_topic_limits.clone()),
+            health_alert_subscriptions: Some(state.health_alert_subscriptions.values().cloned().collect()),
+            pending_health_alerts: Some(state.pending_health_alerts.clone()),
+            cycle_floor: Some(state.cycle_floor),
+            collateral_factor_caps: Some(state.collateral_factor_caps.clone()),
+            default_source_chain: state.default_source_chain,
+            market_incentives: Some(state.market_incentives.clone()),
+            max_concurrent_executions: Some(state.max_concurrent_executions),
+            queue_busy_executions: Some(state.queue_busy_executions),
+            native_token_prices: Some(state.native_token_prices.clone()),
+            unknown_event_policy: Some(state.unknown_event_policy),
+            unrecognized_events: Some(state.unrecognized_events.clone()),
+            route_timings: Some(route_timing_list(&state.route_timings)),
+            liquidation_proposals: Some(state.liquidation_proposals.clone()),
+            next_liquidation_proposal_id: Some(state.next_liquidation_proposal_id),
+            head_blocks: Some(state.head_blocks.clone()),
+            applied_logs: Some(state.applied_logs.iter().cloned().collect()),
+            user_positions: Some(state.user_positions.clone()),
+            market_states: Some(state.market_states.clone()),
+            token_metadata: Some(state.token_metadata.iter()
+                .map(|((chain_id, market), metadata)| ((*chain_id, market.to_string()), metadata.clone()))
+                .collect()),
+            liquidation_events: Some(state.liquidation_events.iter().cloned().collect()),
+        }
+    }
+
+    pub fn restore(self, state: &mut State) {
+        if let Some(rpc_urls) = self.rpc_urls {
+            state.rpc_urls = rpc_urls;
+        }
+        if let Some(chain_configs) = self.chain_configs {
+            // Entries that fail validation are dropped one by one, each logged
+            let (valid, rejected) = partition_chain_configs(chain_configs);
+            for e in &rejected {
+                logger::log(format!("Dropping persisted chain config for {}", e));
+            }
+            if valid.is_empty() {
+                logger::log("No persisted chain config is valid; keeping the built-in chains");
+            } else {
+                state.chain_configs = valid;
+            }
+        }
+        if let Some(principals) = self.authorized_principals {
+            state.authorized_principals = principals.into_iter().collect();
+        }
+        if let Some(prices) = self.prices {
+            state.prices = prices;
+        }
+        if let Some(enabled) = self.mainnet_sources_enabled {
+            state.mainnet_sources_enabled = enabled;
+        }
+        if let Some(ascii_only) = self.log_ascii_only {
+            logger::set_ascii_only(ascii_only);
+        }
+        if let Some(confirmations) = self.target_confirmations {
+            state.target_confirmations = confirmations;
+        }
+        if let Some(max_bytes) = self.max_response_bytes {
+            state.max_response_bytes = max_bytes as usize;
+        }
+        if let Some(threshold) = self.sync_stall_threshold_secs {
+            state.sync_stall_threshold_secs = threshold;
+        }
+        if self.notification_webhook.is_some() {
+            state.notification_webhook = self.notification_webhook;
+        }
+        if let Some(requests) = self.cross_chain_requests {
+            state.cross_chain_requests = requests;
+        }
+        if let Some(queue) = self.retry_queue {
+            state.retry_queue = queue;
+        }
+        if let Some(dead_letters) = self.dead_letters {
+            state.dead_letters = dead_letters;
+        }
+        if let Some(max_attempts) = self.max_retry_attempts {
+            state.max_retry_attempts = max_attempts;
+        }
+        if let Some(max_span) = self.max_block_span {
+            state.max_block_span = max_span;
+        }
+        if let Some(max_staleness) = self.max_price_staleness_secs {
+            state.max_price_staleness_secs = max_staleness;
+        }
+        if let Some(cooldown) = self.liquidation_cooldown_secs {
+            state.liquidation_cooldown_secs = cooldown;
+        }
+        if let Some(disabled) = self.disabled_actions {
+            state.disabled_actions = disabled.into_iter().collect();
+        }
+        if let Some(timeout) = self.rpc_timeout_secs {
+            state.rpc_timeout_secs = timeout;
+        }
+        if let Some(thresholds) = self.risk_thresholds {
+            state.risk_thresholds = thresholds;
+        }
+        if let Some(paused) = self.sync_paused_chains {
+            state.sync_paused_chains = paused.into_iter().collect();
+        }
+        if let Some(enabled) = self.chain_time_deadlines {
+            state.chain_time_deadlines = enabled;
+        }
+        if let Some(filter) = self.arbitrage_filter {
+            state.arbitrage_filter = filter;
+        }
+        if let Some(sources) = self.gas_price_sources {
+            state.gas_price_sources = sources;
+        }
+        if let Some(counter) = self.request_counter {
+            state.request_counter = counter;
+        }
+        if let Some(limits) = self.position_limits {
+            state.position_limits = limits;
+        }
+        if let Some(paused) = self.paused {
+            state.paused = paused;
+        }
+        if let Some(withdrawals) = self.emergency_withdrawals {
+            state.emergency_withdrawals = withdrawals;
+        }
+        if let Some(limits) = self.provider_topic_limits {
+            state.provider_topic_limits = limits;
+        }
+        if let Some(subscriptions) = self.health_alert_subscriptions {
+            state.health_alert_subscriptions = subscriptions.into_iter()
+                .map(|sub| ((sub.user_address.clone(), sub.subscriber), sub))
+                .collect();
+        }
+        if let Some(alerts) = self.pending_health_alerts {
+            state.pending_health_alerts = alerts;
+        }
+        if let Some(floor) = self.cycle_floor {
+            state.cycle_floor = floor;
+        }
+        if let Some(caps) = self.collateral_factor_caps {
+            state.collateral_factor_caps = caps;
+        }
+        if self.default_source_chain.is_some() {
+            state.default_source_chain = self.default_source_chain;
+        }
+        if let Some(incentives) = self.market_incentives {
+            state.market_incentives = incentives;
+        }
+        if let Some(max) = self.max_concurrent_executions {
+            state.max_concurrent_executions = max;
+        }
+        if let Some(queue) = self.queue_busy_executions {
+            state.queue_busy_executions = queue;
+        }
+        if let Some(prices) = self.native_token_prices {
+            state.native_token_prices = prices;
+        }
+        if let Some(policy) = self.unknown_event_policy {
+            state.unknown_event_policy = policy;
+        }
+        if let Some(events) = self.unrecognized_events {
+            state.unrecognized_events = events;
+        }
+        if let Some(routes) = self.route_timings {
+            state.route_timings = routes.into_iter()
+                .map(|(source, target, timing)| ((source, target), timing))
+                .collect();
+        }
+        if let Some(proposals) = self.liquidation_proposals {
+            state.liquidation_proposals = proposals;
+        }
+        if let Some(next_id) = self.next_liquidation_proposal_id {
+            state.next_liquidation_proposal_id = next_id;
+        }
+        if let Some(head_blocks) = self.head_blocks {
+            state.head_blocks = head_blocks;
+        }
+        // Applied log keys only dedupe replays against the positions they built,
+        // so they are restored together or not at all
+        if let (Some(applied_logs), Some(positions), Some(markets)) =
+            (self.applied_logs, self.user_positions, self.market_states)
+        {
+            state.applied_logs = applied_logs.into_iter().collect();
+            state.user_positions = positions;
+            state.market_states = markets;
+        }
+        if let Some(metadata) = self.token_metadata {
+            for ((chain_id, market), metadata) in metadata {
+                match Address::from_str(&market) {
+                    Ok(market) => {
+                        state.token_metadata.insert((chain_id, market), metadata);
+                    }
+                    Err(e) => logger::log(format!("Dropping persisted token metadata for {}: {}", market, e)),
+                }
+            }
+        }
+        if let Some(events) = self.liquidation_events {
+            state.liquidation_events = events.into_iter().collect();
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::cross_chain_transactions::{
+    CrossChainRequest, CrossChainResponse, CrossChainTransactionHandler, PeridotAction,
+};
+use crate::math;
+use crate::state::{mutate_state, read_state};
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+// ===== M-OF-N APPROVAL FOR HIGH-VALUE LIQUIDATIONS =====
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum ProposalStatus {
+    Pending,
+    Executing,
+    Executed,
+    Failed,
+    Expired,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct LiquidationProposal {
+    pub id: u64,
+    pub request: CrossChainRequest,
+    pub proposer: Principal,
+    pub approvals: Vec<Principal>,
+    pub created_at: u64,
+    pub status: ProposalStatus,
+    pub response: Option<CrossChainResponse>,
+    pub error_message: Option<String>,
+}
+
+/// Rough USD value of a liquidation, treating the repay amount as an 18-decimal
+/// stablecoin amount.
+pub fn estimate_liquidation_value_usd(request: &CrossChainRequest) -> f64 {
+    request.amount.parse::<u128>().map(|wei| math::usd_value(wei, 18, 1.0)).unwrap_or(f64::MAX)
+}
+
+/// Whether a request is a liquidation large enough to require M-of-N approval.
+/// A threshold of one approval is the caller's own, so such liquidations run directly.
+pub fn requires_approval(request: &CrossChainRequest) -> bool {
+    if !matches!(request.action, PeridotAction::LiquidateBorrow { .. }) {
+        return false;
+    }
+    let (approvals_required, threshold_usd) =
+        read_state(|s| (s.liquidation_approval_threshold, s.high_value_liquidation_usd));
+    approvals_required > 1 && estimate_liquidation_value_usd(request) >= threshold_usd
+}
+
+/// Store a new liquidation proposal. The proposer's approval is counted.
+pub fn propose(request: CrossChainRequest, proposer: Principal) -> Result<u64, String> {
+    if !matches!(request.action, PeridotAction::LiquidateBorrow { .. }) {
+        return Err("Only LiquidateBorrow requests can be proposed".to_string());
+    }
+    if request.deadline <= current_timestamp() {
+        return Err(format!("Proposal deadline {} has already passed", request.deadline));
+    }
+
+    Ok(mutate_state(|s| {
+        let id = s.next_liquidation_proposal_id;
+        s.next_liquidation_proposal_id += 1;
+        s.liquidation_proposals.insert(id, LiquidationProposal {
+            id,
+            request,
+            proposer,
+            approvals: vec![proposer],
+            created_at: current_timestamp(),
+            status: ProposalStatus::Pending,
+            response: None,
+            error_message: None,
+        });
+        id
+    }))
+}
+
+/// Record an approval from a distinct principal and execute the liquidation once
+/// the approval threshold is reached before the proposal's deadline.
+pub async fn approve(proposal_id: u64, approver: Principal) -> Result<LiquidationProposal, String> {
+    let ready = mutate_state(|s| {
+        let threshold = s.liquidation_approval_threshold as usize;
+        let proposal = s.liquidation_proposals.get_mut(&proposal_id)
+            .ok_or_else(|| format!("Proposal {} not found", proposal_id))?;
+
+        if proposal.status != ProposalStatus::Pending {
+            return Err(format!("Proposal {} is not pending ({:?})", proposal_id, proposal.status));
+        }
+        if proposal.request.deadline <= current_timestamp() {
+            proposal.status = ProposalStatus::Expired;
+            return Err(format!("Proposal {} has expired", proposal_id));
+        }
+        if proposal.approvals.contains(&approver) {
+            return Err(format!("{} has already approved proposal {}", approver, proposal_id));
+        }
+
+        proposal.approvals.push(approver);
+        if proposal.approvals.len() >= threshold {
+            // Mark as executing so concurrent approvals cannot trigger a second run
+            proposal.status = ProposalStatus::Executing;
+            Ok(Some(proposal.request.clone()))
+        } else {
+            Ok(None)
+        }
+    })?;
+
+    if let Some(request) = ready {
+        let result = CrossChainTransactionHandler::execute_cross_chain_action(request).await;
+        mutate_state(|s| {
+            if let Some(proposal) = s.liquidation_proposals.get_mut(&proposal_id) {
+                match result {
+                    Ok(response) => {
+                        proposal.status = ProposalStatus::Executed;
+                        proposal.response = Some(response);
+                    }
+                    Err(e) => {
+                        proposal.status = ProposalStatus::Failed;
+                        proposal.error_message = Some(e);
+                    }
+                }
+            }
+        });
+    }
+
+    read_state(|s| s.liquidation_proposals.get(&proposal_id).cloned())
+        .ok_or_else(|| format!("Proposal {} not found", proposal_id))
+}
+
+/// Proposals still waiting for approvals. Proposals past their deadline are excluded.
+pub fn pending_proposals() -> Vec<LiquidationProposal> {
+    let now = current_timestamp();
+    read_state(|s| {
+        s.liquidation_proposals.values()
+            .filter(|p| p.status == ProposalStatus::Pending && p.request.deadline > now)
+            .cloned()
+            .collect()
+    })
+}
+
+fn current_timestamp() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
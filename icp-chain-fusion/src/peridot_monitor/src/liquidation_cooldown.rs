@@ -0,0 +1,54 @@
+use crate::enhanced_api::canonical_address;
+use crate::state::{mutate_state, read_state, State};
+
+/// How long a borrower is skipped after a successful liquidation, giving the
+/// position time to reflect it before another liquidator acts on stale data.
+pub const DEFAULT_LIQUIDATION_COOLDOWN_SECS: u64 = 5 * 60;
+
+/// Seconds left on the borrower's cooldown, or `None` if they may be liquidated.
+pub fn remaining(state: &State, borrower: &str, now: u64) -> Option<u64> {
+    let liquidated_at = state.liquidation_attempts.get(&canonical_address(borrower))?;
+    let ends_at = liquidated_at.saturating_add(state.liquidation_cooldown_secs);
+    (now < ends_at).then(|| ends_at - now)
+}
+
+/// Reject a liquidation of `borrower` while their cooldown is running.
+pub fn ensure_ready(borrower: &str, now: u64) -> Result<(), String> {
+    match read_state(|s| remaining(s, borrower, now)) {
+        Some(secs) => Err(format!(
+            "Borrower {} is in liquidation cooldown for another {}s",
+            borrower, secs
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Start the borrower's cooldown once a liquidation has succeeded. Failed
+/// attempts leave no cooldown, so they can be retried straight away. Expired
+/// entries are pruned on the way.
+pub fn record_liquidation(borrower: &str, now: u64) {
+    mutate_state(|s| {
+        let cooldown = s.liquidation_cooldown_secs;
+        s.liquidation_attempts.retain(|_, liquidated_at| liquidated_at.saturating_add(cooldown) > now);
+        s.liquidation_attempts.insert(canonical_address(borrower), now);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    #[test]
+    fn only_successful_liquidations_start_a_cooldown() {
+        init_test_state();
+        let borrower = "0x00000000000000000000000000000000000000AA";
+        let cooldown = read_state(|s| s.liquidation_cooldown_secs);
+
+        assert!(ensure_ready(borrower, 1_000).is_ok());
+        record_liquidation(borrower, 1_000);
+        // Lookups are by canonical address
+        assert!(ensure_ready(&borrower.to_lowercase(), 1_000 + cooldown - 1).is_err());
+        assert!(ensure_ready(borrower, 1_000 + cooldown).is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::state::{mutate_state, read_state};
+use crate::PeridotEvents;
+use alloy::rpc::types::Log;
+use alloy::sol_types::SolEvent;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// Observed liquidations kept for auditing; the oldest are dropped beyond this.
+pub const MAX_LIQUIDATION_EVENTS: usize = 5_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct LiquidationEvent {
+    pub chain_id: u64,
+    pub market: String, // pToken whose borrow was repaid
+    pub liquidator: String,
+    pub borrower: String,
+    pub repay_amount: String,
+    pub p_token_collateral: String,
+    pub seize_tokens: String,
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<String>,
+    #[serde(default)]
+    pub block_timestamp: Option<u64>, // seconds, when the RPC provider reports it
+    pub observed_at: u64, // seconds
+}
+
+impl LiquidationEvent {
+    /// When the liquidation happened: the block timestamp, or when we saw it.
+    pub fn event_time(&self) -> u64 {
+        self.block_timestamp.unwrap_or(self.observed_at)
+    }
+}
+
+/// Decode a `LiquidateBorrow(liquidator, borrower, repayAmount, pTokenCollateral, seizeTokens)` log.
+pub fn decode_liquidate_borrow(log: &Log, chain_id: u64, observed_at: u64) -> Result<LiquidationEvent, String> {
+    let decoded = PeridotEvents::LiquidateBorrow::decode_log(&log.inner, true)
+        .map_err(|e| format!("Failed to decode LiquidateBorrow log: {}", e))?;
+    let event = decoded.data;
+
+    Ok(LiquidationEvent {
+        chain_id,
+        market: log.address().to_string(),
+        liquidator: event.liquidator.to_string(),
+        borrower: event.borrower.to_string(),
+        repay_amount: event.repayAmount.to_string(),
+        p_token_collateral: event.pTokenCollateral.to_string(),
+        seize_tokens: event.seizeTokens.to_string(),
+        block_number: log.block_number,
+        transaction_hash: log.transaction_hash.map(|h| h.to_string()),
+        block_timestamp: log.block_timestamp,
+        observed_at,
+    })
+}
+
+pub fn record(event: LiquidationEvent) {
+    mutate_state(|s| {
+        s.liquidation_events.push_back(event);
+        while s.liquidation_events.len() > MAX_LIQUIDATION_EVENTS {
+            s.liquidation_events.pop_front();
+        }
+    });
+}
+
+/// Most recent liquidations first, optionally restricted to a chain and to events
+/// that happened at or after a start time (see `LiquidationEvent::event_time`).
+pub fn query(chain_id: Option<u64>, since_timestamp: Option<u64>, limit: usize) -> Vec<LiquidationEvent> {
+    read_state(|s| {
+        s.liquidation_events.iter()
+            .rev()
+            .filter(|e| chain_id.is_none_or(|id| e.chain_id == id))
+            .filter(|e| since_timestamp.is_none_or(|since| e.event_time() >= since))
+            .take(limit)
+            .cloned()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn liquidate_borrow_logs_decode_indexed_and_data_fields() {
+        let market = Address::repeat_byte(0x11);
+        let event = PeridotEvents::LiquidateBorrow {
+            liquidator: Address::repeat_byte(0x22),
+            borrower: Address::repeat_byte(0x33),
+            repayAmount: U256::from(1_500_000_000_000_000_000u128),
+            pTokenCollateral: Address::repeat_byte(0x44),
+            seizeTokens: U256::from(42u64),
+        };
+        let log = Log {
+            inner: alloy::primitives::Log { address: market, data: event.encode_log_data() },
+            block_number: Some(7),
+            ..Default::default()
+        };
+
+        let decoded = decode_liquidate_borrow(&log, 10143, 100).unwrap();
+        assert_eq!(decoded.market, market.to_string());
+        assert_eq!(decoded.borrower, Address::repeat_byte(0x33).to_string());
+        assert_eq!(decoded.p_token_collateral, Address::repeat_byte(0x44).to_string());
+        assert_eq!(decoded.repay_amount, "1500000000000000000");
+        assert_eq!(decoded.seize_tokens, "42");
+        assert_eq!(decoded.event_time(), 100);
+    }
+
+    #[test]
+    fn non_liquidation_logs_fail_to_decode() {
+        assert!(decode_liquidate_borrow(&Log::default(), 10143, 0).is_err());
+    }
+
+    #[test]
+    fn replayed_liquidations_are_windowed_by_when_they_happened() {
+        crate::state::init_test_state();
+        let liquidation_at = |block_timestamp: Option<u64>, observed_at: u64| {
+            let log = Log {
+                inner: alloy::primitives::Log {
+                    address: Address::repeat_byte(0x11),
+                    data: PeridotEvents::LiquidateBorrow {
+                        liquidator: Address::repeat_byte(0x22),
+                        borrower: Address::repeat_byte(0x33),
+                        repayAmount: U256::from(1u64),
+                        pTokenCollateral: Address::repeat_byte(0x44),
+                        seizeTokens: U256::from(1u64),
+                    }.encode_log_data(),
+                },
+                block_timestamp,
+                ..Default::default()
+            };
+            decode_liquidate_borrow(&log, 10143, observed_at).unwrap()
+        };
+        // A week-old liquidation ingested from a backlog just now, and a recent one
+        record(liquidation_at(Some(400_000), 1_000_000));
+        record(liquidation_at(Some(990_000), 1_000_000));
+        record(liquidation_at(None, 995_000));
+
+        let recent = query(None, Some(950_000), 10);
+        let times: Vec<u64> = recent.iter().map(LiquidationEvent::event_time).collect();
+        assert_eq!(times, vec![995_000, 990_000]);
+        assert_eq!(query(Some(10143), None, 10).len(), 3);
+        assert!(query(Some(97), None, 10).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use std::cell::Cell;
+
+thread_local! {
+    // Kept outside `State` so logging never re-borrows state from inside a
+    // `read_state`/`mutate_state` closure.
+    static ASCII_ONLY: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn ascii_only() -> bool {
+    ASCII_ONLY.with(Cell::get)
+}
+
+pub fn set_ascii_only(enabled: bool) {
+    ASCII_ONLY.with(|flag| flag.set(enabled));
+}
+
+/// Emit a canister log line, stripped of non-ASCII characters (emoji) when
+/// `ascii_only` is set so log ingestion pipelines don't choke on them.
+pub fn log(message: impl AsRef<str>) {
+    let message = message.as_ref();
+    if ascii_only() {
+        emit(&sanitize(message));
+    } else {
+        emit(message);
+    }
+}
+
+#[cfg(not(test))]
+fn emit(line: &str) {
+    ic_cdk::println!("{}", line);
+}
+
+// Unit tests run outside a canister, where the debug print system call is unavailable
+#[cfg(test)]
+fn emit(line: &str) {
+    println!("{}", line);
+}
+
+fn sanitize(message: &str) -> String {
+    let ascii: String = message.chars().filter(char::is_ascii).collect();
+    ascii.trim_start().to_string()
+}
@@ -4,7 +4,9 @@ use crate::SCRAPING_LOGS_INTERVAL;
 use crate::{
     guard::TimerGuard,
     job::job,
+    logger,
     state::{mutate_state, read_state, State, TaskType},
+    watchdog,
 };
 use alloy::rpc::types::Filter;
 use alloy::{eips::BlockNumberOrTag, providers::Provider};
@@ -16,13 +18,31 @@ async fn process_logs() {
         Err(_) => return,
     };
 
-    let logs_to_process = read_state(|s| (s.logs_to_process.clone()));
+    // Queued logs are keyed by transaction hash; apply them in chain order instead
+    let mut logs_to_process: Vec<_> = read_state(|s| s.logs_to_process.clone()).into_iter().collect();
+    logs_to_process.sort_by_key(|(_, log)| (log.block_number, log.log_index));
 
     for (event_source, event) in logs_to_process {
         job(event_source, event).await
     }
 }
 
+/// Queue polled logs for processing and return whether any are waiting. Logs
+/// polled while sync is paused for the chain are dropped, and the poll does not
+/// count as a successful sync.
+fn queue_polled_logs(incoming_logs: &[Log], now: u64) -> bool {
+    let chain_id = read_state(|s| s.chain_id);
+    if read_state(|s| s.sync_paused_chains.contains(&chain_id)) {
+        return false;
+    }
+    // Each delivered poll means the chain's RPC is answering
+    watchdog::record_sync_success_at(chain_id, now);
+    for log in incoming_logs {
+        mutate_state(|s| s.record_log_to_process(log));
+    }
+    read_state(State::has_logs_to_process)
+}
+
 pub async fn scrape_eth_logs() {
     let _guard = match TimerGuard::new(TaskType::ScrapeLogs) {
         Ok(guard) => guard,
@@ -32,15 +52,22 @@ pub async fn scrape_eth_logs() {
     let rpc_service = read_state(|s| s.rpc_service.clone());
     let config = IcpConfig::new(rpc_service).set_max_response_size(100_000);
     let provider = ProviderBuilder::new().on_icp(config);
-    let addresses = read_state(State::get_filter_addresses);
-    let events = read_state(State::get_filter_events);
+    // Markets, the Peridot contract and the controller, so controller events
+    // such as NewCollateralFactor are scraped alongside market events
+    let filter = read_state(|s| -> Result<_, String> {
+        Ok((s.monitored_contracts(s.chain_id)?, s.filter_topics(s.chain_id)?))
+    });
+    let (addresses, topics) = match filter {
+        Ok(filter) => filter,
+        Err(e) => {
+            logger::log(format!("Not scraping logs: {}", e));
+            return;
+        }
+    };
 
     // This callback will be called every time new logs are received
     let callback = |incoming_logs: Vec<Log>| {
-        for log in incoming_logs.iter() {
-            mutate_state(|s| s.record_log_to_process(log));
-        }
-        if read_state(State::has_logs_to_process) {
+        if queue_polled_logs(&incoming_logs, ic_cdk::api::time() / 1_000_000_000) {
             ic_cdk_timers::set_timer(
                 Duration::from_secs(0),
                 move || ic_cdk::spawn(process_logs()),
@@ -50,7 +77,7 @@ pub async fn scrape_eth_logs() {
 
     let filter = Filter::new()
         .address(addresses)
-        .events(events)
+        .event_signature(topics)
         .from_block(BlockNumberOrTag::Latest);
 
     // Initialize the poller and start watching
@@ -59,4 +86,32 @@ pub async fn scrape_eth_logs() {
         .with_poll_interval(SCRAPING_LOGS_INTERVAL)
         .start(callback)
         .unwrap();
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use alloy::primitives::B256;
+
+    fn polled_log(index: u64) -> Log {
+        Log {
+            transaction_hash: Some(B256::repeat_byte(0x01)),
+            log_index: Some(index),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn paused_chains_drop_polled_logs() {
+        init_test_state();
+        mutate_state(|s| s.sync_paused_chains.insert(10143));
+
+        assert!(!queue_polled_logs(&[polled_log(0)], 1_000));
+        assert!(read_state(|s| s.logs_to_process.is_empty() && !s.last_successful_sync.contains_key(&10143)));
+
+        mutate_state(|s| s.sync_paused_chains.remove(&10143));
+        assert!(queue_polled_logs(&[polled_log(1)], 1_000));
+        assert_eq!(read_state(|s| s.last_successful_sync.get(&10143).copied()), Some(1_000));
+    }
+}
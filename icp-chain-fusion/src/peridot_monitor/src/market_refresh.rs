@@ -0,0 +1,166 @@
+use crate::contracts::{IPeridotController, IPToken};
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::job::{self, saturating_u128, saturating_u64};
+use crate::logger;
+use crate::price_oracle;
+use crate::state::{market_key, mutate_state, read_state, MarketState, State};
+use crate::token_metadata;
+use alloy::primitives::Address;
+use alloy::providers::ProviderBuilder;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct MarketRefreshFailure {
+    pub market: String,
+    pub error: String,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct MarketRefreshReport {
+    pub chain_id: u64,
+    pub refreshed: Vec<String>,
+    pub failed: Vec<MarketRefreshFailure>,
+}
+
+/// `get_market_state` response: the original single-market fields, filled from
+/// the chain's latest market, with every tracked market listed alongside.
+#[derive(Serialize)]
+pub struct ChainMarketState<'a> {
+    #[serde(flatten)]
+    pub latest: &'a MarketState,
+    pub markets: Vec<&'a MarketState>,
+}
+
+pub fn chain_market_state(state: &State, chain_id: u64) -> Option<ChainMarketState<'_>> {
+    Some(ChainMarketState {
+        latest: state.latest_market(chain_id)?,
+        markets: state.chain_markets(chain_id).collect(),
+    })
+}
+
+/// On-chain view of one market, read in a single pass.
+struct MarketSnapshot {
+    supply_rate: u64,
+    borrow_rate: u64,
+    total_supply: u128,
+    total_borrows: u128,
+    cash: u128,
+    reserves: u128,
+    exchange_rate: u128,
+    borrow_index: u128,
+    accrual_block: u64,
+}
+
+/// Re-read every market listed by the chain's controller and overwrite the stored
+/// `MarketState`, refreshing its oracle price along the way. A market that fails to read keeps its previous state and is
+/// reported; the collateral factor is only updated from events.
+pub async fn refresh_all_markets(chain_id: u64) -> Result<MarketRefreshReport, String> {
+    let controller = read_state(|s| {
+        s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))
+            .and_then(|c| c.controller_address())
+    })?;
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let markets = IPeridotController::new(controller, provider).getAllMarkets().call().await
+        .map_err(|e| format!("getAllMarkets failed: {}", e))?._0;
+
+    let mut report = MarketRefreshReport {
+        chain_id,
+        refreshed: Vec::new(),
+        failed: Vec::new(),
+    };
+    for market in markets {
+        match read_market(chain_id, market).await {
+            Ok(snapshot) => {
+                let metadata = token_metadata::market_underlying_metadata(chain_id, market).await;
+                mutate_state(|s| {
+                    let state = s.market_states.entry(market_key(chain_id, &market.to_string()))
+                        .or_insert_with(|| job::new_market_state(chain_id, market));
+                    token_metadata::apply_metadata(state, metadata);
+                    state.supply_rate = snapshot.supply_rate;
+                    state.borrow_rate = snapshot.borrow_rate;
+                    state.total_supply = snapshot.total_supply;
+                    state.total_borrows = snapshot.total_borrows;
+                    state.cash = snapshot.cash;
+                    state.reserves = snapshot.reserves;
+                    state.exchange_rate = snapshot.exchange_rate;
+                    state.borrow_index = snapshot.borrow_index;
+                    state.last_accrual_block = snapshot.accrual_block;
+                    state.updated_at = ic_cdk::api::time();
+                });
+                // Feed the live price tier so revalued positions use current oracle prices
+                if let Err(e) = price_oracle::refresh_underlying_price(chain_id, market).await {
+                    logger::log(format!("Failed to refresh price of {} on chain {}: {}", market, chain_id, e));
+                }
+                report.refreshed.push(market.to_string());
+            }
+            Err(error) => {
+                logger::log(format!("Failed to refresh market {} on chain {}: {}", market, chain_id, error));
+                report.failed.push(MarketRefreshFailure { market: market.to_string(), error });
+            }
+        }
+    }
+
+    if !report.refreshed.is_empty() {
+        job::recompute_all_health_factors(chain_id);
+    }
+    Ok(report)
+}
+
+async fn read_market(chain_id: u64, market: Address) -> Result<MarketSnapshot, String> {
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let p_token = IPToken::new(market, provider);
+
+    Ok(MarketSnapshot {
+        supply_rate: saturating_u64(p_token.supplyRatePerBlock().call().await
+            .map_err(|e| format!("supplyRatePerBlock failed: {}", e))?._0),
+        borrow_rate: saturating_u64(p_token.borrowRatePerBlock().call().await
+            .map_err(|e| format!("borrowRatePerBlock failed: {}", e))?._0),
+        total_supply: saturating_u128(p_token.totalSupply().call().await
+            .map_err(|e| format!("totalSupply failed: {}", e))?._0),
+        total_borrows: saturating_u128(p_token.totalBorrows().call().await
+            .map_err(|e| format!("totalBorrows failed: {}", e))?._0),
+        cash: saturating_u128(p_token.getCash().call().await
+            .map_err(|e| format!("getCash failed: {}", e))?._0),
+        reserves: saturating_u128(p_token.totalReserves().call().await
+            .map_err(|e| format!("totalReserves failed: {}", e))?._0),
+        exchange_rate: saturating_u128(p_token.exchangeRateStored().call().await
+            .map_err(|e| format!("exchangeRateStored failed: {}", e))?._0),
+        borrow_index: saturating_u128(p_token.borrowIndex().call().await
+            .map_err(|e| format!("borrowIndex failed: {}", e))?._0),
+        accrual_block: saturating_u64(p_token.accrualBlockNumber().call().await
+            .map_err(|e| format!("accrualBlockNumber failed: {}", e))?._0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    #[test]
+    fn market_state_keeps_the_single_market_fields() {
+        init_test_state();
+        let mut older = job::new_market_state(10143, Address::repeat_byte(0x01));
+        older.total_supply = 1;
+        let mut latest = job::new_market_state(10143, Address::repeat_byte(0x02));
+        // Larger than u64::MAX
+        latest.total_supply = 20_000_000_000_000_000_000;
+        latest.updated_at = 5;
+        mutate_state(|s| {
+            s.market_states.insert(market_key(10143, &older.market_address), older);
+            s.market_states.insert(market_key(10143, &latest.market_address), latest);
+        });
+
+        let json = read_state(|s| serde_json::to_string(&chain_market_state(s, 10143)).unwrap());
+        assert!(json.starts_with(&format!("{{\"market_address\":\"{}\"", Address::repeat_byte(0x02))), "{}", json);
+        assert!(json.contains("\"total_supply\":20000000000000000000,"), "{}", json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["markets"].as_array().unwrap().len(), 2);
+        assert!(read_state(|s| chain_market_state(s, 97)).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+// Shared amount, rate and valuation conversions. Amounts are integer token units
+// (wei-style), rates are 1e18-scaled per-block mantissas as reported by pTokens.
+
+use alloy::primitives::U256;
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+const DAYS_PER_YEAR: i32 = 365;
+
+/// Scale an integer token amount down by `decimals`.
+pub fn wei_to_human(amount: u128, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// USD value of an integer token amount at `price_usd` per whole token.
+pub fn usd_value(amount: u128, decimals: u8, price_usd: f64) -> f64 {
+    wei_to_human(amount, decimals) * price_usd
+}
+
+/// Scale of a pToken's `exchangeRateStored` mantissa. The stored rate already folds
+/// in the difference between underlying and pToken decimals, so converting with it
+/// yields integer underlying units directly.
+const EXCHANGE_RATE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Underlying units redeemable for `ptoken_amount` at `exchange_rate`, rounding down
+/// as the market does.
+pub fn ptoken_to_underlying(ptoken_amount: U256, exchange_rate: U256) -> U256 {
+    ptoken_amount.saturating_mul(exchange_rate) / U256::from(EXCHANGE_RATE_SCALE)
+}
+
+/// pTokens minted for `underlying_amount` at `exchange_rate`; zero if the rate is unknown.
+pub fn underlying_to_ptoken(underlying_amount: U256, exchange_rate: U256) -> U256 {
+    if exchange_rate.is_zero() {
+        return U256::ZERO;
+    }
+    underlying_amount.saturating_mul(U256::from(EXCHANGE_RATE_SCALE)) / exchange_rate
+}
+
+/// Borrow balance grown from `stored_index` to `current_index`, the way the market
+/// computes `borrowBalanceCurrent`. Unknown indexes leave the balance unchanged.
+pub fn accrue_borrow(stored_balance: U256, current_index: U256, stored_index: U256) -> U256 {
+    if stored_index.is_zero() || current_index.is_zero() {
+        return stored_balance;
+    }
+    stored_balance.saturating_mul(current_index) / stored_index
+}
+
+/// Number of blocks produced per year at a given block time.
+pub fn blocks_per_year(block_time_ms: u64) -> f64 {
+    if block_time_ms == 0 {
+        return 0.0;
+    }
+    SECONDS_PER_YEAR * 1000.0 / block_time_ms as f64
+}
+
+/// Convert a 1e18-scaled per-block rate to an APY percentage, compounding daily.
+pub fn per_block_to_apy(rate_per_block: u64, blocks_per_year: f64) -> f64 {
+    if blocks_per_year <= 0.0 {
+        return 0.0;
+    }
+    let blocks_per_day = blocks_per_year / DAYS_PER_YEAR as f64;
+    let daily_rate = wei_to_human(rate_per_block as u128, 18) * blocks_per_day;
+    ((1.0 + daily_rate).powi(DAYS_PER_YEAR) - 1.0) * 100.0
+}
+
+/// Collateral over borrows. Positions without borrows report `f64::MAX` so the
+/// value stays finite when serialized to JSON.
+pub fn health_factor(collateral_usd: f64, borrow_usd: f64) -> f64 {
+    if borrow_usd > 0.0 {
+        collateral_usd / borrow_usd
+    } else {
+        f64::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn amounts_scale_by_decimals() {
+        assert_eq!(wei_to_human(1_500_000, 6), 1.5);
+        assert_eq!(wei_to_human(2 * ONE, 18), 2.0);
+        assert_eq!(usd_value(2_000_000, 6, 0.5), 1.0);
+    }
+
+    #[test]
+    fn ptoken_conversions_round_down_and_round_trip() {
+        // 0.02 underlying per pToken, as a fresh market reports
+        let rate = U256::from(ONE / 50);
+        let underlying = ptoken_to_underlying(U256::from(100 * ONE), rate);
+        assert_eq!(underlying, U256::from(2 * ONE));
+        assert_eq!(underlying_to_ptoken(underlying, rate), U256::from(100 * ONE));
+
+        assert_eq!(ptoken_to_underlying(U256::from(1u8), rate), U256::ZERO);
+        assert_eq!(underlying_to_ptoken(U256::from(ONE), U256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn borrows_accrue_with_the_index() {
+        let balance = U256::from(1_000u64);
+        let stored = U256::from(ONE);
+        let current = U256::from(ONE + ONE / 10);
+        assert_eq!(accrue_borrow(balance, current, stored), U256::from(1_100u64));
+        assert_eq!(accrue_borrow(balance, current, U256::ZERO), balance);
+    }
+
+    #[test]
+    fn per_block_rates_compound_to_apy() {
+        assert_eq!(blocks_per_year(0), 0.0);
+        assert_eq!(blocks_per_year(1000), SECONDS_PER_YEAR);
+        assert_eq!(per_block_to_apy(1_000_000_000, 0.0), 0.0);
+
+        // 1% per day compounded daily for a year
+        let blocks = 86_400.0 * DAYS_PER_YEAR as f64;
+        let rate = (ONE / 100 / 86_400) as u64;
+        let apy = per_block_to_apy(rate, blocks);
+        assert!((apy - 3_678.34).abs() < 1.0, "apy was {}", apy);
+    }
+
+    #[test]
+    fn health_factor_is_finite_without_borrows() {
+        assert_eq!(health_factor(150.0, 100.0), 1.5);
+        assert_eq!(health_factor(150.0, 0.0), f64::MAX);
+    }
+}
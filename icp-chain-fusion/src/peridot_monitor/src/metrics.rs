@@ -0,0 +1,163 @@
+use crate::state::{mutate_state, read_state};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Weight of the newest sample in the rolling confirmation-time average.
+const CONFIRMATION_TIME_SMOOTHING: f64 = 0.2;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, Default)]
+pub struct TxMetrics {
+    pub submitted: u64,
+    pub confirmed: u64,
+    pub failed: u64,
+    pub avg_confirmation_ms: f64,
+}
+
+impl TxMetrics {
+    pub fn success_rate(&self) -> f64 {
+        let settled = self.confirmed + self.failed;
+        if settled == 0 {
+            0.0
+        } else {
+            self.confirmed as f64 / settled as f64
+        }
+    }
+
+    fn record_confirmation(&mut self, elapsed_ms: u64) {
+        self.confirmed += 1;
+        self.avg_confirmation_ms = if self.confirmed == 1 {
+            elapsed_ms as f64
+        } else {
+            self.avg_confirmation_ms * (1.0 - CONFIRMATION_TIME_SMOOTHING)
+                + elapsed_ms as f64 * CONFIRMATION_TIME_SMOOTHING
+        };
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct TxMetricsEntry {
+    pub chain_id: u64,
+    pub action: String,
+    pub submitted: u64,
+    pub confirmed: u64,
+    pub failed: u64,
+    pub success_rate: f64,
+    pub avg_confirmation_ms: f64,
+}
+
+pub fn record_submitted(chain_id: u64, action: &str) {
+    mutate_state(|s| {
+        s.tx_metrics.entry((chain_id, action.to_string())).or_default().submitted += 1;
+    });
+}
+
+pub fn record_confirmed(chain_id: u64, action: &str, elapsed_ms: u64) {
+    mutate_state(|s| {
+        s.tx_metrics.entry((chain_id, action.to_string())).or_default().record_confirmation(elapsed_ms);
+    });
+}
+
+pub fn record_failed(chain_id: u64, action: &str) {
+    mutate_state(|s| {
+        s.tx_metrics.entry((chain_id, action.to_string())).or_default().failed += 1;
+    });
+}
+
+pub fn snapshot() -> Vec<TxMetricsEntry> {
+    read_state(|s| {
+        s.tx_metrics.iter()
+            .map(|((chain_id, action), m)| TxMetricsEntry {
+                chain_id: *chain_id,
+                action: action.clone(),
+                submitted: m.submitted,
+                confirmed: m.confirmed,
+                failed: m.failed,
+                success_rate: m.success_rate(),
+                avg_confirmation_ms: m.avg_confirmation_ms,
+            })
+            .collect()
+    })
+}
+
+pub fn record_event_processed(chain_id: u64) {
+    mutate_state(|s| *s.events_processed.entry(chain_id).or_default() += 1);
+}
+
+pub fn record_rpc_failure(chain_id: u64, provider: &str) {
+    mutate_state(|s| *s.rpc_failures.entry((chain_id, provider.to_string())).or_default() += 1);
+}
+
+/// Counters and gauges in the Prometheus text exposition format.
+pub fn prometheus_text() -> String {
+    let mut out = String::new();
+    read_state(|s| {
+        out.push_str("# HELP peridot_events_processed_total Peridot events applied, by chain.\n");
+        out.push_str("# TYPE peridot_events_processed_total counter\n");
+        for (chain_id, count) in &s.events_processed {
+            out.push_str(&format!("peridot_events_processed_total{{chain_id=\"{}\"}} {}\n", chain_id, count));
+        }
+
+        let mut by_status: BTreeMap<String, u64> = BTreeMap::new();
+        for record in s.cross_chain_requests.values() {
+            *by_status.entry(format!("{:?}", record.status)).or_default() += 1;
+        }
+        out.push_str("# HELP peridot_cross_chain_requests Cross-chain requests, by current status.\n");
+        out.push_str("# TYPE peridot_cross_chain_requests gauge\n");
+        for (status, count) in &by_status {
+            out.push_str(&format!("peridot_cross_chain_requests{{status=\"{}\"}} {}\n", status, count));
+        }
+
+        out.push_str("# HELP peridot_rpc_failures_total Failed RPC calls, by chain and provider host.\n");
+        out.push_str("# TYPE peridot_rpc_failures_total counter\n");
+        for ((chain_id, provider), count) in &s.rpc_failures {
+            out.push_str(&format!(
+                "peridot_rpc_failures_total{{chain_id=\"{}\",provider=\"{}\"}} {}\n",
+                chain_id, escape_label(provider), count
+            ));
+        }
+    });
+
+    out.push_str("# HELP peridot_cycle_balance Canister cycle balance.\n");
+    out.push_str("# TYPE peridot_cycle_balance gauge\n");
+    out.push_str(&format!("peridot_cycle_balance {}\n", ic_cdk::api::canister_balance128()));
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success_rate_counts_only_settled_transactions() {
+        let mut metrics = TxMetrics::default();
+        assert_eq!(metrics.success_rate(), 0.0);
+
+        metrics.submitted = 4;
+        metrics.record_confirmation(1_000);
+        metrics.record_confirmation(1_000);
+        metrics.record_confirmation(1_000);
+        metrics.failed = 1;
+        assert_eq!(metrics.success_rate(), 0.75);
+    }
+
+    #[test]
+    fn confirmation_time_is_smoothed_after_first_sample() {
+        let mut metrics = TxMetrics::default();
+        metrics.record_confirmation(1_000);
+        assert_eq!(metrics.avg_confirmation_ms, 1_000.0);
+
+        metrics.record_confirmation(2_000);
+        assert!((metrics.avg_confirmation_ms - 1_200.0).abs() < 1e-9);
+        assert_eq!(metrics.confirmed, 2);
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
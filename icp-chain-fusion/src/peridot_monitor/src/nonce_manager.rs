@@ -0,0 +1,87 @@
+use crate::logger;
+use crate::rpc_manager::call_with_fallback;
+use crate::state::{mutate_state, read_state};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How often tracked nonces are compared against the chain.
+pub const NONCE_RECONCILE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct NonceReconciliation {
+    pub chain_id: u64,
+    pub stored: Option<u64>,
+    pub on_chain: u64,
+    pub corrected: bool,
+}
+
+/// Compare the locally tracked nonce for `chain_id` with the account's pending
+/// transaction count and adopt the on-chain value when it is ahead, e.g. after
+/// a transaction was submitted from outside the canister. The nonce is never
+/// lowered: a send still in flight may have reserved a nonce the node has not
+/// seen yet, and reusing it would replace or collide with that transaction.
+pub async fn reconcile(chain_id: u64) -> Result<NonceReconciliation, String> {
+    let address = read_state(|s| s.canister_evm_address)
+        .ok_or("Canister EVM address not initialized")?;
+    let on_chain = call_with_fallback(chain_id, |service| async move {
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+        provider.get_transaction_count(address).pending().await.map_err(|e| e.to_string())
+    }).await?;
+
+    let (stored, corrected) = raise_nonce(chain_id, on_chain);
+    if corrected {
+        logger::log(format!(
+            "Nonce for chain {} resynced: stored {:?}, on-chain pending {}",
+            chain_id, stored, on_chain
+        ));
+    }
+
+    Ok(NonceReconciliation { chain_id, stored, on_chain, corrected })
+}
+
+/// Raise the tracked nonce to `on_chain` if it is behind. The stored value is
+/// read in the same update, so nonces reserved while the RPC call was in
+/// flight are respected.
+pub fn raise_nonce(chain_id: u64, on_chain: u64) -> (Option<u64>, bool) {
+    mutate_state(|s| {
+        let stored = s.nonces.get(&chain_id).copied();
+        let corrected = !matches!(stored, Some(nonce) if nonce >= on_chain);
+        if corrected {
+            s.nonces.insert(chain_id, on_chain);
+        }
+        (stored, corrected)
+    })
+}
+
+/// Reconcile every chain the canister currently tracks a nonce for.
+pub async fn reconcile_all() {
+    let chain_ids: Vec<u64> = read_state(|s| s.nonces.keys().copied().collect());
+    for chain_id in chain_ids {
+        if let Err(e) = reconcile(chain_id).await {
+            logger::log(format!("Nonce reconciliation for chain {} failed: {}", chain_id, e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    #[test]
+    fn reconciliation_only_raises_the_nonce() {
+        init_test_state();
+        mutate_state(|s| s.nonces.insert(10143, 7));
+
+        // Nonces 5 and 6 are reserved by sends the node has not seen yet
+        assert_eq!(raise_nonce(10143, 5), (Some(7), false));
+        assert_eq!(read_state(|s| s.nonces[&10143]), 7);
+
+        assert_eq!(raise_nonce(10143, 9), (Some(7), true));
+        assert_eq!(read_state(|s| s.nonces[&10143]), 9);
+        assert_eq!(raise_nonce(97, 3), (None, true));
+    }
+}
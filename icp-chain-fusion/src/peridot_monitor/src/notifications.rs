@@ -0,0 +1,85 @@
+use crate::logger;
+use crate::state::read_state;
+use candid::{CandidType, Nat, Principal};
+use ic_cdk::api::call::call_with_payment128;
+use ic_cdk::api::management_canister::http_request::{
+    HttpHeader, HttpMethod, HttpResponse, TransformArgs, TransformContext,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Webhook replies are discarded; only the status is kept so replicas agree.
+const WEBHOOK_MAX_RESPONSE_BYTES: u64 = 1_024;
+const WEBHOOK_OUTCALL_CYCLES: u128 = 2_000_000_000;
+
+/// Management canister `http_request` arguments including `is_replicated`, which
+/// `CanisterHttpRequestArgument` in this ic-cdk version does not expose.
+#[derive(CandidType, Deserialize, Debug, Clone)]
+struct WebhookRequest {
+    url: String,
+    max_response_bytes: Option<u64>,
+    method: HttpMethod,
+    headers: Vec<HttpHeader>,
+    body: Option<Vec<u8>>,
+    transform: Option<TransformContext>,
+    is_replicated: Option<bool>,
+}
+
+/// POST an alert to the configured webhook, if any. The outcall is made by a single
+/// replica so the receiver sees one POST per alert, and carries `idempotency_key` so
+/// a redelivered alert can be dropped. Delivery failures are logged and never
+/// propagate to the caller.
+pub async fn notify(kind: &str, idempotency_key: &str, message: &str) {
+    let Some(url) = read_state(|s| s.notification_webhook.clone()) else {
+        return;
+    };
+
+    let body = json!({
+        "kind": kind,
+        "message": message,
+        "canister_id": ic_cdk::id().to_text(),
+        "timestamp": ic_cdk::api::time() / 1_000_000_000,
+    });
+    let request = WebhookRequest {
+        url,
+        max_response_bytes: Some(WEBHOOK_MAX_RESPONSE_BYTES),
+        method: HttpMethod::POST,
+        headers: vec![
+            HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "application/json".to_string(),
+            },
+            HttpHeader {
+                name: "Idempotency-Key".to_string(),
+                value: idempotency_key.to_string(),
+            },
+        ],
+        body: Some(body.to_string().into_bytes()),
+        transform: Some(TransformContext::from_name("transform_webhook_response".to_string(), vec![])),
+        is_replicated: Some(false),
+    };
+
+    let result: Result<(HttpResponse,), _> = call_with_payment128(
+        Principal::management_canister(),
+        "http_request",
+        (request,),
+        WEBHOOK_OUTCALL_CYCLES,
+    )
+    .await;
+    match result {
+        Ok((response,)) => {
+            if response.status < Nat::from(200u32) || response.status >= Nat::from(300u32) {
+                logger::log(format!("Webhook for {} returned status {}", kind, response.status));
+            }
+        }
+        Err((code, msg)) => logger::log(format!("Webhook for {} rejected ({:?}): {}", kind, code, msg)),
+    }
+}
+
+pub fn transform_webhook_response(args: TransformArgs) -> HttpResponse {
+    HttpResponse {
+        status: args.response.status,
+        headers: Vec::new(),
+        body: Vec::new(),
+    }
+}
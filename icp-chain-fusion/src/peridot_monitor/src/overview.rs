@@ -0,0 +1,136 @@
+use crate::enhanced_api::{canonical_address, merge_position};
+use crate::job;
+use crate::state::{read_state, UserPosition};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+/// Positions below this health factor count as at risk, matching the
+/// near-liquidation cut-off of the enhanced liquidation feed.
+const AT_RISK_HEALTH_FACTOR: f64 = 1.2;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ChainOverview {
+    pub chain_id: u64,
+    pub name: String,
+    pub positions: u64,
+    pub tvl_usd: f64, // collateral value of tracked positions
+    pub borrow_usd: f64,
+    pub last_successful_sync: Option<u64>, // seconds
+    pub degraded: bool,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ProtocolOverview {
+    pub total_users: u64,
+    pub total_positions: u64,
+    pub total_collateral_usd: f64,
+    pub total_borrow_usd: f64,
+    pub at_risk_positions: u64,
+    pub chains: Vec<ChainOverview>,
+}
+
+/// Top-line figures for the whole deployment, taken from one consistent state read.
+pub fn protocol_overview() -> ProtocolOverview {
+    protocol_overview_at(ic_cdk::api::time() / 1_000_000_000)
+}
+
+fn protocol_overview_at(now: u64) -> ProtocolOverview {
+    read_state(|s| {
+        let empty_chain = |chain_id: u64, name: String| ChainOverview {
+            chain_id,
+            name,
+            positions: 0,
+            tvl_usd: 0.0,
+            borrow_usd: 0.0,
+            last_successful_sync: s.last_successful_sync.get(&chain_id).copied(),
+            degraded: s.sync_degraded.contains(&chain_id),
+        };
+        let mut chains: BTreeMap<u64, ChainOverview> = s.chain_configs.iter()
+            .map(|(chain_id, config)| (*chain_id, empty_chain(*chain_id, config.name.clone())))
+            .collect();
+
+        // Event processors have stored keys in different formats, so one user's
+        // position on a chain may be split across entries; fold them first
+        let mut positions: BTreeMap<(String, u64), UserPosition> = BTreeMap::new();
+        for ((user, chain_id), position) in &s.user_positions {
+            match positions.entry((canonical_address(user), *chain_id)) {
+                Entry::Occupied(mut existing) => merge_position(
+                    existing.get_mut(),
+                    position,
+                    &s.prices,
+                    &s.market_states,
+                    &job::collateral_factor(s, *chain_id),
+                    now,
+                ),
+                Entry::Vacant(slot) => {
+                    slot.insert(position.clone());
+                }
+            }
+        }
+
+        let mut at_risk_positions = 0;
+        for ((_, chain_id), position) in &positions {
+            if position.total_borrow_value_usd > 0.0 && position.health_factor < AT_RISK_HEALTH_FACTOR {
+                at_risk_positions += 1;
+            }
+            let chain = chains.entry(*chain_id)
+                .or_insert_with(|| empty_chain(*chain_id, format!("Chain {}", chain_id)));
+            chain.positions += 1;
+            chain.tvl_usd += position.total_collateral_value_usd;
+            chain.borrow_usd += position.total_borrow_value_usd;
+        }
+
+        let mut users: Vec<&String> = positions.keys().map(|(user, _)| user).collect();
+        users.dedup();
+        ProtocolOverview {
+            total_users: users.len() as u64,
+            total_positions: positions.len() as u64,
+            total_collateral_usd: chains.values().map(|c| c.tvl_usd).sum(),
+            total_borrow_usd: chains.values().map(|c| c.borrow_usd).sum(),
+            at_risk_positions,
+            chains: chains.into_values().collect(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{init_test_state, mutate_state};
+    use crate::watchdog;
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    fn seed(user: &str, chain_id: u64, collateral: f64, borrow: f64, health_factor: f64, updated_at: u64) {
+        let mut position = user_position(chain_id);
+        position.user_address = user.to_string();
+        position.total_collateral_value_usd = collateral;
+        position.total_borrow_value_usd = borrow;
+        position.health_factor = health_factor;
+        position.updated_at = updated_at;
+        mutate_state(|s| s.user_positions.insert((user.to_string(), chain_id), position));
+    }
+
+    #[test]
+    fn overview_counts_each_user_position_once() {
+        init_test_state();
+        seed("0x00000000000000000000000000000000000000aa", 10143, 100.0, 50.0, 1.6, 1);
+        seed("0x00000000000000000000000000000000000000bb", 10143, 40.0, 35.0, 1.1, 1);
+        seed("0x00000000000000000000000000000000000000aa", 97, 10.0, 0.0, f64::MAX, 1);
+        // The same user on Monad under a checksummed key
+        seed("0x00000000000000000000000000000000000000AA", 10143, 0.0, 0.0, f64::MAX, 2);
+        watchdog::record_sync_success_at(10143, 1_000);
+
+        let overview = protocol_overview_at(1_000);
+        assert_eq!((overview.total_users, overview.total_positions, overview.at_risk_positions), (2, 3, 1));
+        let monad = overview.chains.iter().find(|c| c.chain_id == 10143).unwrap();
+        assert_eq!(monad.positions, 2);
+        assert_eq!((monad.last_successful_sync, monad.degraded), (Some(1_000), false));
+        let bnb = overview.chains.iter().find(|c| c.chain_id == 97).unwrap();
+        assert_eq!((bnb.positions, bnb.tvl_usd, bnb.last_successful_sync), (1, 10.0, None));
+    }
+}
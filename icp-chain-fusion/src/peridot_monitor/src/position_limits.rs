@@ -0,0 +1,174 @@
+use crate::cross_chain_transactions::{CrossChainRequest, PeridotAction};
+use crate::enhanced_api::canonical_address;
+use crate::math;
+use crate::price_oracle::PriceSource;
+use crate::state::State;
+use crate::token_metadata;
+use alloy::primitives::Address;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Per-user exposure caps across all chains. `None` leaves that side uncapped.
+#[derive(CandidType, Deserialize, Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PositionLimits {
+    pub max_borrow_usd: Option<f64>,
+    pub max_collateral_usd: Option<f64>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct UserLimits {
+    pub user_address: String,
+    pub max_borrow_usd: Option<f64>,
+    pub max_collateral_usd: Option<f64>,
+    pub borrow_usd: f64,
+    pub collateral_usd: f64,
+    pub borrow_headroom_usd: Option<f64>,
+    pub collateral_headroom_usd: Option<f64>,
+}
+
+pub fn validate_cap(cap: Option<f64>) -> Result<(), String> {
+    match cap {
+        Some(usd) if !usd.is_finite() || usd <= 0.0 => {
+            Err(format!("Position cap must be a positive USD amount, got {}", usd))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The user's (collateral, borrow) USD totals summed over every chain.
+pub fn user_exposure(state: &State, user_address: &str) -> (f64, f64) {
+    let canonical = canonical_address(user_address);
+    state.user_positions.iter()
+        .filter(|((addr, _), _)| canonical_address(addr) == canonical)
+        .fold((0.0, 0.0), |(collateral, borrow), (_, position)| {
+            (collateral + position.total_collateral_value_usd, borrow + position.total_borrow_value_usd)
+        })
+}
+
+pub fn user_limits(state: &State, user_address: &str) -> UserLimits {
+    let (collateral_usd, borrow_usd) = user_exposure(state, user_address);
+    let limits = &state.position_limits;
+    UserLimits {
+        user_address: canonical_address(user_address),
+        max_borrow_usd: limits.max_borrow_usd,
+        max_collateral_usd: limits.max_collateral_usd,
+        borrow_usd,
+        collateral_usd,
+        borrow_headroom_usd: limits.max_borrow_usd.map(|cap| (cap - borrow_usd).max(0.0)),
+        collateral_headroom_usd: limits.max_collateral_usd.map(|cap| (cap - collateral_usd).max(0.0)),
+    }
+}
+
+/// USD value of the request amount in the underlying token's own decimals. The
+/// oracle prices pTokens, so the price is looked up under the target chain's
+/// market for `underlying`. A request whose market, decimals or price is not
+/// known cannot be checked against a cap and is rejected.
+fn requested_value_usd(state: &State, request: &CrossChainRequest, underlying: &str, now: u64) -> Result<f64, String> {
+    let amount = request.amount.parse::<u128>()
+        .map_err(|_| format!("Invalid amount: {}", request.amount))?;
+    let chain_id = request.target_chain_id;
+    let market = Address::from_str(underlying).ok()
+        .and_then(|underlying| token_metadata::cached_market_for(state, chain_id, underlying))
+        .ok_or_else(|| format!("No market for underlying {} on chain {} to price against the position cap", underlying, chain_id))?
+        .to_string();
+    let decimals = token_metadata::cached_decimals(state, chain_id, &market)
+        .ok_or_else(|| format!("Decimals of market {} on chain {} not loaded yet", market, chain_id))?;
+    let resolved = state.prices.resolve(&market, None, now);
+    if resolved.source == PriceSource::Unavailable {
+        return Err(format!("No price for market {} on chain {} to check the position cap", market, chain_id));
+    }
+    Ok(math::usd_value(amount, decimals, resolved.price_usd))
+}
+
+/// Reject a Supply or Borrow that would take the user past a configured cap.
+/// Other actions only reduce or move exposure and are not checked.
+pub fn check_request(state: &State, request: &CrossChainRequest, now: u64) -> Result<(), String> {
+    let limits = &state.position_limits;
+    let (cap, current, side, asset) = match &request.action {
+        PeridotAction::Supply { underlying_asset } => {
+            (limits.max_collateral_usd, user_exposure(state, &request.user_address).0, "collateral", underlying_asset)
+        }
+        PeridotAction::Borrow { underlying_asset, .. } => {
+            (limits.max_borrow_usd, user_exposure(state, &request.user_address).1, "borrow", underlying_asset)
+        }
+        _ => return Ok(()),
+    };
+    let Some(cap) = cap else {
+        return Ok(());
+    };
+
+    let requested = requested_value_usd(state, request, asset, now)?;
+    if current + requested > cap {
+        return Err(format!(
+            "Position limit exceeded: {} of ${:.2} plus ${:.2} requested exceeds the ${:.2} cap",
+            side, current, requested, cap
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{init_test_state, mutate_state, read_state};
+    use crate::token_metadata::TokenMetadata;
+
+    fn supply_request(amount: &str) -> CrossChainRequest {
+        CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    const NOW: u64 = 1_000;
+
+    fn list_usdc_market() -> Address {
+        let market = Address::repeat_byte(0x01);
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, market), TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some("0x00000000000000000000000000000000000000bb".to_string()),
+            });
+            s.position_limits.max_collateral_usd = Some(1_000.0);
+        });
+        market
+    }
+
+    #[test]
+    fn requests_are_valued_in_underlying_decimals_at_the_ptoken_price() {
+        init_test_state();
+        let market = list_usdc_market();
+        mutate_state(|s| s.prices.record_live(&market.to_string(), 1.0, NOW));
+
+        // 900 and 1,100 USDC at 6 decimals, against a $1,000 cap
+        read_state(|s| {
+            assert_eq!(requested_value_usd(s, &supply_request("900000000"), "0x00000000000000000000000000000000000000bb", NOW), Ok(900.0));
+            assert!(check_request(s, &supply_request("900000000"), NOW).is_ok());
+            let error = check_request(s, &supply_request("1100000000"), NOW).unwrap_err();
+            assert!(error.starts_with("Position limit exceeded"), "{}", error);
+        });
+    }
+
+    #[test]
+    fn unpriced_assets_are_rejected() {
+        init_test_state();
+        list_usdc_market();
+
+        let error = read_state(|s| check_request(s, &supply_request("1"), NOW)).unwrap_err();
+        assert!(error.starts_with("No price for market"), "{}", error);
+
+        mutate_state(|s| s.token_metadata.clear());
+        let error = read_state(|s| check_request(s, &supply_request("1"), NOW)).unwrap_err();
+        assert!(error.starts_with("No market for underlying"), "{}", error);
+    }
+}
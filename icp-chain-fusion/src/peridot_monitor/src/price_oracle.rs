@@ -0,0 +1,353 @@
+use crate::contracts::IPriceOracle;
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::logger;
+use crate::math;
+use crate::state::{market_key, mutate_state, read_state, MarketState, UserPosition};
+use crate::token_metadata;
+use alloy::primitives::{Address, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// How long a previously fetched oracle price may be reused when the oracle is unavailable.
+pub const DEFAULT_PRICE_CACHE_TTL_SECS: u64 = 15 * 60;
+/// Oldest oracle price a liquidation may act on.
+pub const DEFAULT_MAX_PRICE_STALENESS_SECS: u64 = 5 * 60;
+/// An oracle answer at most this old is served as live rather than cached.
+pub const LIVE_PRICE_WINDOW_SECS: u64 = 60;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct CachedPrice {
+    pub price_usd: f64,
+    pub updated_at: u64, // seconds
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum PriceSource {
+    Live,
+    Cached,
+    Fallback,
+    Unavailable,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ResolvedPrice {
+    pub asset: String,
+    pub price_usd: f64,
+    pub source: PriceSource,
+    pub stale: bool,
+}
+
+/// Oracle prices with the fallback chain used when the oracle is unavailable:
+/// live price -> cached price within TTL -> configured fallback -> unavailable.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct PriceBook {
+    pub cached: BTreeMap<String, CachedPrice>,
+    pub fallback: BTreeMap<String, f64>,
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for PriceBook {
+    fn default() -> Self {
+        Self {
+            cached: BTreeMap::new(),
+            fallback: BTreeMap::new(),
+            cache_ttl_secs: DEFAULT_PRICE_CACHE_TTL_SECS,
+        }
+    }
+}
+
+impl PriceBook {
+    /// Store a fresh oracle price so it can serve as the cached tier later.
+    pub fn record_live(&mut self, asset: &str, price_usd: f64, now: u64) {
+        if is_valid_price(price_usd) {
+            self.cached.insert(asset_key(asset), CachedPrice { price_usd, updated_at: now });
+        }
+    }
+
+    /// Seconds since the asset's last live oracle price, or `None` if it has never
+    /// been fetched. The oracle exposes no update time, so this is our fetch time.
+    pub fn price_age(&self, asset: &str, now: u64) -> Option<u64> {
+        self.cached.get(&asset_key(asset)).map(|cached| now.saturating_sub(cached.updated_at))
+    }
+
+    pub fn set_fallback(&mut self, asset: &str, price_usd: f64) {
+        self.fallback.insert(asset_key(asset), price_usd);
+    }
+
+    /// Resolve the best available price for an asset. `live` is the oracle's answer
+    /// for this call, or `None` if the oracle was not queried; an answer recorded
+    /// within `LIVE_PRICE_WINDOW_SECS` then still counts as live.
+    pub fn resolve(&self, asset: &str, live: Option<f64>, now: u64) -> ResolvedPrice {
+        let key = asset_key(asset);
+        let resolved = |price_usd: f64, source: PriceSource, stale: bool| ResolvedPrice {
+            asset: key.clone(),
+            price_usd,
+            source,
+            stale,
+        };
+
+        if let Some(price) = live.filter(|p| is_valid_price(*p)) {
+            return resolved(price, PriceSource::Live, false);
+        }
+
+        if let Some(cached) = self.cached.get(&key) {
+            let age = now.saturating_sub(cached.updated_at);
+            if age <= LIVE_PRICE_WINDOW_SECS {
+                return resolved(cached.price_usd, PriceSource::Live, false);
+            }
+            if age <= self.cache_ttl_secs {
+                return resolved(cached.price_usd, PriceSource::Cached, false);
+            }
+        }
+
+        if let Some(price) = self.fallback.get(&key) {
+            return resolved(*price, PriceSource::Fallback, true);
+        }
+
+        resolved(0.0, PriceSource::Unavailable, true)
+    }
+}
+
+/// Query the chain's configured price oracle for a pToken's underlying price and
+/// resolve it through the fallback chain, caching a successful live answer.
+pub async fn refresh_underlying_price(chain_id: u64, p_token: Address) -> Result<ResolvedPrice, String> {
+    let oracle_address = read_state(|s| {
+        s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))
+            .and_then(|c| c.price_oracle_address())
+    })?;
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let oracle = IPriceOracle::new(oracle_address, provider);
+
+    let asset = p_token.to_string();
+    // The mantissa cannot be read without the underlying's decimals, so an asset
+    // whose metadata has not loaded keeps its cached or fallback price
+    let live = match read_state(|s| token_metadata::cached_decimals(s, chain_id, &asset)) {
+        None => {
+            logger::log(format!("Skipping oracle price for {} on chain {}: underlying decimals not loaded", p_token, chain_id));
+            None
+        }
+        Some(decimals) => match oracle.getUnderlyingPrice(p_token).call().await {
+            Ok(result) => oracle_price_usd(result._0, decimals),
+            Err(e) => {
+                logger::log(format!("Oracle unavailable for {} on chain {}: {}", p_token, chain_id, e));
+                None
+            }
+        },
+    };
+
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    Ok(mutate_state(|s| {
+        if let Some(price) = live {
+            s.prices.record_live(&asset, price, now);
+        }
+        s.prices.resolve(&asset, live, now)
+    }))
+}
+
+/// USD price per whole underlying token from an oracle mantissa, which is scaled
+/// by `1e(36 - underlying_decimals)` so that it prices the smallest unit.
+fn oracle_price_usd(mantissa: U256, underlying_decimals: u8) -> Option<f64> {
+    let scale = 36u8.checked_sub(underlying_decimals)?;
+    u128::try_from(mantissa).ok().map(|mantissa| math::wei_to_human(mantissa, scale))
+}
+
+/// Fail with `PriceStale` if any pToken has no oracle price within
+/// `max_price_staleness_secs`, then refresh each one. Ages are checked before
+/// refreshing, since a refresh always looks fresh; the periodic market refresh
+/// is what must have kept them current. Fallback prices never satisfy this check.
+pub async fn ensure_fresh_prices(chain_id: u64, p_tokens: &[Address]) -> Result<(), String> {
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    read_state(|s| check_price_ages(&s.prices, p_tokens, s.max_price_staleness_secs, now))?;
+
+    for p_token in p_tokens {
+        refresh_underlying_price(chain_id, *p_token).await?;
+    }
+    Ok(())
+}
+
+fn check_price_ages(prices: &PriceBook, p_tokens: &[Address], max_age: u64, now: u64) -> Result<(), String> {
+    for p_token in p_tokens {
+        let asset = p_token.to_string();
+        match prices.price_age(&asset, now) {
+            Some(age) if age <= max_age => {}
+            Some(age) => {
+                return Err(format!("PriceStale: price for {} is {}s old (max {}s)", asset, age, max_age));
+            }
+            None => return Err(format!("PriceStale: no oracle price for {}", asset)),
+        }
+    }
+    Ok(())
+}
+
+/// Recompute a position's USD totals from its balances, flagging it as
+/// `price_stale` when any asset was valued with a fallback or missing price.
+/// Once the user has entered markets, only those count toward collateral.
+/// pToken balances are converted to underlying at the market's exchange rate.
+pub fn revalue_position(
+    position: &mut UserPosition,
+    prices: &PriceBook,
+    markets: &BTreeMap<(u64, String), MarketState>,
+    now: u64,
+) {
+    let mut stale = false;
+    let entered = &position.collateral_enabled;
+    let counts_as_collateral = |asset: &str| {
+        entered.is_empty() || entered.iter().any(|m| m.eq_ignore_ascii_case(asset))
+    };
+
+    let mut collateral = 0.0;
+    for (asset, amount) in position.p_token_balances.iter().filter(|(asset, _)| counts_as_collateral(asset)) {
+        let price = prices.resolve(asset, None, now);
+        stale |= price.stale;
+        // Without the market's exchange rate the pToken balance cannot be valued
+        let Some(market) = markets.get(&market_key(position.chain_id, asset)).filter(|m| m.exchange_rate > 0) else {
+            stale = true;
+            continue;
+        };
+        let underlying = math::ptoken_to_underlying(U256::from(*amount), U256::from(market.exchange_rate));
+        let underlying = u128::try_from(underlying).unwrap_or(u128::MAX);
+        collateral += math::usd_value(underlying, market.underlying_decimals, price.price_usd);
+    }
+
+    // Stored debt is grown to the market's current borrow index when both are known
+    let mut borrows = 0.0;
+    for (asset, amount) in &position.borrow_balances {
+        let price = prices.resolve(asset, None, now);
+        stale |= price.stale;
+        let Some(market) = markets.get(&market_key(position.chain_id, asset)) else {
+            stale = true;
+            continue;
+        };
+        let stored_index = position.borrow_indexes.iter()
+            .find(|(market, _)| market.eq_ignore_ascii_case(asset))
+            .map_or(0, |(_, index)| *index);
+        let balance = math::accrue_borrow(U256::from(*amount), U256::from(market.borrow_index), U256::from(stored_index));
+        borrows += math::usd_value(u128::try_from(balance).unwrap_or(u128::MAX), market.underlying_decimals, price.price_usd);
+    }
+
+    position.total_collateral_value_usd = collateral;
+    position.total_borrow_value_usd = borrows;
+    position.price_stale = stale;
+}
+
+fn asset_key(asset: &str) -> String {
+    asset.to_lowercase()
+}
+
+fn is_valid_price(price: f64) -> bool {
+    price.is_finite() && price > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    const MARKET: &str = "0x1111111111111111111111111111111111111111";
+
+    #[test]
+    fn resolve_prefers_live_then_cached_then_fallback() {
+        let mut book = PriceBook::default();
+        book.set_fallback(MARKET, 0.5);
+        assert_eq!(book.resolve(MARKET, None, 1_000).source, PriceSource::Fallback);
+
+        book.record_live(MARKET, 2.0, 1_000);
+        assert_eq!(book.resolve(MARKET, Some(3.0), 1_000).price_usd, 3.0);
+
+        let recent = book.resolve(MARKET, None, 1_000 + LIVE_PRICE_WINDOW_SECS);
+        assert_eq!((recent.source, recent.price_usd), (PriceSource::Live, 2.0));
+
+        let older = book.resolve(MARKET, None, 1_000 + LIVE_PRICE_WINDOW_SECS + 1);
+        assert_eq!(older.source, PriceSource::Cached);
+
+        let expired = book.resolve(MARKET, None, 1_000 + book.cache_ttl_secs + 1);
+        assert_eq!((expired.source, expired.stale), (PriceSource::Fallback, true));
+    }
+
+    #[test]
+    fn stale_prices_are_rejected_before_any_refresh() {
+        let fresh = Address::repeat_byte(0x11);
+        let stale = Address::repeat_byte(0x22);
+        let mut book = PriceBook::default();
+        book.record_live(&fresh.to_string(), 1.0, 1_000);
+        book.record_live(&stale.to_string(), 1.0, 600);
+        book.set_fallback(&Address::repeat_byte(0x33).to_string(), 1.0);
+
+        assert!(check_price_ages(&book, &[fresh], 300, 1_000).is_ok());
+        let err = check_price_ages(&book, &[fresh, stale], 300, 1_000).unwrap_err();
+        assert!(err.starts_with("PriceStale: price for"), "{}", err);
+        assert!(check_price_ages(&book, &[Address::repeat_byte(0x33)], 300, 1_000).unwrap_err().contains("no oracle price"));
+    }
+
+    fn market(address: &str, decimals: u8, exchange_rate: u128) -> MarketState {
+        MarketState {
+            underlying_decimals: decimals,
+            exchange_rate,
+            ..crate::job::new_market_state(10143, Address::from_str(address).unwrap())
+        }
+    }
+
+    fn value(decimals: u8, exchange_rate: u128, ptokens: u128) -> (f64, bool) {
+        let mut prices = PriceBook::default();
+        prices.record_live(MARKET, 2.0, 1_000);
+        let markets = BTreeMap::from([(market_key(10143, MARKET), market(MARKET, decimals, exchange_rate))]);
+        let mut position = user_position(10143);
+        position.p_token_balances = vec![(MARKET.to_string(), ptokens)];
+        revalue_position(&mut position, &prices, &markets, 1_000);
+        (position.total_collateral_value_usd, position.price_stale)
+    }
+
+    #[test]
+    fn collateral_is_valued_in_the_underlying_decimals() {
+        // 18-decimal underlying at 1:1 and at 1.5 underlying per pToken
+        assert_eq!(value(18, 1_000_000_000_000_000_000, 5_000_000_000_000_000_000), (10.0, false));
+        assert_eq!(value(18, 1_500_000_000_000_000_000, 5_000_000_000_000_000_000), (15.0, false));
+        // 6-decimal underlying behind an 8-decimal pToken: 1:1 is a 1e16 mantissa
+        assert_eq!(value(6, 10_000_000_000_000_000, 500_000_000), (10.0, false));
+        assert_eq!(value(6, 10_200_000_000_000_000, 50_000_000_000), (1_020.0, false));
+    }
+
+    #[test]
+    fn oracle_mantissas_are_scaled_by_the_underlying_decimals() {
+        let one_dollar = |decimals: u32| U256::from(10u128.pow(36 - decimals));
+        let close = |price: Option<f64>, expected: f64| price.is_some_and(|p| (p - expected).abs() < 1e-9);
+        assert!(close(oracle_price_usd(one_dollar(18), 18), 1.0));
+        assert!(close(oracle_price_usd(one_dollar(6) * U256::from(2), 6), 2.0));
+        assert_eq!(oracle_price_usd(U256::from(1u8), 37), None);
+
+        // 1,000 USDC of collateral is worth $1,000, not 1e12 times that
+        let usdc = "0x2222222222222222222222222222222222222222";
+        let mut prices = PriceBook::default();
+        prices.record_live(usdc, oracle_price_usd(one_dollar(6), 6).unwrap(), 1_000);
+        let markets = BTreeMap::from([(market_key(10143, usdc), market(usdc, 6, 10_000_000_000_000_000))]);
+        let mut position = user_position(10143);
+        position.p_token_balances = vec![(usdc.to_string(), 100_000_000_000)];
+        revalue_position(&mut position, &prices, &markets, 1_000);
+        assert!((position.total_collateral_value_usd - 1_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unloaded_markets_are_skipped_and_mark_the_position_stale() {
+        assert_eq!(value(18, 0, 5_000_000_000_000_000_000), (0.0, true));
+
+        let usdc = "0x2222222222222222222222222222222222222222";
+        let mut prices = PriceBook::default();
+        prices.record_live(usdc, 1.0, 1_000);
+        prices.record_live(MARKET, 1.0, 1_000);
+        let markets = BTreeMap::from([(market_key(10143, usdc), market(usdc, 6, 10_000_000_000_000_000))]);
+        let mut position = user_position(10143);
+        position.borrow_balances = vec![(usdc.to_string(), 250_000_000), (MARKET.to_string(), 1_000)];
+        revalue_position(&mut position, &prices, &markets, 1_000);
+        assert_eq!((position.total_borrow_value_usd, position.price_stale), (250.0, true));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::math;
+use crate::state::{read_state, MarketState, State};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Liquidity-mining rewards paid on a market, as APY percentages. Markets
+/// without an entry earn no incentive.
+#[derive(CandidType, Deserialize, Debug, Clone, Default, Serialize)]
+pub struct MarketIncentive {
+    pub supply_apy: f64,
+    pub borrow_apy: f64,
+}
+
+impl MarketIncentive {
+    pub fn validate(&self) -> Result<(), String> {
+        for (side, apy) in [("supply", self.supply_apy), ("borrow", self.borrow_apy)] {
+            if !apy.is_finite() || apy < 0.0 {
+                return Err(format!("Incentive {} APY must be a non-negative percentage, got {}", side, apy));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Percent APYs for one side of a market. Incentives add to what suppliers earn
+/// and subtract from what borrowers pay.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ApyBreakdown {
+    pub base_apy: f64,
+    pub incentive_apy: f64,
+    pub effective_apy: f64,
+}
+
+/// `get_cross_chain_rates` response: the original chain id -> supply rate map,
+/// taken from each chain's latest market, with per-market rates alongside.
+#[derive(Serialize)]
+pub struct CrossChainRates {
+    #[serde(flatten)]
+    pub supply_rates: BTreeMap<u64, u64>,
+    pub market_supply_rates: BTreeMap<u64, BTreeMap<String, u64>>,
+}
+
+pub fn cross_chain_rates(state: &State) -> CrossChainRates {
+    let mut market_supply_rates: BTreeMap<u64, BTreeMap<String, u64>> = BTreeMap::new();
+    for market in state.market_states.values() {
+        market_supply_rates.entry(market.chain_id)
+            .or_default()
+            .insert(market.market_address.clone(), market.supply_rate);
+    }
+    let supply_rates = market_supply_rates.keys()
+        .filter_map(|chain_id| state.latest_market(*chain_id).map(|market| (*chain_id, market.supply_rate)))
+        .collect();
+    CrossChainRates { supply_rates, market_supply_rates }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ChainAssetRate {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub market_address: String,
+    pub supply_apy: f64, // percent, before incentives
+    pub borrow_apy: f64, // percent, before incentives
+    pub supply: ApyBreakdown,
+    pub borrow: ApyBreakdown,
+    pub available_liquidity: f64, // underlying units
+    pub best_supply: bool,
+    pub best_borrow: bool,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct AssetRateComparison {
+    pub symbol: String,
+    pub chains: Vec<ChainAssetRate>,
+    pub best_supply_chain: Option<u64>,
+    pub best_borrow_chain: Option<u64>,
+}
+
+/// Compare supply/borrow APY and liquidity of one underlying asset across chains,
+/// flagging the highest effective supply APY and the lowest effective borrow APY.
+pub fn compare_asset_rates(symbol: &str) -> AssetRateComparison {
+    let mut chains: Vec<ChainAssetRate> = read_state(|s| {
+        s.market_states.values()
+            .filter(|m| m.underlying_symbol.eq_ignore_ascii_case(symbol))
+            .filter_map(|m| {
+                let config = s.chain_configs.get(&m.chain_id)?;
+                Some(chain_rate(m, &config.name, config.block_time_ms, &s.market_incentives))
+            })
+            .collect()
+    });
+
+    let best_supply_chain = chains.iter()
+        .max_by(|a, b| a.supply.effective_apy.total_cmp(&b.supply.effective_apy))
+        .map(|r| r.chain_id);
+    // Borrowing only makes sense where there is liquidity to borrow
+    let best_borrow_chain = chains.iter()
+        .filter(|r| r.available_liquidity > 0.0)
+        .min_by(|a, b| a.borrow.effective_apy.total_cmp(&b.borrow.effective_apy))
+        .map(|r| r.chain_id);
+
+    for rate in &mut chains {
+        rate.best_supply = Some(rate.chain_id) == best_supply_chain;
+        rate.best_borrow = Some(rate.chain_id) == best_borrow_chain;
+    }
+
+    AssetRateComparison {
+        symbol: symbol.to_uppercase(),
+        chains,
+        best_supply_chain,
+        best_borrow_chain,
+    }
+}
+
+pub(crate) fn chain_rate(
+    market: &MarketState,
+    chain_name: &str,
+    block_time_ms: u64,
+    incentives: &BTreeMap<String, MarketIncentive>,
+) -> ChainAssetRate {
+    let blocks_per_year = math::blocks_per_year(block_time_ms);
+    let incentive = incentives.get(&market.market_address.to_lowercase()).cloned().unwrap_or_default();
+    let supply_apy = math::per_block_to_apy(market.supply_rate, blocks_per_year);
+    let borrow_apy = math::per_block_to_apy(market.borrow_rate, blocks_per_year);
+    ChainAssetRate {
+        chain_id: market.chain_id,
+        chain_name: chain_name.to_string(),
+        market_address: market.market_address.clone(),
+        supply_apy,
+        borrow_apy,
+        supply: ApyBreakdown {
+            base_apy: supply_apy,
+            incentive_apy: incentive.supply_apy,
+            effective_apy: supply_apy + incentive.supply_apy,
+        },
+        borrow: ApyBreakdown {
+            base_apy: borrow_apy,
+            incentive_apy: incentive.borrow_apy,
+            effective_apy: borrow_apy - incentive.borrow_apy,
+        },
+        available_liquidity: math::wei_to_human(market.cash, market.underlying_decimals),
+        best_supply: false,
+        best_borrow: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::new_market_state;
+    use crate::state::{init_test_state, mutate_state};
+    use alloy::primitives::Address;
+
+    fn add_usdc_market(chain_id: u64, byte: u8, supply_rate: u64, borrow_rate: u64, cash: u128) {
+        let mut market = new_market_state(chain_id, Address::repeat_byte(byte));
+        market.underlying_symbol = "USDC".to_string();
+        market.underlying_decimals = 6;
+        market.supply_rate = supply_rate;
+        market.borrow_rate = borrow_rate;
+        market.cash = cash;
+        mutate_state(|s| s.market_states.insert((chain_id, market.market_address.clone()), market));
+    }
+
+    #[test]
+    fn best_chains_are_flagged_per_side() {
+        init_test_state();
+        // Monad pays more to suppliers but has no liquidity to borrow
+        add_usdc_market(10143, 0x01, 2_000_000_000, 1_000_000_000, 0);
+        add_usdc_market(97, 0x02, 1_000_000_000, 3_000_000_000, 5_000_000);
+
+        let comparison = compare_asset_rates("usdc");
+        assert_eq!(comparison.symbol, "USDC");
+        assert_eq!(comparison.chains.len(), 2);
+        assert_eq!(comparison.best_supply_chain, Some(10143));
+        assert_eq!(comparison.best_borrow_chain, Some(97));
+        let bnb = comparison.chains.iter().find(|r| r.chain_id == 97).unwrap();
+        assert!(bnb.best_borrow && !bnb.best_supply);
+        assert_eq!(bnb.available_liquidity, 5.0);
+    }
+
+    #[test]
+    fn incentives_raise_supply_and_lower_borrow_apy() {
+        let mut market = new_market_state(10143, Address::repeat_byte(0x01));
+        market.supply_rate = 1_000_000_000;
+        market.borrow_rate = 2_000_000_000;
+        let incentives = BTreeMap::from([(
+            market.market_address.to_lowercase(),
+            MarketIncentive { supply_apy: 1.5, borrow_apy: 0.5 },
+        )]);
+
+        let rate = chain_rate(&market, "Monad", 1000, &incentives);
+        assert_eq!(rate.supply.effective_apy, rate.supply.base_apy + 1.5);
+        assert_eq!(rate.borrow.effective_apy, rate.borrow.base_apy - 0.5);
+    }
+
+    #[test]
+    fn flat_apys_stay_the_base_rates_beside_the_breakdown() {
+        let mut market = new_market_state(10143, Address::repeat_byte(0x01));
+        market.supply_rate = 1_000_000_000;
+        market.borrow_rate = 2_000_000_000;
+        let incentives = BTreeMap::from([(
+            market.market_address.to_lowercase(),
+            MarketIncentive { supply_apy: 1.5, borrow_apy: 0.5 },
+        )]);
+
+        let rate = chain_rate(&market, "Monad", 1000, &incentives);
+        let unincentivized = chain_rate(&market, "Monad", 1000, &BTreeMap::new());
+        assert_eq!((rate.supply_apy, rate.borrow_apy), (unincentivized.supply_apy, unincentivized.borrow_apy));
+        assert_eq!((rate.supply_apy, rate.borrow_apy), (rate.supply.base_apy, rate.borrow.base_apy));
+        assert!(rate.supply_apy > 0.0 && rate.borrow_apy > rate.supply_apy);
+
+        let json = serde_json::to_value(&rate).unwrap();
+        assert_eq!(json["supply_apy"], rate.supply_apy);
+        assert_eq!(json["supply"]["effective_apy"], rate.supply_apy + 1.5);
+    }
+
+    #[test]
+    fn cross_chain_rates_keep_the_per_chain_map() {
+        init_test_state();
+        add_usdc_market(10143, 0x01, 100, 200, 0);
+        add_usdc_market(10143, 0x02, 300, 400, 0);
+        mutate_state(|s| s.market_states.get_mut(&(10143, Address::repeat_byte(0x02).to_string())).unwrap().updated_at = 5);
+
+        let json = serde_json::to_value(read_state(cross_chain_rates)).unwrap();
+        assert_eq!(json["10143"], 300);
+        assert_eq!(json["market_supply_rates"]["10143"][Address::repeat_byte(0x01).to_string()], 100);
+        assert_eq!(json["market_supply_rates"]["10143"].as_object().unwrap().len(), 2);
+    }
+}
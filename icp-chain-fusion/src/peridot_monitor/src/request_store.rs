@@ -0,0 +1,351 @@
+use crate::cross_chain_transactions::{CrossChainRequest, CrossChainResponse, TransactionStatus};
+use crate::source_proof::SourceProof;
+use crate::state::{mutate_state, read_state};
+use candid::{CandidType, Deserialize};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+
+// ===== CROSS-CHAIN REQUEST STORE =====
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct StatusTransition {
+    pub status: TransactionStatus,
+    pub timestamp: u64, // seconds
+    pub note: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct CrossChainRequestRecord {
+    pub request_id: String,
+    pub request: CrossChainRequest,
+    pub status: TransactionStatus,
+    pub timeline: Vec<StatusTransition>,
+    pub response: Option<CrossChainResponse>,
+    /// Completed execution steps and their JSON-encoded results, so a flow
+    /// interrupted by an upgrade resumes instead of repeating them.
+    pub checkpoints: BTreeMap<String, String>,
+    /// Bridge evidence of the source-chain deposit, kept for `verify_source_proof`.
+    #[serde(default)]
+    pub source_proof: Option<SourceProof>,
+    /// Step most recently started by `run_step`.
+    #[serde(default)]
+    pub current_step: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Progress of a request that has not reached a terminal status.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct ActiveRequest {
+    pub request_id: String,
+    pub action: String,
+    pub status: TransactionStatus,
+    pub current_step: Option<String>,
+    pub completed_steps: Vec<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Register a new request in `Pending` state. An id that is already taken is
+/// rejected rather than overwriting the existing record.
+pub fn create(request_id: &str, request: &CrossChainRequest) -> Result<(), String> {
+    create_at(request_id, request, current_timestamp())
+}
+
+pub(crate) fn create_at(request_id: &str, request: &CrossChainRequest, now: u64) -> Result<(), String> {
+    mutate_state(|s| {
+        if s.cross_chain_requests.contains_key(request_id) {
+            return Err(format!("Request id {} is already in use", request_id));
+        }
+        s.cross_chain_requests.insert(request_id.to_string(), CrossChainRequestRecord {
+            request_id: request_id.to_string(),
+            request: request.clone(),
+            status: TransactionStatus::Pending,
+            timeline: vec![StatusTransition {
+                status: TransactionStatus::Pending,
+                timestamp: now,
+                note: None,
+            }],
+            response: None,
+            checkpoints: BTreeMap::new(),
+            source_proof: None,
+            current_step: None,
+            created_at: now,
+            updated_at: now,
+        });
+        Ok(())
+    })
+}
+
+/// Move a request to `status`, appending the transition to its timeline.
+pub fn transition(request_id: &str, status: TransactionStatus, note: Option<String>) {
+    let now = current_timestamp();
+    mutate_state(|s| {
+        if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+            record.status = status.clone();
+            record.timeline.push(StatusTransition { status, timestamp: now, note });
+            record.updated_at = now;
+        }
+    });
+}
+
+pub fn record_source_proof(request_id: &str, proof: SourceProof) {
+    mutate_state(|s| {
+        if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+            record.source_proof = Some(proof);
+        }
+    });
+}
+
+/// Record the final outcome of a request, filling a successful response's
+/// `status_history` from the request timeline.
+pub fn finish(request_id: &str, result: &mut Result<CrossChainResponse, String>) {
+    match result {
+        Ok(response) => {
+            transition(request_id, TransactionStatus::Completed, None);
+            mutate_state(|s| {
+                if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+                    response.status_history = Some(record.timeline.iter()
+                        .map(|transition| (transition.status.clone(), transition.timestamp))
+                        .collect());
+                    record.response = Some(response.clone());
+                }
+            });
+        }
+        Err(e) => transition(request_id, TransactionStatus::Failed, Some(e.clone())),
+    }
+}
+
+/// Saved result of `step`, if it has completed.
+pub fn checkpoint<T: DeserializeOwned>(request_id: &str, step: &str) -> Option<T> {
+    read_state(|s| {
+        s.cross_chain_requests.get(request_id).and_then(|r| r.checkpoints.get(step).cloned())
+    })
+    .and_then(|saved| serde_json::from_str(&saved).ok())
+}
+
+/// Overwrite the saved result of `step`, for a completed step whose outcome was
+/// later superseded, such as a transaction replaced at a higher fee.
+pub fn replace_checkpoint<T: Serialize>(request_id: &str, step: &str, value: &T) -> Result<(), String> {
+    let encoded = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to checkpoint '{}' for {}: {}", step, request_id, e))?;
+    mutate_state(|s| {
+        if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+            record.checkpoints.insert(step.to_string(), encoded);
+        }
+    });
+    Ok(())
+}
+
+/// Run one execution step at most once per request. If `step` already has a
+/// checkpoint its saved result is returned and `action` is dropped unpolled;
+/// otherwise `action` runs and its result is checkpointed on success.
+pub async fn run_step<T, Fut>(request_id: &str, step: &str, action: Fut) -> Result<T, String>
+where
+    T: Serialize + DeserializeOwned,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let saved = read_state(|s| {
+        s.cross_chain_requests.get(request_id).and_then(|r| r.checkpoints.get(step).cloned())
+    });
+    if let Some(saved) = saved {
+        return serde_json::from_str(&saved)
+            .map_err(|e| format!("Corrupt checkpoint '{}' for {}: {}", step, request_id, e));
+    }
+
+    let now = current_timestamp();
+    mutate_state(|s| {
+        if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+            record.current_step = Some(step.to_string());
+            record.updated_at = now;
+        }
+    });
+
+    let value = action.await?;
+    let encoded = serde_json::to_string(&value)
+        .map_err(|e| format!("Failed to checkpoint '{}' for {}: {}", step, request_id, e))?;
+    mutate_state(|s| {
+        if let Some(record) = s.cross_chain_requests.get_mut(request_id) {
+            record.checkpoints.insert(step.to_string(), encoded);
+        }
+    });
+    Ok(value)
+}
+
+/// Run a safety check that protects `guarded_step`. Checks are never
+/// checkpointed, so a resumed request re-validates against current state; once
+/// `guarded_step` has completed the check has nothing left to protect and is skipped.
+pub async fn run_check<Fut>(request_id: &str, guarded_step: &str, check: Fut) -> Result<(), String>
+where
+    Fut: Future<Output = Result<(), String>>,
+{
+    let guarded_done = read_state(|s| {
+        s.cross_chain_requests.get(request_id).is_some_and(|r| r.checkpoints.contains_key(guarded_step))
+    });
+    if guarded_done {
+        return Ok(());
+    }
+    check.await
+}
+
+/// Requests that started but never reached `Completed` or `Failed`.
+pub fn in_flight() -> Vec<CrossChainRequestRecord> {
+    read_state(|s| {
+        s.cross_chain_requests.values()
+            .filter(|r| !matches!(r.status, TransactionStatus::Completed | TransactionStatus::Failed))
+            .cloned()
+            .collect()
+    })
+}
+
+/// In-flight requests, oldest first.
+pub fn active() -> Vec<ActiveRequest> {
+    let mut active: Vec<ActiveRequest> = in_flight()
+        .into_iter()
+        .map(|record| ActiveRequest {
+            action: record.request.action.kind().to_string(),
+            status: record.status,
+            current_step: record.current_step,
+            completed_steps: record.checkpoints.into_keys().collect(),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            request_id: record.request_id,
+        })
+        .collect();
+    active.sort_by(|a, b| (a.created_at, &a.request_id).cmp(&(b.created_at, &b.request_id)));
+    active
+}
+
+pub fn get(request_id: &str) -> Option<CrossChainRequestRecord> {
+    read_state(|s| s.cross_chain_requests.get(request_id).cloned())
+}
+
+#[cfg(not(test))]
+fn current_timestamp() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
+
+// Unit tests run outside a canister, where the time system call is unavailable
+#[cfg(test)]
+fn current_timestamp() -> u64 {
+    crate::test_support::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use crate::test_support::block_on;
+
+    fn supply_request(amount: &str) -> CrossChainRequest {
+        CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: crate::cross_chain_transactions::PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    fn completed_response(request_id: &str) -> CrossChainResponse {
+        CrossChainResponse {
+            request_id: request_id.to_string(),
+            status: TransactionStatus::Completed,
+            source_tx_hash: None,
+            target_tx_hash: None,
+            gas_used: None,
+            actual_amount: None,
+            error_message: None,
+            estimated_completion_time: None,
+            claim: None,
+            source_proof: None,
+            status_history: None,
+        }
+    }
+
+    #[test]
+    fn duplicate_request_ids_are_rejected() {
+        init_test_state();
+        create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+        let error = create_at("ccreq_1", &supply_request("200"), 20).unwrap_err();
+        assert!(error.contains("already in use"));
+
+        let record = get("ccreq_1").unwrap();
+        assert_eq!(record.request.amount, "100");
+        assert_eq!(record.created_at, 10);
+    }
+
+    #[test]
+    fn checks_rerun_until_the_step_they_guard_completes() {
+        init_test_state();
+        create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+
+        let failing = || async { Err::<(), _>("prices are stale".to_string()) };
+        assert!(block_on(run_check("ccreq_1", "liquidation_tx", failing())).is_err());
+        assert!(block_on(run_check("ccreq_1", "liquidation_tx", async { Ok(()) })).is_ok());
+        assert!(get("ccreq_1").unwrap().checkpoints.is_empty());
+
+        mutate_state(|s| {
+            let record = s.cross_chain_requests.get_mut("ccreq_1").unwrap();
+            record.checkpoints.insert("liquidation_tx".to_string(), "\"0xabc\"".to_string());
+        });
+        assert!(block_on(run_check("ccreq_1", "liquidation_tx", failing())).is_ok());
+    }
+
+    #[test]
+    fn finished_responses_carry_the_timeline_in_order() {
+        init_test_state();
+        create_at("ccreq_1", &supply_request("100"), 10).unwrap();
+        for (now, status) in [
+            (12, TransactionStatus::SourceChainProcessing),
+            (15, TransactionStatus::CrossChainBridging),
+            (40, TransactionStatus::TargetChainProcessing),
+        ] {
+            crate::test_support::set_now(now);
+            transition("ccreq_1", status, None);
+        }
+        crate::test_support::set_now(45);
+
+        let mut result = Ok(completed_response("ccreq_1"));
+        finish("ccreq_1", &mut result);
+
+        let expected = vec![
+            (TransactionStatus::Pending, 10),
+            (TransactionStatus::SourceChainProcessing, 12),
+            (TransactionStatus::CrossChainBridging, 15),
+            (TransactionStatus::TargetChainProcessing, 40),
+            (TransactionStatus::Completed, 45),
+        ];
+        assert_eq!(result.unwrap().status_history, Some(expected.clone()));
+        assert_eq!(get("ccreq_1").unwrap().response.unwrap().status_history, Some(expected));
+    }
+
+    #[test]
+    fn requests_are_active_until_they_finish() {
+        init_test_state();
+        create_at("ccreq_late", &supply_request("200"), 20).unwrap();
+        create_at("ccreq_early", &supply_request("100"), 10).unwrap();
+
+        crate::test_support::set_now(25);
+        block_on(run_step("ccreq_late", "approve_tx", async { Ok("0xabc".to_string()) })).unwrap();
+        transition("ccreq_late", TransactionStatus::TargetChainProcessing, None);
+
+        let listed = active();
+        assert_eq!(listed.iter().map(|r| r.request_id.as_str()).collect::<Vec<_>>(), vec!["ccreq_early", "ccreq_late"]);
+        let late = &listed[1];
+        assert_eq!((late.action.as_str(), late.status.clone()), ("Supply", TransactionStatus::TargetChainProcessing));
+        assert_eq!((late.current_step.as_deref(), late.completed_steps.clone()), (Some("approve_tx"), vec!["approve_tx".to_string()]));
+        assert_eq!((late.created_at, late.updated_at), (20, 25));
+
+        finish("ccreq_late", &mut Ok(completed_response("ccreq_late")));
+        finish("ccreq_early", &mut Err("reverted".to_string()));
+        assert!(active().is_empty());
+    }
+}
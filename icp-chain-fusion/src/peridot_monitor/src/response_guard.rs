@@ -0,0 +1,79 @@
+use crate::state::read_state;
+use serde::Serialize;
+
+/// Default serialized response budget, kept under the IC's ~2MB reply limit.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1_800_000;
+
+/// A collection response that may have been cut to fit the response budget.
+#[derive(Debug, Serialize)]
+pub struct Page<'a, T> {
+    pub items: &'a [T],
+    pub truncated: bool,
+    pub total_available: usize,
+}
+
+/// Serialize `items` as a plain JSON array, keeping as many leading items as fit
+/// the budget. List endpoints keep their array shape this way.
+pub fn list_json<T: Serialize>(items: &[T]) -> Result<String, String> {
+    fit_to_budget(items, |kept, _| {
+        serde_json::to_string(kept).map_err(|e| format!("Serialization error: {}", e))
+    })
+}
+
+/// Serialize `items` as a `Page`, keeping as many leading items as fit the budget.
+/// For endpoints that report truncation alongside the items.
+pub fn page_json<T: Serialize>(items: &[T]) -> Result<String, String> {
+    fit_to_budget(items, |kept, truncated| {
+        serde_json::to_string(&Page { items: kept, truncated, total_available: items.len() })
+            .map_err(|e| format!("Serialization error: {}", e))
+    })
+}
+
+/// Render the longest prefix of `items` whose output fits the configured budget.
+/// `render` receives the kept items and whether any were dropped.
+pub fn fit_to_budget<T, F>(items: &[T], render: F) -> Result<String, String>
+where
+    F: Fn(&[T], bool) -> Result<String, String>,
+{
+    let budget = read_state(|s| s.max_response_bytes);
+    let full = render(items, false)?;
+    if full.len() <= budget {
+        return Ok(full);
+    }
+
+    // Binary search for the largest prefix that fits
+    let (mut low, mut high) = (0, items.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if render(&items[..mid], true)?.len() <= budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    render(&items[..low], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{init_test_state, mutate_state};
+
+    #[test]
+    fn oversized_responses_keep_the_leading_items() {
+        init_test_state();
+        let items: Vec<u32> = (0..100).collect();
+        assert_eq!(list_json(&items).unwrap(), serde_json::to_string(&items).unwrap());
+
+        mutate_state(|s| s.max_response_bytes = 20);
+        let list: Vec<u32> = serde_json::from_str(&list_json(&items).unwrap()).unwrap();
+        assert_eq!(list, (0..list.len() as u32).collect::<Vec<_>>());
+        assert!(serde_json::to_string(&list).unwrap().len() <= 20);
+
+        mutate_state(|s| s.max_response_bytes = 60);
+        let page: serde_json::Value = serde_json::from_str(&page_json(&items).unwrap()).unwrap();
+        assert_eq!(page["truncated"], true);
+        assert_eq!(page["total_available"], 100);
+        assert!(page["items"].as_array().unwrap().len() < 100);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::cross_chain_transactions::{CrossChainRequest, CrossChainTransactionHandler, TransactionStatus};
+use crate::logger;
+use crate::request_store;
+use crate::state::{mutate_state, read_state};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How often due retries are picked up.
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// Attempts (including the original execution) before a request is dead-lettered.
+pub const DEFAULT_MAX_RETRY_ATTEMPTS: u32 = 5;
+/// Delay before the first retry; doubled after every further failure.
+const RETRY_BASE_DELAY_SECS: u64 = 60;
+const RETRY_MAX_DELAY_SECS: u64 = 3_600;
+/// Dead letters kept for inspection; the oldest are dropped beyond this.
+pub const MAX_DEAD_LETTERS: usize = 1_000;
+
+/// Error fragments from outcalls, RPC providers and signer start-up that are
+/// worth retrying. Reverts and validation errors are permanent. Receipt and
+/// confirmation errors are safe to retry because broadcast hashes are
+/// checkpointed, so a retry polls the same transaction instead of resending.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "All RPC providers failed",
+    "Failed to send",
+    "Failed to fetch",
+    "outcall",
+    "Signer not ready",
+    "timed out",
+    "was not mined",
+    "did not reach",
+    "Busy:",
+];
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct RetryEntry {
+    pub request_id: String,
+    pub request: CrossChainRequest,
+    pub attempts: u32,
+    pub next_attempt_at: u64, // seconds
+    pub last_error: String,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub request_id: String,
+    pub request: CrossChainRequest,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: u64,
+}
+
+pub fn is_transient(error: &str) -> bool {
+    TRANSIENT_ERRORS.iter().any(|fragment| error.contains(fragment))
+}
+
+/// Queue a failed request for retry, or dead-letter it if the error is permanent.
+pub fn record_failure(request_id: &str, request: &CrossChainRequest, error: &str, attempts: u32) {
+    record_failure_at(request_id, request, error, attempts, current_timestamp());
+}
+
+fn record_failure_at(request_id: &str, request: &CrossChainRequest, error: &str, attempts: u32, now: u64) {
+    let max_attempts = read_state(|s| s.max_retry_attempts);
+
+    if !is_transient(error) || attempts >= max_attempts || deadline_passed(request, now) {
+        logger::log(format!("Cross-chain request {} failed permanently after {} attempt(s): {}", request_id, attempts, error));
+        mutate_state(|s| {
+            s.retry_queue.remove(request_id);
+            if s.dead_letters.len() >= MAX_DEAD_LETTERS {
+                let excess = s.dead_letters.len() + 1 - MAX_DEAD_LETTERS;
+                s.dead_letters.drain(..excess);
+            }
+            s.dead_letters.push(DeadLetter {
+                request_id: request_id.to_string(),
+                request: request.clone(),
+                attempts,
+                error: error.to_string(),
+                failed_at: now,
+            });
+        });
+        return;
+    }
+
+    let delay = RETRY_BASE_DELAY_SECS
+        .saturating_mul(1u64 << attempts.saturating_sub(1).min(16))
+        .min(RETRY_MAX_DELAY_SECS);
+    logger::log(format!("Retrying cross-chain request {} in {}s (attempt {}): {}", request_id, delay, attempts + 1, error));
+    mutate_state(|s| {
+        s.retry_queue.insert(request_id.to_string(), RetryEntry {
+            request_id: request_id.to_string(),
+            request: request.clone(),
+            attempts,
+            next_attempt_at: now + delay,
+            last_error: error.to_string(),
+        });
+    });
+}
+
+/// Retry every queued request whose backoff has elapsed. Requests resume from
+/// their checkpoints, so completed steps are never executed twice.
+pub async fn process_due() {
+    let now = current_timestamp();
+    let due: Vec<RetryEntry> = read_state(|s| {
+        s.retry_queue.values().filter(|entry| entry.next_attempt_at <= now).cloned().collect()
+    });
+
+    for entry in due {
+        mutate_state(|s| s.retry_queue.remove(&entry.request_id));
+
+        // A request that reached Completed (e.g. via post-upgrade resume) must not run again
+        if request_store::get(&entry.request_id).is_some_and(|r| r.status == TransactionStatus::Completed) {
+            continue;
+        }
+        if deadline_passed(&entry.request, now) {
+            record_failure(&entry.request_id, &entry.request, "Deadline passed before retry", entry.attempts);
+            continue;
+        }
+
+        request_store::transition(&entry.request_id, TransactionStatus::Pending, Some(format!("retry {}", entry.attempts)));
+        let attempts = entry.attempts + 1;
+        if let Err(e) = CrossChainTransactionHandler::retry(entry.request.clone(), entry.request_id.clone()).await {
+            record_failure(&entry.request_id, &entry.request, &e, attempts);
+        }
+    }
+}
+
+pub fn dead_letters() -> Vec<DeadLetter> {
+    read_state(|s| s.dead_letters.clone())
+}
+
+/// A zero deadline means none was set.
+fn deadline_passed(request: &CrossChainRequest, now: u64) -> bool {
+    request.deadline != 0 && request.deadline < now
+}
+
+#[cfg(not(test))]
+fn current_timestamp() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
+
+// Unit tests run outside a canister, where the time system call is unavailable
+#[cfg(test)]
+fn current_timestamp() -> u64 {
+    crate::test_support::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    fn supply_request(amount: &str) -> CrossChainRequest {
+        CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: crate::cross_chain_transactions::PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    #[test]
+    fn unconfirmed_transactions_are_retried_and_reverts_are_not() {
+        init_test_state();
+        let request = supply_request("1000");
+
+        record_failure_at("pending", &request, "Monad supply transaction 0xab was not mined after 60 polls", 1, 1_000);
+        record_failure_at("reverted", &request, "Monad supply transaction 0xcd reverted", 1, 1_000);
+
+        read_state(|s| {
+            assert_eq!(s.retry_queue.get("pending").map(|e| e.next_attempt_at), Some(1_000 + RETRY_BASE_DELAY_SECS));
+            assert!(!s.retry_queue.contains_key("reverted"));
+            assert_eq!(s.dead_letters.iter().map(|d| d.request_id.as_str()).collect::<Vec<_>>(), vec!["reverted"]);
+        });
+    }
+
+    #[test]
+    fn dead_letters_drop_the_oldest_beyond_the_cap() {
+        init_test_state();
+        let request = supply_request("1000");
+
+        for i in 0..=MAX_DEAD_LETTERS {
+            record_failure_at(&format!("req-{}", i), &request, "reverted", 1, 1_000);
+        }
+
+        read_state(|s| {
+            assert_eq!(s.dead_letters.len(), MAX_DEAD_LETTERS);
+            assert_eq!(s.dead_letters.first().map(|d| d.request_id.as_str()), Some("req-1"));
+            assert_eq!(s.dead_letters.last().map(|d| d.request_id.clone()), Some(format!("req-{}", MAX_DEAD_LETTERS)));
+        });
+    }
+}
@@ -0,0 +1,199 @@
+use crate::contracts::{IPToken, IPeridotController};
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::state::{mutate_state, read_state};
+use alloy::primitives::{Address, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// Comptroller risk parameters rarely change; refetch them at most hourly.
+pub const RISK_PARAMETERS_TTL_SECS: u64 = 60 * 60;
+
+const MANTISSA_ONE: u128 = 1_000_000_000_000_000_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct RiskParameters {
+    pub close_factor_mantissa: u128,          // e.g. 0.5e18 = 50% of a borrow repayable at once
+    pub liquidation_incentive_mantissa: u128, // e.g. 1.08e18 = 8% bonus collateral
+    pub updated_at: u64,
+}
+
+impl RiskParameters {
+    pub fn close_factor(&self) -> f64 {
+        self.close_factor_mantissa as f64 / MANTISSA_ONE as f64
+    }
+
+    pub fn liquidation_incentive(&self) -> f64 {
+        self.liquidation_incentive_mantissa as f64 / MANTISSA_ONE as f64
+    }
+
+    /// Largest repay amount the comptroller accepts for a given borrow balance.
+    pub fn max_repay(&self, borrow_balance: U256) -> U256 {
+        borrow_balance * U256::from(self.close_factor_mantissa) / U256::from(MANTISSA_ONE)
+    }
+
+    /// USD value of the collateral seized for repaying `repay_value_usd`.
+    pub fn estimate_seize_value_usd(&self, repay_value_usd: f64) -> f64 {
+        repay_value_usd * self.liquidation_incentive()
+    }
+
+    /// Reject repays above `closeFactor × borrowBalance`, which the comptroller would revert.
+    pub fn check_repay(&self, repay_amount: U256, borrow_balance: U256) -> Result<(), String> {
+        let max_repay = self.max_repay(borrow_balance);
+        if repay_amount > max_repay {
+            return Err(format!(
+                "Repay amount {} exceeds close factor limit {} ({:.2}% of borrow balance {})",
+                repay_amount, max_repay, self.close_factor() * 100.0, borrow_balance
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Cached risk parameters for a chain, refreshed from the controller when missing or expired.
+pub async fn get_or_fetch(chain_id: u64) -> Result<RiskParameters, String> {
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    if let Some(cached) = read_state(|s| s.risk_parameters.get(&chain_id).cloned()) {
+        if now.saturating_sub(cached.updated_at) <= RISK_PARAMETERS_TTL_SECS {
+            return Ok(cached);
+        }
+    }
+
+    let controller = read_state(|s| {
+        s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))
+            .and_then(|c| c.controller_address())
+    })?;
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let comptroller = IPeridotController::new(controller, provider);
+
+    let close_factor = comptroller.closeFactorMantissa().call().await
+        .map_err(|e| format!("Failed to read closeFactorMantissa: {}", e))?._0;
+    let incentive = comptroller.liquidationIncentiveMantissa().call().await
+        .map_err(|e| format!("Failed to read liquidationIncentiveMantissa: {}", e))?._0;
+
+    let params = RiskParameters {
+        close_factor_mantissa: u128::try_from(close_factor)
+            .map_err(|_| format!("closeFactorMantissa out of range: {}", close_factor))?,
+        liquidation_incentive_mantissa: u128::try_from(incentive)
+            .map_err(|_| format!("liquidationIncentiveMantissa out of range: {}", incentive))?,
+        updated_at: now,
+    };
+    mutate_state(|s| s.risk_parameters.insert(chain_id, params.clone()));
+    Ok(params)
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct SeizeSimulation {
+    pub chain_id: u64,
+    pub borrower: String,
+    pub p_token_borrowed: String,
+    pub p_token_collateral: String,
+    pub repay_amount: String,
+    pub seize_tokens: String,
+    pub borrow_balance: String,
+    pub max_repay: String,
+    pub within_close_factor: bool,
+}
+
+/// Ask the controller how many collateral pTokens a liquidation repaying
+/// `repay_amount` would seize, exactly as `liquidateBorrow` computes it.
+pub async fn calculate_seize_tokens(
+    chain_id: u64,
+    p_token_borrowed: Address,
+    p_token_collateral: Address,
+    repay_amount: U256,
+) -> Result<U256, String> {
+    let controller = read_state(|s| {
+        s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))
+            .and_then(|c| c.controller_address())
+    })?;
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let comptroller = IPeridotController::new(controller, provider);
+
+    let result = comptroller.liquidateCalculateSeizeTokens(p_token_borrowed, p_token_collateral, repay_amount)
+        .call().await
+        .map_err(|e| format!("Failed to call liquidateCalculateSeizeTokens: {}", e))?;
+    seize_tokens_from(result)
+}
+
+/// `seizeTokens` from a `liquidateCalculateSeizeTokens` result, or the
+/// controller's non-zero error code.
+fn seize_tokens_from(result: IPeridotController::liquidateCalculateSeizeTokensReturn) -> Result<U256, String> {
+    if result._0 != U256::ZERO {
+        return Err(format!("Controller returned error code {} for seize calculation", result._0));
+    }
+    Ok(result._1)
+}
+
+/// Simulate a liquidation: seized collateral plus the close factor limit it must respect.
+pub async fn simulate_seize(
+    chain_id: u64,
+    borrower: Address,
+    p_token_borrowed: Address,
+    p_token_collateral: Address,
+    repay_amount: U256,
+) -> Result<SeizeSimulation, String> {
+    let seize_tokens = calculate_seize_tokens(chain_id, p_token_borrowed, p_token_collateral, repay_amount).await?;
+    let params = get_or_fetch(chain_id).await?;
+    let borrow_balance = read_borrow_balance(chain_id, p_token_borrowed, borrower).await?;
+    let max_repay = params.max_repay(borrow_balance);
+
+    Ok(SeizeSimulation {
+        chain_id,
+        borrower: borrower.to_string(),
+        p_token_borrowed: p_token_borrowed.to_string(),
+        p_token_collateral: p_token_collateral.to_string(),
+        repay_amount: repay_amount.to_string(),
+        seize_tokens: seize_tokens.to_string(),
+        borrow_balance: borrow_balance.to_string(),
+        max_repay: max_repay.to_string(),
+        within_close_factor: repay_amount <= max_repay,
+    })
+}
+
+/// Read a borrower's stored borrow balance from a pToken market.
+pub async fn read_borrow_balance(chain_id: u64, market: Address, borrower: Address) -> Result<U256, String> {
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let p_token = IPToken::new(market, provider);
+
+    p_token.borrowBalanceStored(borrower).call().await
+        .map(|result| result._0)
+        .map_err(|e| format!("Failed to read borrow balance for {}: {}", borrower, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::sol_types::{SolCall, SolValue};
+
+    fn controller_returns(error: u64, seize_tokens: u64) -> IPeridotController::liquidateCalculateSeizeTokensReturn {
+        let data = (U256::from(error), U256::from(seize_tokens)).abi_encode_params();
+        IPeridotController::liquidateCalculateSeizeTokensCall::abi_decode_returns(&data, true).unwrap()
+    }
+
+    #[test]
+    fn seize_tokens_come_from_the_controller_result() {
+        assert_eq!(seize_tokens_from(controller_returns(0, 4_200)).unwrap(), U256::from(4_200u64));
+        let error = seize_tokens_from(controller_returns(9, 0)).unwrap_err();
+        assert!(error.contains("error code 9"));
+    }
+
+    #[test]
+    fn repays_are_capped_by_the_close_factor() {
+        let params = RiskParameters {
+            close_factor_mantissa: MANTISSA_ONE / 2,
+            liquidation_incentive_mantissa: MANTISSA_ONE + MANTISSA_ONE / 12,
+            updated_at: 0,
+        };
+        let balance = U256::from(1_000u64);
+        assert_eq!(params.max_repay(balance), U256::from(500u64));
+        assert!(params.check_repay(U256::from(500u64), balance).is_ok());
+        assert!(params.check_repay(U256::from(501u64), balance).is_err());
+    }
+}
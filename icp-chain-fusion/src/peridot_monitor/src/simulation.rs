@@ -0,0 +1,247 @@
+use crate::cross_chain_transactions::PeridotAction;
+use crate::enhanced_api::canonical_address;
+use crate::job;
+use crate::math;
+use crate::state::{market_key, read_state, State, UserPosition};
+use crate::token_metadata;
+use alloy::primitives::{Address, U256};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Projected effect of a hypothetical action on a user's position.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct HealthSimulation {
+    pub user_address: String,
+    pub chain_id: u64,
+    pub current_health_factor: f64,
+    pub projected_health_factor: f64,
+    pub projected_collateral_usd: f64,
+    pub projected_borrow_usd: f64,
+    /// Risk-adjusted borrow value in excess of collateral; zero when the position stays solvent.
+    pub shortfall_usd: f64,
+    /// True when the comptroller would reject the action for insufficient liquidity.
+    pub would_be_rejected: bool,
+    pub price_stale: bool,
+}
+
+/// Apply `action` to a copy of the user's position and value it with the same
+/// health math and prices used for tracked positions. State is never modified.
+pub fn simulate_action_health(user: &str, chain_id: u64, action: &PeridotAction, amount: &str) -> Result<HealthSimulation, String> {
+    let amount: u128 = match action {
+        PeridotAction::EnableCollateral { .. } | PeridotAction::DisableCollateral { .. } => 0,
+        _ => amount.parse().map_err(|_| format!("Invalid amount '{}'", amount))?,
+    };
+
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    read_state(|s| simulate_in(s, user, chain_id, action, amount, now))
+}
+
+fn simulate_in(
+    s: &State,
+    user: &str,
+    chain_id: u64,
+    action: &PeridotAction,
+    amount: u128,
+    now: u64,
+) -> Result<HealthSimulation, String> {
+    let canonical = canonical_address(user);
+    let mut position = s.user_positions.iter()
+        .find(|((key, chain), _)| *chain == chain_id && canonical_address(key) == canonical)
+        .map(|(_, position)| position.clone())
+        .unwrap_or_else(|| job::new_position(&canonical, chain_id));
+    let collateral_factor = job::collateral_factor(s, chain_id);
+
+    job::calculate_health_factor_at(&mut position, &s.prices, &s.market_states, &collateral_factor, now);
+    let current_health_factor = position.health_factor;
+
+    // Actions that reduce liquidity are checked by the comptroller; the rest always pass
+    let checks_liquidity = match action {
+        PeridotAction::Supply { underlying_asset } => {
+            let market = market_for(s, chain_id, underlying_asset)?;
+            let minted = to_p_tokens(s, chain_id, &market, amount);
+            adjust(&mut position.p_token_balances, &market, minted, true);
+            false
+        }
+        PeridotAction::RepayBorrow { underlying_asset } => {
+            let market = market_for(s, chain_id, underlying_asset)?;
+            adjust_borrow(s, &mut position, &market, amount, false);
+            false
+        }
+        PeridotAction::Borrow { underlying_asset, .. } => {
+            let market = market_for(s, chain_id, underlying_asset)?;
+            adjust_borrow(s, &mut position, &market, amount, true);
+            true
+        }
+        PeridotAction::RedeemUnderlying { underlying_asset, .. } => {
+            let market = market_for(s, chain_id, underlying_asset)?;
+            let burned = to_p_tokens(s, chain_id, &market, amount);
+            adjust(&mut position.p_token_balances, &market, burned, false);
+            true
+        }
+        PeridotAction::EnableCollateral { p_token } => {
+            if !position.collateral_enabled.iter().any(|m| m.eq_ignore_ascii_case(p_token)) {
+                position.collateral_enabled.push(p_token.clone());
+            }
+            false
+        }
+        PeridotAction::DisableCollateral { p_token } => {
+            position.collateral_enabled.retain(|m| !m.eq_ignore_ascii_case(p_token));
+            true
+        }
+        PeridotAction::Redeem { .. } | PeridotAction::LiquidateBorrow { .. } => {
+            return Err("Simulation supports supply, borrow, repay, redeem-underlying and collateral toggles".to_string());
+        }
+    };
+
+    job::calculate_health_factor_at(&mut position, &s.prices, &s.market_states, &collateral_factor, now);
+    let shortfall_usd = (position.total_borrow_value_usd
+        - position.total_collateral_value_usd * collateral_factor.value).max(0.0);
+
+    Ok(HealthSimulation {
+        user_address: canonical,
+        chain_id,
+        current_health_factor,
+        projected_health_factor: position.health_factor,
+        projected_collateral_usd: position.total_collateral_value_usd,
+        projected_borrow_usd: position.total_borrow_value_usd,
+        shortfall_usd,
+        would_be_rejected: checks_liquidity && shortfall_usd > 0.0,
+        price_stale: position.price_stale,
+    })
+}
+
+/// The pToken market for `underlying_asset`. Position balances are keyed by
+/// market, so simulated amounts must land on the market's entry.
+fn market_for(state: &State, chain_id: u64, underlying_asset: &str) -> Result<String, String> {
+    let underlying = Address::from_str(underlying_asset)
+        .map_err(|_| format!("Invalid underlying asset '{}'", underlying_asset))?;
+    token_metadata::cached_market_for(state, chain_id, underlying)
+        .map(|market| market.to_string())
+        .ok_or_else(|| format!("No known market for underlying {} on chain {}", underlying_asset, chain_id))
+}
+
+/// pTokens corresponding to an underlying amount in the market, or the amount
+/// itself while the market's exchange rate is unknown (matching how unloaded
+/// markets are valued).
+fn to_p_tokens(state: &State, chain_id: u64, market: &str, underlying_amount: u128) -> u128 {
+    match state.market_states.get(&market_key(chain_id, market)) {
+        Some(m) if m.exchange_rate > 0 => job::saturating_u128(math::underlying_to_ptoken(
+            U256::from(underlying_amount),
+            U256::from(m.exchange_rate),
+        )),
+        _ => underlying_amount,
+    }
+}
+
+/// Move the borrow balance in `market` by `amount`. As the market does on every
+/// borrow and repay, the balance is first accrued to the current borrow index and
+/// re-stamped with it, so interest accrued earlier does not scale `amount`.
+fn adjust_borrow(state: &State, position: &mut UserPosition, market: &str, amount: u128, increase: bool) {
+    let current_index = state.market_states.get(&market_key(position.chain_id, market)).map_or(0, |m| m.borrow_index);
+    let stored_index = position.borrow_indexes.iter()
+        .find(|(m, _)| m.eq_ignore_ascii_case(market))
+        .map_or(0, |(_, index)| *index);
+    if let Some((_, balance)) = position.borrow_balances.iter_mut().find(|(m, _)| m.eq_ignore_ascii_case(market)) {
+        *balance = job::saturating_u128(math::accrue_borrow(U256::from(*balance), U256::from(current_index), U256::from(stored_index)));
+    }
+    adjust(&mut position.borrow_balances, market, amount, increase);
+
+    if current_index == 0 {
+        return;
+    }
+    match position.borrow_indexes.iter_mut().find(|(m, _)| m.eq_ignore_ascii_case(market)) {
+        Some((_, index)) => *index = current_index,
+        None => position.borrow_indexes.push((market.to_string(), current_index)),
+    }
+}
+
+/// Add `amount` to (or, when `increase` is false, take it from) the balance held
+/// in `asset`, flooring at zero.
+fn adjust(balances: &mut Vec<(String, u128)>, asset: &str, amount: u128, increase: bool) {
+    match balances.iter_mut().find(|(a, _)| a.eq_ignore_ascii_case(asset)) {
+        Some((_, balance)) if increase => *balance = balance.saturating_add(amount),
+        Some((_, balance)) => *balance = balance.saturating_sub(amount),
+        None if increase && amount > 0 => balances.push((asset.to_string(), amount)),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{init_test_state, mutate_state};
+    use crate::token_metadata::TokenMetadata;
+
+    fn user_position(chain_id: u64) -> UserPosition {
+        crate::job::new_position("0x00000000000000000000000000000000000000aa", chain_id)
+    }
+
+    #[test]
+    fn amounts_beyond_u64_land_on_the_market_balance() {
+        init_test_state();
+        let market = Address::repeat_byte(0x11);
+        let underlying = Address::repeat_byte(0x22);
+        let user = "0x00000000000000000000000000000000000000aa";
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, market), TokenMetadata {
+                symbol: "WETH".to_string(),
+                decimals: 18,
+                underlying_address: Some(underlying.to_string()),
+            });
+            let mut state = job::new_market_state(10143, market);
+            state.exchange_rate = 1_000_000_000_000_000_000;
+            s.market_states.insert(market_key(10143, &market.to_string()), state);
+            s.prices.record_live(&market.to_string(), 1.0, 1_000);
+            let mut position = user_position(10143);
+            position.p_token_balances.push((market.to_string(), 100_000_000_000_000_000_000));
+            s.user_positions.insert((canonical_address(user), 10143), position);
+        });
+
+        // 20e18 does not fit in a u64
+        let borrow = PeridotAction::Borrow { underlying_asset: underlying.to_string(), recipient_address: None };
+        let simulation = read_state(|s| simulate_in(s, user, 10143, &borrow, 20_000_000_000_000_000_000, 1_000)).unwrap();
+        assert_eq!(simulation.projected_borrow_usd, 20.0);
+        assert_eq!(simulation.projected_collateral_usd, 100.0);
+
+        let unknown = PeridotAction::Borrow { underlying_asset: Address::repeat_byte(0x33).to_string(), recipient_address: None };
+        assert!(read_state(|s| simulate_in(s, user, 10143, &unknown, 1, 1_000)).is_err());
+    }
+
+    #[test]
+    fn simulated_debt_is_accrued_before_the_amount_moves() {
+        init_test_state();
+        let market = Address::repeat_byte(0x11);
+        let usdc = Address::repeat_byte(0x22);
+        let user = "0x00000000000000000000000000000000000000aa";
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, market), TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some(usdc.to_string()),
+            });
+            let mut state = job::new_market_state(10143, market);
+            state.underlying_decimals = 6;
+            state.borrow_index = 1_100_000_000_000_000_000;
+            s.market_states.insert(market_key(10143, &market.to_string()), state);
+            s.prices.record_live(&market.to_string(), 1.0, 1_000);
+            // 100 USDC borrowed at index 1.0, now owed as 110 USDC
+            let mut position = user_position(10143);
+            position.borrow_balances.push((market.to_string(), 100_000_000));
+            position.borrow_indexes.push((market.to_string(), 1_000_000_000_000_000_000));
+            s.user_positions.insert((canonical_address(user), 10143), position);
+        });
+
+        let borrow = PeridotAction::Borrow { underlying_asset: usdc.to_string(), recipient_address: None };
+        let simulation = read_state(|s| simulate_in(s, user, 10143, &borrow, 10_000_000, 1_000)).unwrap();
+        assert_eq!(simulation.projected_borrow_usd, 120.0);
+
+        let repay = PeridotAction::RepayBorrow { underlying_asset: usdc.to_string() };
+        let simulation = read_state(|s| simulate_in(s, user, 10143, &repay, 10_000_000, 1_000)).unwrap();
+        assert_eq!(simulation.projected_borrow_usd, 100.0);
+
+        let mut position = user_position(10143);
+        read_state(|s| adjust_borrow(s, &mut position, &market.to_string(), 5_000_000, true));
+        assert_eq!(position.borrow_indexes, vec![(market.to_string(), 1_100_000_000_000_000_000)]);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::cross_chain_transactions::{transferred_to, CrossChainRequest, CrossChainTransactionHandler};
+use crate::request_store;
+use crate::rpc_manager::call_with_fallback;
+use crate::state::read_state;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::Log;
+use alloy::sol_types::SolValue;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Evidence of the source-chain deposit behind a request, as reported by the
+/// bridge. `proof` is `abi.encode(address recipient, address asset, uint256 amount)`:
+/// who the deposit paid, in which token (the zero address for the native token),
+/// and how much. Verification checks that claim against the request and against
+/// the deposit transaction on the source chain.
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct SourceProof {
+    pub chain_id: u64,
+    pub tx_hash: String,
+    pub proof: Vec<u8>,
+}
+
+impl SourceProof {
+    pub fn deposit(chain_id: u64, tx_hash: B256, recipient: Address, asset: Address, amount: U256) -> Self {
+        Self {
+            chain_id,
+            tx_hash: tx_hash.to_string(),
+            proof: (recipient, asset, amount).abi_encode_params(),
+        }
+    }
+
+    /// The (recipient, asset, amount) the proof claims.
+    fn claim(&self) -> Result<(Address, Address, U256), String> {
+        <(Address, Address, U256)>::abi_decode_params(&self.proof, true)
+            .map_err(|e| format!("Source proof does not decode as (recipient, asset, amount): {}", e))
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct SourceProofVerification {
+    pub request_id: String,
+    pub chain_id: u64,
+    pub tx_hash: String,
+    pub recipient: String,
+    /// The proof's asset and amount are the request's.
+    pub matches_request: bool,
+    pub found: bool,
+    pub succeeded: bool,
+    /// The transaction paid the claimed amount of the asset to the recipient.
+    pub transfer_found: bool,
+    pub confirmations: u64,
+    pub required_confirmations: u64,
+    pub verified: bool,
+}
+
+/// What the source chain reports about a deposit transaction.
+struct ObservedDeposit {
+    succeeded: bool,
+    block_number: Option<u64>,
+    logs: Vec<Log>,
+    /// Recipient and value of the transaction itself, for native deposits.
+    native_transfer: Option<(Address, U256)>,
+}
+
+/// Decode the stored proof, look its deposit transaction up on the source chain
+/// again, and check that it matches the request, succeeded, paid the claimed
+/// transfer and is buried by at least the chain's `confirmation_blocks`.
+pub async fn verify(request_id: &str) -> Result<SourceProofVerification, String> {
+    let record = request_store::get(request_id)
+        .ok_or_else(|| format!("Request {} not found", request_id))?;
+    let proof = record.source_proof
+        .ok_or_else(|| format!("Request {} has no source proof", request_id))?;
+    let (_, asset, _) = proof.claim()?;
+    let tx_hash = B256::from_str(&proof.tx_hash)
+        .map_err(|e| format!("Invalid source tx hash {}: {}", proof.tx_hash, e))?;
+    let required_confirmations = read_state(|s| {
+        s.chain_configs.get(&proof.chain_id).map(|c| c.confirmation_blocks.max(1))
+    }).ok_or_else(|| format!("Chain {} not configured", proof.chain_id))?;
+
+    let (observed, head) = call_with_fallback(proof.chain_id, |service| async move {
+        let provider = ProviderBuilder::new().on_icp(IcpConfig::new(service));
+        let receipt = provider.get_transaction_receipt(tx_hash).await.map_err(|e| e.to_string())?;
+        let native_transfer = if asset == Address::ZERO && receipt.is_some() {
+            provider.get_transaction_by_hash(tx_hash).await.map_err(|e| e.to_string())?
+                .and_then(|tx| tx.to.map(|to| (to, tx.value)))
+        } else {
+            None
+        };
+        let head = provider.get_block_number().await.map_err(|e| e.to_string())?;
+        let observed = receipt.map(|receipt| ObservedDeposit {
+            succeeded: receipt.status(),
+            block_number: receipt.block_number,
+            logs: receipt.inner.logs().to_vec(),
+            native_transfer,
+        });
+        Ok((observed, head))
+    }).await?;
+
+    assess(request_id, &record.request, &proof, observed.as_ref(), head, required_confirmations)
+}
+
+fn assess(
+    request_id: &str,
+    request: &CrossChainRequest,
+    proof: &SourceProof,
+    observed: Option<&ObservedDeposit>,
+    head: u64,
+    required_confirmations: u64,
+) -> Result<SourceProofVerification, String> {
+    let (recipient, asset, amount) = proof.claim()?;
+    let matches_request = proof.chain_id == request.source_chain_id
+        && Address::from_str(&request.asset_address).ok() == Some(asset)
+        && CrossChainTransactionHandler::parse_amount(&request.amount).ok() == Some(amount);
+
+    let succeeded = observed.is_some_and(|o| o.succeeded);
+    let transfer_found = observed.is_some_and(|o| {
+        if asset == Address::ZERO {
+            o.native_transfer == Some((recipient, amount))
+        } else {
+            transferred_to(&o.logs, asset, recipient) >= amount
+        }
+    });
+    let confirmations = observed
+        .and_then(|o| o.block_number)
+        .map_or(0, |block| head.saturating_sub(block) + 1);
+
+    Ok(SourceProofVerification {
+        request_id: request_id.to_string(),
+        chain_id: proof.chain_id,
+        tx_hash: proof.tx_hash.clone(),
+        recipient: recipient.to_string(),
+        matches_request,
+        found: observed.is_some(),
+        succeeded,
+        transfer_found,
+        confirmations,
+        required_confirmations,
+        verified: matches_request && succeeded && transfer_found && confirmations >= required_confirmations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts::IERC20;
+    use alloy::sol_types::SolEvent;
+
+    fn supply_request(amount: &str) -> CrossChainRequest {
+        CrossChainRequest {
+            user_address: "0x00000000000000000000000000000000000000aa".to_string(),
+            source_chain_id: 97,
+            target_chain_id: 10143,
+            action: crate::cross_chain_transactions::PeridotAction::Supply {
+                underlying_asset: "0x00000000000000000000000000000000000000bb".to_string(),
+            },
+            amount: amount.to_string(),
+            asset_address: "0x00000000000000000000000000000000000000cc".to_string(),
+            max_gas_price: 0,
+            deadline: 0,
+        }
+    }
+
+    const ESCROW: Address = Address::repeat_byte(0xee);
+
+    fn deposit_of(request: &CrossChainRequest, amount: u64) -> (SourceProof, ObservedDeposit) {
+        let asset = Address::from_str(&request.asset_address).unwrap();
+        let proof = SourceProof::deposit(97, B256::repeat_byte(0x11), ESCROW, asset, U256::from(amount));
+        let transfer = Log {
+            inner: alloy::primitives::Log {
+                address: asset,
+                data: IERC20::Transfer { from: Address::repeat_byte(0xaa), to: ESCROW, value: U256::from(amount) }.encode_log_data(),
+            },
+            ..Default::default()
+        };
+        let observed = ObservedDeposit { succeeded: true, block_number: Some(100), logs: vec![transfer], native_transfer: None };
+        (proof, observed)
+    }
+
+    #[test]
+    fn a_buried_matching_deposit_verifies() {
+        let request = supply_request("1000");
+        let (proof, observed) = deposit_of(&request, 1000);
+
+        let verification = assess("ccreq_1", &request, &proof, Some(&observed), 114, 15).unwrap();
+        assert!(verification.matches_request && verification.transfer_found);
+        assert_eq!(verification.confirmations, 15);
+        assert!(verification.verified);
+        assert_eq!(verification.recipient, ESCROW.to_string());
+    }
+
+    #[test]
+    fn mismatched_or_shallow_deposits_do_not_verify() {
+        let request = supply_request("1000");
+
+        // The proof claims less than the request moved
+        let (short, observed) = deposit_of(&request, 999);
+        let verification = assess("ccreq_1", &request, &short, Some(&observed), 200, 15).unwrap();
+        assert!(!verification.matches_request && !verification.verified);
+
+        // The claim matches but the transaction paid someone else
+        let (proof, mut observed) = deposit_of(&request, 1000);
+        observed.logs.clear();
+        let verification = assess("ccreq_1", &request, &proof, Some(&observed), 200, 15).unwrap();
+        assert!(verification.matches_request && !verification.transfer_found && !verification.verified);
+
+        let (proof, observed) = deposit_of(&request, 1000);
+        assert!(!assess("ccreq_1", &request, &proof, Some(&observed), 105, 15).unwrap().verified);
+        assert!(!assess("ccreq_1", &request, &proof, None, 200, 15).unwrap().found);
+
+        let garbage = SourceProof { proof: vec![1, 2, 3], ..proof };
+        assert!(assess("ccreq_1", &request, &garbage, Some(&observed), 200, 15).is_err());
+    }
+}
@@ -1,13 +1,38 @@
-use alloy::primitives::{Address, FixedBytes};
+use alloy::primitives::{keccak256, Address, FixedBytes, B256};
 use alloy::rpc::types::Log;
 use alloy::signers::icp::IcpSigner;
 use alloy::transports::icp::RpcService;
-use candid::{CandidType, Deserialize};
+use crate::bridge::RouteTiming;
+use crate::canister_wallet::NativeBalance;
+use crate::chain_fusion_manager::ChainConfig;
+use crate::emergency_withdraw::EmergencyWithdrawal;
+use crate::enhanced_api::{ArbitrageFilter, RiskThresholds};
+use crate::health_alerts::{HealthAlert, HealthAlertSubscription};
+use crate::gas_price::GasPriceSource;
+use crate::liquidation_approvals::LiquidationProposal;
+use crate::liquidation_events::LiquidationEvent;
+use crate::metrics::TxMetrics;
+use crate::position_limits::PositionLimits;
+use crate::price_oracle::PriceBook;
+use crate::rates::MarketIncentive;
+use crate::request_store::CrossChainRequestRecord;
+use crate::retry_queue::{DeadLetter, RetryEntry};
+use crate::risk_parameters::RiskParameters;
+use crate::token_metadata::TokenMetadata;
+use crate::unrecognized_events::{UnknownEventPolicy, UnrecognizedEvent};
+use candid::{CandidType, Deserialize, Principal};
 use ic_cdk::api::management_canister::ecdsa::EcdsaKeyId;
 use serde::Serialize;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 use std::cell::RefCell;
 
+/// Number of most recent processed log sources kept after compaction.
+pub const DEFAULT_PROCESSED_LOGS_RETENTION: usize = 10_000;
+
+/// Blocks below a chain's sync cursor whose applied log keys are kept, so replays
+/// and reorged blocks within this window are still deduped.
+pub const APPLIED_LOG_WINDOW_BLOCKS: u64 = 10_000;
+
 thread_local! {
     static STATE: RefCell<Option<State>> = RefCell::default();
 }
@@ -16,13 +41,26 @@ thread_local! {
 pub struct UserPosition {
     pub user_address: String,
     pub chain_id: u64,
-    pub p_token_balances: Vec<(String, u64)>,
-    pub borrow_balances: Vec<(String, u64)>,
+    pub p_token_balances: Vec<(String, u128)>,
+    pub borrow_balances: Vec<(String, u128)>,
     pub collateral_enabled: Vec<String>,
     pub health_factor: f64,
     pub total_collateral_value_usd: f64,
     pub total_borrow_value_usd: f64,
     pub account_liquidity: f64,
+    /// True when valuations used a fallback or missing price and are approximate.
+    #[serde(default)]
+    pub price_stale: bool,
+    /// Blocks since the market last accrued interest; borrow values may understate debt.
+    #[serde(default)]
+    pub stale_since_blocks: Option<u64>,
+    /// Market `borrowIndex` at the time each borrow balance was read, keyed like
+    /// `borrow_balances`; debt is grown to the market's current index when valued.
+    #[serde(default)]
+    pub borrow_indexes: Vec<(String, u128)>,
+    /// True when a market's on-chain collateral factor exceeded its cap and the cap was used.
+    #[serde(default)]
+    pub collateral_factor_capped: bool,
     pub updated_at: u64,
 }
 
@@ -31,14 +69,19 @@ pub struct MarketState {
     pub market_address: String,
     pub chain_id: u64,
     pub underlying_symbol: String,
+    pub underlying_decimals: u8,
     pub supply_rate: u64,
     pub borrow_rate: u64,
-    pub total_supply: u64,
-    pub total_borrows: u64,
-    pub cash: u64,
-    pub reserves: u64,
+    pub total_supply: u128,
+    pub total_borrows: u128,
+    pub cash: u128,
+    pub reserves: u128,
     pub collateral_factor: u64,
-    pub exchange_rate: u64,
+    /// `exchangeRateStored`, scaled by 1e18 and the underlying/pToken decimal gap; can exceed u64.
+    pub exchange_rate: u128,
+    /// Latest `borrowIndex`, 1e18-scaled; zero until read.
+    pub borrow_index: u128,
+    pub last_accrual_block: u64,
     pub updated_at: u64,
 }
 
@@ -50,24 +93,86 @@ pub struct State {
     pub filter_events: Vec<String>,
     pub logs_to_process: BTreeMap<LogSource, Log>,
     pub processed_logs: BTreeMap<LogSource, Log>,
+    pub processed_logs_retention: usize,
     pub active_tasks: HashSet<TaskType>,
     pub signer: Option<IcpSigner>,
     pub ecdsa_key_id: EcdsaKeyId,
     pub canister_evm_address: Option<Address>,
-    pub nonce: Option<u64>,
+    pub nonces: BTreeMap<u64, u64>, // chain_id -> next nonce for the canister's EVM account
     pub user_positions: BTreeMap<(String, u64), UserPosition>,
-    pub market_states: BTreeMap<u64, MarketState>,
+    pub market_states: BTreeMap<(u64, String), MarketState>, // (chain_id, market_key) -> market
+    pub authorized_principals: BTreeSet<Principal>,
+    pub liquidation_approval_threshold: u32,
+    pub high_value_liquidation_usd: f64,
+    pub liquidation_proposals: BTreeMap<u64, LiquidationProposal>,
+    pub next_liquidation_proposal_id: u64,
+    pub prices: PriceBook,
+    pub tx_metrics: BTreeMap<(u64, String), TxMetrics>, // (chain_id, action) -> metrics
+    pub chain_configs: BTreeMap<u64, ChainConfig>,
+    pub rpc_urls: BTreeMap<u64, Vec<String>>, // chain_id -> provider URLs in priority order
+    pub risk_parameters: BTreeMap<u64, RiskParameters>, // chain_id -> cached comptroller parameters
+    pub native_balances: BTreeMap<u64, NativeBalance>, // chain_id -> last fetched canister balance
+    pub cross_chain_requests: BTreeMap<String, CrossChainRequestRecord>, // request_id -> record
+    pub liquidation_events: VecDeque<LiquidationEvent>, // oldest first
+    pub mainnet_sources_enabled: bool, // accept Ethereum mainnet as a source chain
+    pub target_confirmations: u64, // blocks to wait after a successful receipt; 0 = none
+    pub max_response_bytes: usize, // serialized size budget for large query responses
+    pub started_at: u64, // seconds; baseline for chains that have never synced
+    pub last_successful_sync: BTreeMap<u64, u64>, // chain_id -> seconds
+    pub sync_degraded: BTreeSet<u64>, // chains stalled past the watchdog threshold
+    pub sync_stall_threshold_secs: u64,
+    pub sync_paused_chains: BTreeSet<u64>, // skipped by sync_all_chains and the watchdog
+    pub notification_webhook: Option<String>,
+    pub token_metadata: BTreeMap<(u64, Address), TokenMetadata>, // (chain_id, market) -> underlying metadata
+    pub head_blocks: BTreeMap<u64, u64>, // chain_id -> sync cursor: highest block whose logs were applied
+    pub events_processed: BTreeMap<u64, u64>, // chain_id -> applied Peridot events
+    pub rpc_failures: BTreeMap<(u64, String), u64>, // (chain_id, provider host) -> failed calls
+    pub retry_queue: BTreeMap<String, RetryEntry>, // request_id -> pending retry
+    pub dead_letters: Vec<DeadLetter>, // requests that exhausted retries or failed permanently
+    pub max_retry_attempts: u32,
+    pub max_block_span: u64, // widest block range requested in one eth_getLogs
+    pub max_price_staleness_secs: u64, // oldest price a liquidation may act on
+    pub liquidation_attempts: BTreeMap<String, u64>, // canonical borrower -> last successful liquidation, seconds
+    pub liquidation_cooldown_secs: u64,
+    pub disabled_actions: BTreeSet<String>, // PeridotAction kinds rejected at dispatch
+    pub rpc_timeout_secs: u64, // per-provider deadline in call_with_fallback
+    pub chain_time_deadlines: bool, // check request deadlines against the target chain's block time
+    pub risk_thresholds: RiskThresholds,
+    pub arbitrage_filter: ArbitrageFilter, // bar for opportunities attached to enhanced positions
+    pub gas_price_sources: BTreeMap<u64, GasPriceSource>, // chains not listed use eth_gasPrice
+    pub request_counter: u64, // last counter embedded in a request id
+    pub position_limits: PositionLimits, // per-user caps checked in validate_request
+    pub paused: bool, // reject every cross-chain request; required for emergency withdrawals
+    pub emergency_withdrawals: Vec<EmergencyWithdrawal>,
+    pub provider_topic_limits: BTreeMap<String, u64>, // provider host -> max topic0 alternatives per filter
+    pub health_alert_subscriptions: BTreeMap<(String, Principal), HealthAlertSubscription>, // (canonical user, subscriber)
+    pub pending_health_alerts: BTreeMap<Principal, Vec<HealthAlert>>, // drained by poll_alerts
+    pub cycle_floor: u128, // expensive updates are declined below this balance
+    pub collateral_factor_caps: BTreeMap<String, f64>, // lowercase market address -> max collateral factor fraction
+    pub default_source_chain: Option<u64>, // used when an execute call omits source_chain_id
+    pub market_incentives: BTreeMap<String, MarketIncentive>, // lowercase market address -> reward APYs
+    pub max_concurrent_executions: u64,
+    pub queue_busy_executions: bool, // park excess executions in the retry queue instead of rejecting
+    pub in_flight_executions: u64, // held by ExecutionGuard; not persisted
+    pub native_token_prices: BTreeMap<String, f64>, // uppercase gas token symbol -> USD
+    pub observed_gas_prices: BTreeMap<u64, u128>, // last gas price (wei) read per chain; not persisted
+    pub unknown_event_policy: UnknownEventPolicy,
+    pub unrecognized_events: Vec<UnrecognizedEvent>, // kept under StoreRaw, oldest first
+    pub route_timings: BTreeMap<(u64, u64), RouteTiming>, // (source chain, target chain) -> leg durations
+    pub applied_logs: BTreeSet<AppliedLogKey>, // logs applied by the multi-chain pipeline, within APPLIED_LOG_WINDOW_BLOCKS of the cursor
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum InvalidStateError {
     InvalidEthereumContractAddress(String),
+    InvalidRpcService(String),
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub enum TaskType {
     ProcessLogs,
     ScrapeLogs,
+    SyncChains,
 }
 
 impl State {
@@ -93,6 +198,90 @@ impl State {
             None,
             "attempted to run job twice for the same event {source:?}"
         );
+
+        // Compact in batches once the map grows 20% past the retention limit
+        let auto_compact_threshold = self.processed_logs_retention + self.processed_logs_retention / 5;
+        if self.processed_logs.len() > auto_compact_threshold {
+            self.compact_processed_logs();
+        }
+    }
+
+    /// Drop the oldest processed log sources (by block number) beyond the retention
+    /// limit and return how many were removed. `logs_to_process` holds pending work
+    /// and is never compacted.
+    pub fn compact_processed_logs(&mut self) -> usize {
+        let excess = self.processed_logs.len().saturating_sub(self.processed_logs_retention);
+        if excess == 0 {
+            return 0;
+        }
+
+        let mut by_age: Vec<(u64, LogSource)> = self
+            .processed_logs
+            .iter()
+            .map(|(source, log)| (log.block_number.unwrap_or_default(), source.clone()))
+            .collect();
+        by_age.sort();
+
+        for (_, source) in by_age.into_iter().take(excess) {
+            self.processed_logs.remove(&source);
+        }
+        excess
+    }
+
+    pub fn has_applied_log(&self, chain_id: u64, log: &Log) -> bool {
+        self.applied_logs.contains(&applied_log_key(chain_id, log))
+    }
+
+    pub fn record_applied_log(&mut self, chain_id: u64, log: &Log) {
+        self.applied_logs.insert(applied_log_key(chain_id, log));
+    }
+
+    /// Advance `chain_id`'s sync cursor to `block`; the cursor never moves back.
+    /// Applied log keys that fall out of the replay window are dropped.
+    pub fn record_synced_block(&mut self, chain_id: u64, block: u64) {
+        let cursor = self.head_blocks.entry(chain_id).or_insert(block);
+        *cursor = (*cursor).max(block);
+        let horizon = cursor.saturating_sub(APPLIED_LOG_WINDOW_BLOCKS);
+
+        let expired: Vec<AppliedLogKey> = self.applied_logs
+            .range((chain_id, 0, String::new(), 0)..(chain_id, horizon, String::new(), 0))
+            .cloned()
+            .collect();
+        for key in expired {
+            self.applied_logs.remove(&key);
+        }
+    }
+
+    /// Lowest block of `chain_id` that can still be replayed: logs below it were
+    /// applied before their keys left the dedupe window, so replaying them would
+    /// apply them twice.
+    pub fn replay_horizon(&self, chain_id: u64) -> u64 {
+        self.head_blocks.get(&chain_id)
+            .map_or(0, |cursor| cursor.saturating_sub(APPLIED_LOG_WINDOW_BLOCKS))
+    }
+
+    /// Block to resume scanning `chain_id` from: the sync cursor, or the chain's
+    /// configured genesis block before anything was synced. `None` when neither
+    /// is known.
+    pub fn sync_cursor(&self, chain_id: u64) -> Option<u64> {
+        self.head_blocks.get(&chain_id).copied().or_else(|| {
+            self.chain_configs.get(&chain_id).and_then(|config| config.genesis_sync_block)
+        })
+    }
+
+    /// Markets tracked on one chain, in address order.
+    pub fn chain_markets(&self, chain_id: u64) -> impl Iterator<Item = &MarketState> {
+        self.market_states
+            .range((chain_id, String::new())..)
+            .take_while(move |((market_chain_id, _), _)| *market_chain_id == chain_id)
+            .map(|(_, market)| market)
+    }
+
+    /// The chain's most recently updated market. Before markets were tracked
+    /// individually this was the chain's only market slot, so per-chain views
+    /// that predate per-market tracking report it.
+    pub fn latest_market(&self, chain_id: u64) -> Option<&MarketState> {
+        self.chain_markets(chain_id).max_by_key(|market| market.updated_at)
     }
 
     pub fn has_logs_to_process(&self) -> bool {
@@ -103,12 +292,32 @@ impl State {
         self.ecdsa_key_id.clone()
     }
 
-    pub fn get_filter_addresses(&self) -> Vec<Address> {
-        self.filter_addresses.clone()
+    /// Contracts whose logs are fetched and applied on a chain: the chain's Peridot
+    /// deployment and controller plus the market addresses configured at init.
+    /// This is the only place the set is assembled.
+    pub fn monitored_contracts(&self, chain_id: u64) -> Result<Vec<Address>, String> {
+        let config = self.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+
+        let mut contracts = self.filter_addresses.clone();
+        contracts.push(*config.peridot_contract);
+        contracts.push(config.controller_address()?);
+        contracts.sort();
+        contracts.dedup();
+        Ok(contracts)
     }
 
-    pub fn get_filter_events(&self) -> Vec<String> {
-        self.filter_events.clone()
+    /// topic0 hashes the log scraper subscribes to on a chain: the chain's
+    /// monitored events plus any event signatures configured at init.
+    pub fn filter_topics(&self, chain_id: u64) -> Result<Vec<B256>, String> {
+        let config = self.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))?;
+
+        let mut topics = config.monitored_topics()?;
+        topics.extend(self.filter_events.iter().map(|signature| keccak256(signature.as_bytes())));
+        topics.sort();
+        topics.dedup();
+        Ok(topics)
     }
 }
 
@@ -137,6 +346,25 @@ pub struct LogSource {
     pub log_index: u64,
 }
 
+/// Durable identity of an applied log: chain, block, lowercase transaction hash and
+/// log index. Unlike `processed_logs`, these keys are persisted with the positions
+/// they built, and are only dropped once they fall out of the replay window.
+pub type AppliedLogKey = (u64, u64, String, u64);
+
+pub fn applied_log_key(chain_id: u64, log: &Log) -> AppliedLogKey {
+    (
+        chain_id,
+        log.block_number.unwrap_or_default(),
+        log.transaction_hash.unwrap_or_default().to_string().to_lowercase(),
+        log.log_index.unwrap_or_default(),
+    )
+}
+
+/// Key of a market in `State::market_states`; addresses compare case-insensitively.
+pub fn market_key(chain_id: u64, market: &str) -> (u64, String) {
+    (chain_id, market.to_lowercase())
+}
+
 pub fn initialize_state(state: State) {
     STATE.with(|s| {
         *s.borrow_mut() = Some(state);
@@ -155,4 +383,161 @@ where
     F: FnOnce(&mut State) -> R,
 {
     STATE.with(|s| f(s.borrow_mut().as_mut().expect("BUG: state is not initialized")))
-} 
\ No newline at end of file
+}
+
+/// Initialize this thread's state from a default Monad testnet `InitArg`.
+#[cfg(test)]
+pub fn init_test_state() {
+    use crate::lifecycle::InitArg;
+    use ic_cdk::api::management_canister::ecdsa::EcdsaCurve;
+
+    let arg = InitArg {
+        rpc_service: RpcService::Chain(10143),
+        chain_id: 10143,
+        filter_addresses: Vec::new(),
+        filter_events: Vec::new(),
+        ecdsa_key_id: EcdsaKeyId { curve: EcdsaCurve::Secp256k1, name: "test_key".to_string() },
+    };
+    initialize_state(State::try_from(arg).expect("test InitArg is valid"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_at(block: u64) -> Log {
+        Log {
+            block_number: Some(block),
+            transaction_hash: Some(FixedBytes::with_last_byte(block as u8)),
+            log_index: Some(0),
+            ..Default::default()
+        }
+    }
+
+    fn process(state: &mut State, block: u64) {
+        let log = log_at(block);
+        state.record_log_to_process(&log);
+        state.record_processed_log(log.source());
+    }
+
+    #[test]
+    fn compaction_keeps_the_newest_processed_logs() {
+        init_test_state();
+        mutate_state(|s| {
+            s.processed_logs_retention = 5;
+            for block in 1..=6 {
+                process(s, block);
+            }
+            // 6 entries is within the 20% auto-compaction slack over a retention of 5
+            assert_eq!(s.processed_logs.len(), 6);
+
+            assert_eq!(s.compact_processed_logs(), 1);
+            assert!(!s.processed_logs.contains_key(&log_at(1).source()));
+            assert!(s.processed_logs.contains_key(&log_at(6).source()));
+            assert_eq!(s.compact_processed_logs(), 0);
+        });
+    }
+
+    #[test]
+    fn applied_logs_survive_compaction_and_upgrades() {
+        init_test_state();
+        mutate_state(|s| {
+            s.processed_logs_retention = 1;
+            for block in 1..=3 {
+                process(s, block);
+                s.record_applied_log(10143, &log_at(block));
+            }
+            s.compact_processed_logs();
+            assert!(!s.processed_logs.contains_key(&log_at(1).source()));
+            assert!(s.has_applied_log(10143, &log_at(1)));
+            // The same transaction and index on another chain is a different log
+            assert!(!s.has_applied_log(97, &log_at(1)));
+        });
+
+        let persisted = read_state(crate::lifecycle::PersistedState::capture);
+        init_test_state();
+        mutate_state(|s| persisted.restore(s));
+        assert!(read_state(|s| (1..=3).all(|block| s.has_applied_log(10143, &log_at(block)))));
+    }
+
+    #[test]
+    fn applied_logs_below_the_replay_window_are_pruned() {
+        init_test_state();
+        mutate_state(|s| {
+            for block in [1, 5] {
+                s.record_applied_log(10143, &log_at(block));
+            }
+            s.record_applied_log(97, &log_at(1));
+            assert_eq!(s.replay_horizon(10143), 0);
+
+            s.record_synced_block(10143, APPLIED_LOG_WINDOW_BLOCKS + 5);
+            assert_eq!(s.replay_horizon(10143), 5);
+            assert!(!s.has_applied_log(10143, &log_at(1)));
+            assert!(s.has_applied_log(10143, &log_at(5)));
+            // Other chains keep their keys until their own cursor moves
+            assert!(s.has_applied_log(97, &log_at(1)));
+        });
+    }
+
+    #[test]
+    fn processed_logs_compact_automatically_past_the_slack() {
+        init_test_state();
+        mutate_state(|s| {
+            s.processed_logs_retention = 5;
+            for block in 1..=7 {
+                process(s, block);
+            }
+            assert_eq!(s.processed_logs.len(), 5);
+            assert!(s.processed_logs.contains_key(&log_at(3).source()));
+            assert!(!s.has_logs_to_process());
+        });
+    }
+
+    #[test]
+    fn monitored_contracts_follow_added_chains_and_contract_updates() {
+        use crate::chain_fusion_manager::{insert_chain_config, ContractAddress};
+
+        init_test_state();
+        let market = Address::repeat_byte(0x0f);
+        let peridot = Address::repeat_byte(0x01);
+        let controller = Address::repeat_byte(0x02);
+        mutate_state(|s| {
+            s.filter_addresses.push(market);
+            let mut config = s.chain_configs[&10143].clone();
+            config.chain_id = 143;
+            config.name = "Monad".to_string();
+            config.peridot_contract = ContractAddress(peridot);
+            config.controller = controller.to_string();
+            insert_chain_config(&mut s.chain_configs, config).unwrap();
+        });
+
+        assert_eq!(read_state(|s| s.monitored_contracts(143)), Ok(vec![peridot, controller, market]));
+
+        // Pointing the chain at a new controller swaps it in the monitored set
+        let new_controller = Address::repeat_byte(0x03);
+        mutate_state(|s| s.chain_configs.get_mut(&143).unwrap().controller = new_controller.to_string());
+        let contracts = read_state(|s| s.monitored_contracts(143)).unwrap();
+        assert!(contracts.contains(&new_controller) && !contracts.contains(&controller));
+        assert!(read_state(|s| s.monitored_contracts(1)).is_err());
+    }
+
+    #[test]
+    fn scrape_filter_covers_controller_and_market_events() {
+        use crate::PeridotEvents;
+        use alloy::sol_types::SolEvent;
+
+        init_test_state();
+        read_state(|s| {
+            let controller = s.chain_configs[&10143].controller_address().unwrap();
+            assert!(s.monitored_contracts(10143).unwrap().contains(&controller));
+
+            let topics = s.filter_topics(10143).unwrap();
+            assert!(topics.contains(&PeridotEvents::Mint::SIGNATURE_HASH));
+            assert!(topics.contains(&PeridotEvents::AccrueInterest::SIGNATURE_HASH));
+            assert!(topics.contains(&PeridotEvents::NewCollateralFactor::SIGNATURE_HASH));
+            assert!(topics.contains(&PeridotEvents::MarketEntered::SIGNATURE_HASH));
+            assert!(topics.contains(&PeridotEvents::MarketExited::SIGNATURE_HASH));
+            assert!(s.filter_topics(1).is_err());
+        });
+    }
+}
@@ -0,0 +1,46 @@
+// Helpers shared by unit tests; compiled only under `cfg(test)`.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+thread_local! {
+    static NOW: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Seconds reported as the current time to code that would read the canister
+/// clock. Starts at zero for every test thread.
+pub fn now() -> u64 {
+    NOW.with(Cell::get)
+}
+
+pub fn set_now(seconds: u64) {
+    NOW.with(|now| now.set(seconds));
+}
+
+fn noop_raw_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker {
+        noop_raw_waker()
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(std::ptr::null(), &VTABLE)
+}
+
+/// A waker that does nothing, for tests that poll futures by hand.
+pub fn noop_waker() -> Waker {
+    // SAFETY: the vtable functions ignore the data pointer.
+    unsafe { Waker::from_raw(noop_raw_waker()) }
+}
+
+/// Poll `future` once and return its output. The futures under test never make
+/// real outcalls, so anything still pending after one poll is a test failure.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = noop_waker();
+    let mut context = Context::from_waker(&waker);
+    match pin!(future).poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future did not complete without outcalls"),
+    }
+}
@@ -0,0 +1,262 @@
+use crate::contracts::{IERC20, IPToken, IPeridotController};
+use crate::cross_chain_transactions::CrossChainTransactionHandler;
+use crate::logger;
+use crate::state::{mutate_state, read_state, MarketState, State};
+use alloy::primitives::Address;
+use alloy::providers::ProviderBuilder;
+use alloy::transports::icp::IcpConfig;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+use std::str::FromStr;
+
+pub const UNKNOWN_SYMBOL: &str = "UNKNOWN";
+pub const DEFAULT_DECIMALS: u8 = 18;
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct TokenMetadata {
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(default)]
+    pub underlying_address: Option<String>,
+}
+
+impl Default for TokenMetadata {
+    fn default() -> Self {
+        Self {
+            symbol: UNKNOWN_SYMBOL.to_string(),
+            decimals: DEFAULT_DECIMALS,
+            underlying_address: None,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct PTokenUnderlying {
+    pub chain_id: u64,
+    pub ptoken_address: String,
+    pub underlying_address: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// Symbol and decimals of a market's underlying token. Successful lookups are
+/// cached per market; failures fall back to `UNKNOWN`/18 and are retried on the
+/// next call.
+pub async fn market_underlying_metadata(chain_id: u64, market: Address) -> TokenMetadata {
+    let key = (chain_id, market);
+    if let Some(cached) = read_state(|s| s.token_metadata.get(&key).cloned()) {
+        return cached;
+    }
+
+    match fetch_underlying_metadata(chain_id, market).await {
+        Ok(metadata) => {
+            mutate_state(|s| s.token_metadata.insert(key, metadata.clone()));
+            metadata
+        }
+        Err(e) => {
+            logger::log(format!("Token metadata unavailable for market {} on chain {}: {}", market, chain_id, e));
+            TokenMetadata::default()
+        }
+    }
+}
+
+/// Record the underlying token's symbol and decimals on a market's state.
+pub fn apply_metadata(market: &mut MarketState, metadata: TokenMetadata) {
+    market.underlying_symbol = metadata.symbol;
+    market.underlying_decimals = metadata.decimals;
+}
+
+/// Underlying token of a pToken market, from the metadata cache or a live
+/// `underlying()` lookup that is then cached. Unlike `market_underlying_metadata`,
+/// a failed lookup is reported instead of defaulted.
+pub async fn ptoken_underlying(chain_id: u64, market: Address) -> Result<PTokenUnderlying, String> {
+    let key = (chain_id, market);
+    let metadata = match read_state(|s| s.token_metadata.get(&key).cloned()) {
+        Some(cached) => cached,
+        None => {
+            let metadata = fetch_underlying_metadata(chain_id, market).await?;
+            mutate_state(|s| s.token_metadata.insert(key, metadata.clone()));
+            metadata
+        }
+    };
+
+    Ok(PTokenUnderlying {
+        chain_id,
+        ptoken_address: market.to_string(),
+        underlying_address: metadata.underlying_address
+            .ok_or_else(|| format!("Underlying address of market {} not known", market))?,
+        symbol: metadata.symbol,
+        decimals: metadata.decimals,
+    })
+}
+
+/// pToken market on `chain_id` whose underlying token is `underlying`. Markets are
+/// matched from the metadata cache; on a miss, every market listed by the chain's
+/// controller that is not cached yet has its underlying looked up and cached.
+pub async fn market_for_underlying(chain_id: u64, underlying: Address) -> Result<Address, String> {
+    if let Some(market) = read_state(|s| cached_market_for(s, chain_id, underlying)) {
+        return Ok(market);
+    }
+
+    let controller = read_state(|s| {
+        s.chain_configs.get(&chain_id)
+            .ok_or_else(|| format!("Chain {} not configured", chain_id))
+            .and_then(|c| c.controller_address())
+    })?;
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+    let markets = IPeridotController::new(controller, provider).getAllMarkets().call().await
+        .map_err(|e| format!("getAllMarkets failed: {}", e))?._0;
+
+    for market in markets {
+        if read_state(|s| s.token_metadata.contains_key(&(chain_id, market))) {
+            continue;
+        }
+        match fetch_underlying_metadata(chain_id, market).await {
+            Ok(metadata) => {
+                mutate_state(|s| s.token_metadata.insert((chain_id, market), metadata));
+            }
+            Err(e) => logger::log(format!("Token metadata unavailable for market {} on chain {}: {}", market, chain_id, e)),
+        }
+    }
+
+    read_state(|s| cached_market_for(s, chain_id, underlying))
+        .ok_or_else(|| format!("No market for underlying {} on chain {}", underlying, chain_id))
+}
+
+/// Decimals of a market's underlying token, from the metadata cache or the
+/// market's stored state. `None` while neither has loaded, so amounts are never
+/// scaled by a guessed precision.
+pub fn cached_decimals(state: &State, chain_id: u64, market: &str) -> Option<u8> {
+    let cached = Address::from_str(market).ok()
+        .and_then(|address| state.token_metadata.get(&(chain_id, address)))
+        .map(|metadata| metadata.decimals);
+    cached.or_else(|| {
+        state.market_states.get(&crate::state::market_key(chain_id, market))
+            .filter(|m| !m.underlying_symbol.is_empty())
+            .map(|m| m.underlying_decimals)
+    })
+}
+
+pub fn cached_market_for(state: &State, chain_id: u64, underlying: Address) -> Option<Address> {
+    state.token_metadata.iter()
+        .filter(|((market_chain_id, _), _)| *market_chain_id == chain_id)
+        .find(|(_, metadata)| {
+            metadata.underlying_address.as_deref()
+                .and_then(|address| Address::from_str(address).ok()) == Some(underlying)
+        })
+        .map(|((_, market), _)| *market)
+}
+
+/// Metadata of a native-token market such as pMON, which has no `underlying()`
+/// token. The zero address stands in for the native asset, so
+/// `market_for_underlying(chain_id, Address::ZERO)` resolves to the native market.
+fn native_market_metadata(state: &State, chain_id: u64) -> TokenMetadata {
+    TokenMetadata {
+        symbol: state.chain_configs.get(&chain_id)
+            .map_or(UNKNOWN_SYMBOL, |config| config.native_symbol())
+            .to_string(),
+        decimals: DEFAULT_DECIMALS,
+        underlying_address: Some(Address::ZERO.to_string()),
+    }
+}
+
+/// Whether a failed `underlying()` call was answered by the node (a revert or
+/// empty return data), meaning the market has no underlying token, rather than
+/// lost in transport.
+fn market_has_no_underlying(error: &alloy::contract::Error) -> bool {
+    match error {
+        alloy::contract::Error::TransportError(e) => e.is_error_resp(),
+        _ => true,
+    }
+}
+
+async fn fetch_underlying_metadata(chain_id: u64, market: Address) -> Result<TokenMetadata, String> {
+    let rpc_service = CrossChainTransactionHandler::get_rpc_service_for_chain(chain_id)?;
+    let provider = ProviderBuilder::new().on_icp(IcpConfig::new(rpc_service));
+
+    let underlying = match IPToken::new(market, provider.clone()).underlying().call().await {
+        Ok(result) => result._0,
+        Err(e) if market_has_no_underlying(&e) => {
+            return Ok(read_state(|s| native_market_metadata(s, chain_id)));
+        }
+        Err(e) => return Err(format!("underlying failed: {}", e)),
+    };
+    let token = IERC20::new(underlying, provider);
+    let symbol = token.symbol().call().await
+        .map_err(|e| format!("symbol failed: {}", e))?._0;
+    let decimals = token.decimals().call().await
+        .map_err(|e| format!("decimals failed: {}", e))?._0;
+
+    Ok(TokenMetadata { symbol, decimals, underlying_address: Some(underlying.to_string()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::new_market_state;
+    use crate::state::init_test_state;
+    use crate::test_support::block_on;
+
+    #[test]
+    fn markets_record_the_underlying_symbol_and_decimals() {
+        init_test_state();
+        let market = Address::repeat_byte(0x01);
+        let usdc = TokenMetadata {
+            symbol: "USDC".to_string(),
+            decimals: 6,
+            underlying_address: Some(Address::repeat_byte(0x02).to_string()),
+        };
+        mutate_state(|s| s.token_metadata.insert((10143, market), usdc));
+
+        let mut state = new_market_state(10143, market);
+        apply_metadata(&mut state, block_on(market_underlying_metadata(10143, market)));
+        assert_eq!((state.underlying_symbol.as_str(), state.underlying_decimals), ("USDC", 6));
+        assert_eq!(read_state(|s| cached_market_for(s, 10143, Address::repeat_byte(0x02))), Some(market));
+    }
+
+    #[test]
+    fn native_markets_resolve_from_the_zero_address() {
+        init_test_state();
+        let erc20_market = Address::repeat_byte(0x01);
+        let native_market = Address::repeat_byte(0x03);
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, erc20_market), TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some(Address::repeat_byte(0x02).to_string()),
+            });
+            let native = native_market_metadata(s, 10143);
+            s.token_metadata.insert((10143, native_market), native);
+        });
+
+        let native = read_state(|s| s.token_metadata[&(10143, native_market)].clone());
+        assert_eq!((native.symbol.as_str(), native.decimals), ("MON", 18));
+        assert_eq!(block_on(market_for_underlying(10143, Address::ZERO)), Ok(native_market));
+    }
+
+    #[test]
+    fn ptoken_underlying_resolves_from_the_cache() {
+        init_test_state();
+        let market = Address::repeat_byte(0x01);
+        let usdc = Address::repeat_byte(0x02);
+        let unresolved = Address::repeat_byte(0x04);
+        mutate_state(|s| {
+            s.token_metadata.insert((10143, market), TokenMetadata {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                underlying_address: Some(usdc.to_string()),
+            });
+            s.token_metadata.insert((10143, unresolved), TokenMetadata::default());
+        });
+
+        let underlying = block_on(ptoken_underlying(10143, market)).unwrap();
+        assert_eq!(underlying.ptoken_address, market.to_string());
+        assert_eq!(underlying.underlying_address, usdc.to_string());
+        assert_eq!((underlying.symbol.as_str(), underlying.decimals, underlying.chain_id), ("USDC", 6, 10143));
+
+        // A cached entry without an underlying address is reported, not defaulted
+        let error = block_on(ptoken_underlying(10143, unresolved)).unwrap_err();
+        assert!(error.contains("not known"), "{}", error);
+    }
+}
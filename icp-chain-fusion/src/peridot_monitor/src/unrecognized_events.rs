@@ -0,0 +1,130 @@
+use crate::logger;
+use crate::state::{mutate_state, read_state};
+use alloy::rpc::types::Log;
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+/// Unrecognized logs kept under `StoreRaw`; the oldest are dropped first.
+pub const MAX_UNRECOGNIZED_EVENTS: usize = 1_000;
+
+/// What happens to a log from a monitored contract that no event handler recognizes.
+#[derive(CandidType, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum UnknownEventPolicy {
+    Ignore,
+    #[default]
+    LogAndIgnore,
+    /// Log it and keep the raw log for `get_unrecognized_events`.
+    StoreRaw,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
+pub struct UnrecognizedEvent {
+    pub chain_id: u64,
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String, // 0x-prefixed hex
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub seen_at: u64, // seconds
+}
+
+/// Apply the configured policy to a log no handler recognized.
+pub fn handle(chain_id: u64, log: &Log) {
+    let policy = read_state(|s| s.unknown_event_policy);
+    if policy == UnknownEventPolicy::Ignore {
+        return;
+    }
+
+    logger::log(format!(
+        "Ignoring unrecognised event {} from {} on chain {}",
+        log.topics().first().map(|topic| topic.to_string()).unwrap_or_else(|| "(no topics)".to_string()),
+        log.address(),
+        chain_id
+    ));
+    if policy == UnknownEventPolicy::StoreRaw {
+        let event = UnrecognizedEvent {
+            chain_id,
+            address: log.address().to_string(),
+            topics: log.topics().iter().map(|topic| topic.to_string()).collect(),
+            data: log.data().data.to_string(),
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash.map(|hash| hash.to_string()),
+            log_index: log.log_index,
+            seen_at: current_timestamp(),
+        };
+        mutate_state(|s| {
+            s.unrecognized_events.push(event);
+            if s.unrecognized_events.len() > MAX_UNRECOGNIZED_EVENTS {
+                s.unrecognized_events.remove(0);
+            }
+        });
+    }
+}
+
+#[cfg(not(test))]
+fn current_timestamp() -> u64 {
+    ic_cdk::api::time() / 1_000_000_000
+}
+
+// Unit tests run outside a canister, where the time system call is unavailable
+#[cfg(test)]
+fn current_timestamp() -> u64 {
+    crate::test_support::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+    use alloy::primitives::{Address, Bytes, LogData, B256};
+
+    fn unknown_log(log_index: u64) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(0x01),
+                data: LogData::new_unchecked(vec![B256::repeat_byte(0x99)], Bytes::from(vec![0xab, 0xcd])),
+            },
+            block_number: Some(42),
+            transaction_hash: Some(B256::repeat_byte(0x11)),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn only_store_raw_keeps_unrecognized_logs() {
+        init_test_state();
+        crate::test_support::set_now(1_700);
+        for policy in [UnknownEventPolicy::Ignore, UnknownEventPolicy::LogAndIgnore] {
+            mutate_state(|s| s.unknown_event_policy = policy);
+            handle(10143, &unknown_log(0));
+            assert!(read_state(|s| s.unrecognized_events.is_empty()), "{:?} stored a log", policy);
+        }
+
+        mutate_state(|s| s.unknown_event_policy = UnknownEventPolicy::StoreRaw);
+        handle(10143, &unknown_log(3));
+        let stored = read_state(|s| s.unrecognized_events.clone());
+        assert_eq!(stored.len(), 1);
+        let event = &stored[0];
+        assert_eq!((event.chain_id, event.address.as_str()), (10143, Address::repeat_byte(0x01).to_string().as_str()));
+        assert_eq!(event.topics, vec![B256::repeat_byte(0x99).to_string()]);
+        assert_eq!(event.data, "0xabcd");
+        assert_eq!((event.block_number, event.log_index, event.seen_at), (Some(42), Some(3), 1_700));
+        assert_eq!(event.transaction_hash, Some(B256::repeat_byte(0x11).to_string()));
+    }
+
+    #[test]
+    fn stored_logs_drop_the_oldest_past_the_cap() {
+        init_test_state();
+        mutate_state(|s| s.unknown_event_policy = UnknownEventPolicy::StoreRaw);
+        for log_index in 0..=MAX_UNRECOGNIZED_EVENTS as u64 {
+            handle(10143, &unknown_log(log_index));
+        }
+
+        let stored = read_state(|s| s.unrecognized_events.clone());
+        assert_eq!(stored.len(), MAX_UNRECOGNIZED_EVENTS);
+        assert_eq!(stored.first().and_then(|e| e.log_index), Some(1));
+        assert_eq!(stored.last().and_then(|e| e.log_index), Some(MAX_UNRECOGNIZED_EVENTS as u64));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::notifications;
+use crate::state::{mutate_state, read_state};
+use std::time::Duration;
+
+/// How often sync freshness is checked.
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+/// Default time without a successful sync before a chain is marked degraded.
+pub const DEFAULT_SYNC_STALL_THRESHOLD_SECS: u64 = 900;
+
+/// Record a successful sync for `chain_id`, clearing any degraded mark.
+pub fn record_sync_success(chain_id: u64) {
+    record_sync_success_at(chain_id, ic_cdk::api::time() / 1_000_000_000);
+}
+
+pub fn record_sync_success_at(chain_id: u64, now: u64) {
+    mutate_state(|s| {
+        s.last_successful_sync.insert(chain_id, now);
+        s.sync_degraded.remove(&chain_id);
+    });
+}
+
+/// Chains whose last successful sync (or canister start, if never synced) is
+/// older than the threshold at `now`, with how long each has been stalled.
+pub fn stalled_chains(now: u64) -> Vec<(u64, u64)> {
+    read_state(|s| {
+        s.chain_configs
+            .keys()
+            .filter(|chain_id| !s.sync_paused_chains.contains(chain_id))
+            .filter_map(|chain_id| {
+                let last = s.last_successful_sync.get(chain_id).copied().unwrap_or(s.started_at);
+                let stalled_for = now.saturating_sub(last);
+                (stalled_for > s.sync_stall_threshold_secs).then_some((*chain_id, stalled_for))
+            })
+            .collect()
+    })
+}
+
+/// Mark chains stalled at `now` as degraded, returning only those that were not
+/// already degraded so each stall is alerted once.
+pub fn mark_degraded(now: u64) -> Vec<(u64, u64)> {
+    stalled_chains(now)
+        .into_iter()
+        .filter(|(chain_id, _)| mutate_state(|s| s.sync_degraded.insert(*chain_id)))
+        .collect()
+}
+
+/// Webhook idempotency key for a stall, fixed by the chain and when the stall
+/// began so every delivery of the same alert carries the same key.
+pub fn degraded_alert_key(chain_id: u64, stalled_since: u64) -> String {
+    format!("sync_degraded:{}:{}", chain_id, stalled_since)
+}
+
+/// Mark newly stalled chains as degraded and alert once per transition.
+pub async fn check_sync_health() {
+    let now = ic_cdk::api::time() / 1_000_000_000;
+    for (chain_id, stalled_for) in mark_degraded(now) {
+        let message = format!("Chain {} has not synced successfully for {}s", chain_id, stalled_for);
+        crate::logger::log(&message);
+        let key = degraded_alert_key(chain_id, now.saturating_sub(stalled_for));
+        notifications::notify("sync_degraded", &key, &message).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::init_test_state;
+
+    #[test]
+    fn successful_log_polls_clear_a_stall() {
+        init_test_state();
+        let (started_at, threshold) = read_state(|s| (s.started_at, s.sync_stall_threshold_secs));
+        let now = started_at + threshold + 1;
+
+        let degraded = mark_degraded(now);
+        assert!(degraded.iter().any(|(chain_id, stalled_for)| *chain_id == 10143 && *stalled_for == threshold + 1));
+        // Already degraded chains are not alerted again
+        assert!(mark_degraded(now).is_empty());
+
+        record_sync_success_at(10143, now);
+        assert!(!read_state(|s| s.sync_degraded.contains(&10143)));
+        assert!(stalled_chains(now).iter().all(|(chain_id, _)| *chain_id != 10143));
+        assert_eq!(degraded_alert_key(10143, started_at), degraded_alert_key(10143, now - (threshold + 1)));
+    }
+}